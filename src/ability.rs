@@ -0,0 +1,182 @@
+use crate::fetcher::Fetcher;
+use crate::image::ImageCache;
+use crate::index::{AbilityId, DexId, Index};
+use crate::page::{
+    get_attr, is_element, is_tag, outer_xhtml, read_sections, Page, Reference, SectionHtml,
+};
+use crate::Config;
+use anyhow::{anyhow, Context};
+use html5ever::tendril::TendrilSink;
+use kuchikiki::NodeRef;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AbilityEntry {
+    /// Page URL
+    pub url: String,
+
+    pub id: AbilityId,
+    pub name: String,
+    pub name_jp_text: Option<String>,
+    pub name_jp_html: Option<String>,
+
+    /// Summary HTML contents
+    pub summary_html: String,
+    /// Effect descriptions, usually one per generation
+    pub effects: Vec<SectionHtml>,
+    /// Pokémon that can have this ability
+    pub pokemon: Vec<AbilityMon>,
+    /// Footnotes cited in the summary or effects, in citation order
+    pub references: Vec<Reference>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AbilityMon {
+    pub dex_id: DexId,
+    /// Form name, if only some forms have this ability
+    pub form: Option<String>,
+    /// Whether this is the Pokémon's Hidden Ability
+    pub hidden: bool,
+}
+
+pub fn read_ability(
+    fetcher: &Fetcher,
+    index: &Index,
+    image_cache: &ImageCache,
+    config: &Config,
+    id: AbilityId,
+    url: &str,
+) -> anyhow::Result<AbilityEntry> {
+    let html = String::from_utf8(fetcher.get(url, true)?)?;
+    let doc = kuchikiki::parse_html().one(html);
+    let mut page = Page::new(fetcher, index, image_cache, config, url, &doc);
+
+    let title = doc
+        .select_first("#firstHeading")
+        .map_err(|()| anyhow!("could not find page title"))?
+        .text_contents();
+    let name = title
+        .trim()
+        .trim_end_matches("(Ability)")
+        .trim()
+        .to_string();
+
+    let name_jp = doc
+        .select_first(".mw-parser-output table.roundy [lang='ja']")
+        .ok();
+    let name_jp_text = name_jp
+        .as_ref()
+        .map(|node| node.text_contents().trim().to_string());
+    let name_jp_html = name_jp.as_ref().map(|node| outer_xhtml(node.as_node()));
+
+    let mw_parser_output = doc
+        .select_first(".mw-parser-output")
+        .map_err(|()| anyhow!("no mw-parser-output"))?;
+
+    let (intro, sections) = read_sections(mw_parser_output.as_node());
+
+    let mut summary_html = String::new();
+    for node in intro {
+        if is_tag(&node, "p") {
+            page.fix_links(&node)
+                .context("error fixing summary links")?;
+            summary_html += &outer_xhtml(&node);
+        }
+    }
+
+    let mut effects = Vec::new();
+    let mut pokemon = Vec::new();
+    for section in &sections {
+        if section.title == "Effect" {
+            effects = page.section_html(section)?;
+        } else if section.title.starts_with("Pokémon with") {
+            for node in section.all_nodes() {
+                if node.select_first("a[href$='_(Pok%C3%A9mon)']").is_ok() {
+                    read_pokemon_table(&page, &name, node, &mut pokemon)
+                        .context("error reading Pokémon table")?;
+                }
+            }
+        }
+    }
+
+    let references = page.references()?;
+
+    Ok(AbilityEntry {
+        url: url.to_string(),
+        id,
+        name,
+        name_jp_text,
+        name_jp_html,
+        summary_html,
+        effects,
+        pokemon,
+        references,
+    })
+}
+
+/// Reads the “Pokémon with …” table, which has one row per Pokémon (or form) and
+/// a column for each ability slot.
+fn read_pokemon_table(
+    page: &Page,
+    ability_name: &str,
+    table: &NodeRef,
+    pokemon: &mut Vec<AbilityMon>,
+) -> anyhow::Result<()> {
+    let mut hidden_column = None;
+
+    for tr in table
+        .select("tr")
+        .map_err(|()| anyhow!("could not find <tr>"))?
+    {
+        let tr = tr.as_node();
+        if tr.select_first("table").is_ok() {
+            // row of an outer layout table
+            continue;
+        }
+        let cells: Vec<_> = tr.children().filter(is_element).collect();
+
+        let is_header = cells
+            .iter()
+            .all(|cell| cell.as_element().unwrap().name.local.as_ref() == "th");
+        if is_header {
+            let mut column = 0;
+            for cell in &cells {
+                if cell.text_contents().contains("Hidden") {
+                    hidden_column = Some(column);
+                }
+                column += colspan(cell);
+            }
+            continue;
+        }
+
+        let Ok(link) = tr.select_first("a[href$='_(Pok%C3%A9mon)']") else {
+            continue;
+        };
+        let Some((dex_id, form)) = page.pokemon_link(link.as_node())? else {
+            continue;
+        };
+
+        let mut column = 0;
+        let mut ability_column = None;
+        for cell in &cells {
+            if cell.text_contents().trim() == ability_name {
+                ability_column = Some(column);
+            }
+            column += colspan(cell);
+        }
+
+        pokemon.push(AbilityMon {
+            dex_id,
+            form,
+            hidden: ability_column.is_some() && ability_column == hidden_column,
+        });
+    }
+
+    Ok(())
+}
+
+fn colspan(cell: &NodeRef) -> usize {
+    get_attr(cell, "colspan")
+        .and_then(|span| span.parse().ok())
+        .unwrap_or(1)
+}
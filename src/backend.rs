@@ -0,0 +1,127 @@
+//! Output formats. Each backend renders the same [`Index`] and [`Entries`] into a dictionary
+//! format, so adding a format doesn't touch the scraping code.
+
+use crate::dictd::Dictd;
+use crate::dsl::Dsl;
+use crate::epub::Epub;
+use crate::gen::generate_dictionary;
+use crate::image::copy_compressed;
+use crate::index::Index;
+use crate::json::Json;
+use crate::kobo::Kobo;
+use crate::mdict::MDict;
+use crate::rendered::{disambiguate_titles, rewrite_images, split_entries, RenderedEntry};
+use crate::stardict::StarDict;
+use crate::xdxf::Xdxf;
+use crate::yomitan::Yomitan;
+use crate::Entries;
+use anyhow::{anyhow, Context};
+use rayon::prelude::*;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Everything a backend gets to render.
+pub struct Dictionary<'a> {
+    pub index: &'a Index,
+    pub entries: &'a Entries,
+    /// Directory with the image files that entries refer to as `images/<file>`
+    pub image_dir: &'a Path,
+}
+
+impl Dictionary<'_> {
+    /// The entries as generated for the Apple format, for formats that reuse its HTML and keys.
+    /// Titles are made unique with [`disambiguate_titles`], since most formats link by title.
+    pub fn rendered_entries(&self) -> anyhow::Result<Vec<RenderedEntry>> {
+        let mut entries = split_entries(&generate_dictionary(self.index, self.entries)?)?;
+        disambiguate_titles(&mut entries);
+        Ok(entries)
+    }
+}
+
+pub trait Backend: Sync {
+    /// Name used to pick the backend on the command line, e.g. “apple”.
+    fn name(&self) -> &'static str;
+
+    /// Where to write the output if not specified otherwise.
+    fn default_output_dir(&self) -> PathBuf {
+        Path::new("out").join(self.name())
+    }
+
+    /// Renders the dictionary into `out_dir`, which may not exist yet.
+    fn write(&self, dict: &Dictionary, out_dir: &Path) -> anyhow::Result<()>;
+}
+
+/// All available backends.
+pub fn backends() -> Vec<Box<dyn Backend>> {
+    vec![
+        Box::new(AppleDictionary),
+        Box::new(StarDict),
+        Box::new(Yomitan),
+        Box::new(Epub),
+        Box::new(Kobo),
+        Box::new(MDict),
+        Box::new(Dsl),
+        Box::new(Dictd),
+        Box::new(Xdxf),
+        Box::new(Json),
+    ]
+}
+
+pub fn backend_by_name(name: &str) -> anyhow::Result<Box<dyn Backend>> {
+    backends()
+        .into_iter()
+        .find(|backend| backend.name() == name)
+        .ok_or_else(|| {
+            let names: Vec<_> = backends().iter().map(|backend| backend.name()).collect();
+            anyhow!("unknown format “{name}” (available: {})", names.join(", "))
+        })
+}
+
+/// Apple DictionaryService XML, built into a `.dictionary` with the Dictionary Development Kit.
+/// Images are copied into `OtherResources/images`, compressed to HEIF where possible.
+pub struct AppleDictionary;
+
+impl Backend for AppleDictionary {
+    fn name(&self) -> &'static str {
+        "apple"
+    }
+
+    fn default_output_dir(&self) -> PathBuf {
+        PathBuf::from("ddk")
+    }
+
+    fn write(&self, dict: &Dictionary, out_dir: &Path) -> anyhow::Result<()> {
+        let xml = generate_dictionary(dict.index, dict.entries)?;
+
+        let mut files = BTreeSet::new();
+        rewrite_images(&xml, |file| {
+            files.insert(file.to_string());
+            String::new()
+        });
+        let image_dir = out_dir.join("OtherResources").join("images");
+        fs::create_dir_all(&image_dir)?;
+        let copies = files
+            .into_par_iter()
+            .map(|file| {
+                let copy = copy_compressed(&dict.image_dir.join(&file), &image_dir)
+                    .with_context(|| format!("error copying image {file}"))?;
+                Ok((file, copy))
+            })
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
+        let xml = rewrite_images(&xml, |file| {
+            format!("images/{}", urlencoding::encode(&copies[file]))
+        });
+
+        // remove images from previous runs
+        let current: HashSet<_> = copies.values().map(String::as_str).collect();
+        for file in fs::read_dir(&image_dir)? {
+            let file = file?;
+            if !current.contains(&*file.file_name().to_string_lossy()) {
+                fs::remove_file(file.path())?;
+            }
+        }
+
+        fs::write(out_dir.join("Dictionary.xml"), xml).context("error writing Dictionary.xml")
+    }
+}
@@ -0,0 +1,94 @@
+use crate::fetcher::Fetcher;
+use crate::image::ImageCache;
+use crate::Config;
+use anyhow::Context;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::{fs, io};
+
+/// Bump this whenever extraction changes in a way that should invalidate cached entries.
+pub const EXTRACTION_VERSION: u32 = 4;
+
+/// Keeps extracted entries from previous runs, so only pages whose inputs changed get parsed
+/// again.
+///
+/// An entry is reused if its page content, the extraction version, the [`Config`] and what the
+/// entry depends on in the index (what its links resolve to, see [`Index::link_targets`]) are all
+/// the same, and its images are still in the image cache. So new redirects or a different
+/// selection only invalidate the entries whose links they change.
+///
+/// [`Index::link_targets`]: crate::Index::link_targets
+pub struct EntryCache {
+    dir: PathBuf,
+    /// Hash of everything that all entries depend on
+    base_key: blake3::Hash,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedEntry<T> {
+    key: String,
+    images: Vec<String>,
+    entry: T,
+}
+
+impl EntryCache {
+    pub fn new(dir: PathBuf, config: &Config) -> anyhow::Result<Self> {
+        fs::create_dir_all(&dir)?;
+
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&EXTRACTION_VERSION.to_le_bytes());
+        hasher.update(&serde_json::to_vec(config)?);
+
+        Ok(Self {
+            dir,
+            base_key: hasher.finalize(),
+        })
+    }
+
+    /// Returns the cached entry for the page if it's still valid, or reads it with `read` and
+    /// caches the result. `name` identifies the entry, e.g. “pokemon-25”, and `dependencies`
+    /// describes everything besides the page that the entry depends on.
+    pub fn get_or_read<T: Serialize + DeserializeOwned>(
+        &self,
+        fetcher: &Fetcher,
+        image_cache: &ImageCache,
+        name: &str,
+        url: &str,
+        dependencies: &str,
+        read: impl FnOnce() -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        let page = fetcher.get(url, true)?;
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(self.base_key.as_bytes());
+        hasher.update(dependencies.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(&page);
+        let key = hasher.finalize().to_hex().to_string();
+
+        let path = self.dir.join(format!("{name}.json"));
+        match fs::read(&path) {
+            Ok(data) => {
+                // a broken cache file is just a cache miss
+                if let Ok(cached) = serde_json::from_slice::<CachedEntry<T>>(&data) {
+                    if cached.key == key && cached.images.iter().all(|id| image_cache.contains(id))
+                    {
+                        image_cache.mark_used(cached.images);
+                        return Ok(cached.entry);
+                    }
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+            Err(err) => return Err(err).context("error reading cached entry"),
+        }
+
+        let (entry, images) = image_cache.record(read);
+        let cached = CachedEntry {
+            key,
+            images,
+            entry: entry?,
+        };
+        fs::write(&path, serde_json::to_vec(&cached)?).context("error writing cached entry")?;
+        Ok(cached.entry)
+    }
+}
@@ -0,0 +1,64 @@
+//! UTC dates and times for output metadata.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Today's date as year, month and day.
+pub fn today() -> (i64, i64, i64) {
+    civil_date((unix_now() / 86400) as i64)
+}
+
+/// The current time in ISO 8601, e.g. “2024-01-31T12:00:00Z”.
+pub fn timestamp() -> String {
+    format_timestamp(unix_now())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+fn format_timestamp(secs: u64) -> String {
+    let (days, secs) = (secs / 86400, secs % 86400);
+    let (year, month, day) = civil_date(days as i64);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Year, month and day of a number of days since 1970-01-01, from
+/// <https://howardhinnant.github.io/date_algorithms.html>.
+fn civil_date(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(-1), (1969, 12, 31));
+        assert_eq!(civil_date(11016), (2000, 2, 29));
+        assert_eq!(civil_date(19753), (2024, 1, 31));
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(1706702400), "2024-01-31T12:00:00Z");
+        assert_eq!(format_timestamp(951868799), "2000-02-29T23:59:59Z");
+    }
+}
@@ -0,0 +1,294 @@
+//! A DICT protocol ([RFC 2229]) server for databases written by the
+//! [`Dictd`](crate::dictd::Dictd) backend, e.g. for `dict -h localhost pikachu`.
+//!
+//! Supports `DEFINE`, `MATCH` with the strategies in [`Strategy`], `SHOW DB`/`STRAT`/`INFO`/
+//! `SERVER`, `OPTION MIME`, `CLIENT`, `STATUS`, `HELP` and `QUIT`.
+//!
+//! [RFC 2229]: https://www.rfc-editor.org/rfc/rfc2229
+
+use crate::dictd::{DictdDatabase, Strategy};
+use anyhow::Context;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::{TcpListener, ToSocketAddrs};
+use std::sync::Arc;
+use std::thread;
+
+/// Used for `MATCH` with the default strategy `.`, like dictd.
+const DEFAULT_STRATEGY: Strategy = Strategy::Levenshtein;
+
+const HELP: &str = "\
+DEFINE database word         -- look up word in database
+MATCH database strategy word -- match word in database using strategy
+SHOW DB                      -- list all accessible databases
+SHOW STRAT                   -- list available matching strategies
+SHOW INFO database           -- provide information about the database
+SHOW SERVER                  -- provide site-specific information
+OPTION MIME                  -- use MIME headers
+CLIENT info                  -- identify client to server
+STATUS                       -- display timing information
+HELP                         -- display this help information
+QUIT                         -- terminate connection";
+
+/// Accepts connections on `addr` until the listener fails, handling each on its own thread.
+pub fn serve(db: DictdDatabase, addr: impl ToSocketAddrs) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr).context("error starting DICT server")?;
+    eprintln!("serving {} on {}", db.name, listener.local_addr()?);
+    let db = Arc::new(db);
+    for (id, stream) in listener.incoming().enumerate() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("error accepting connection: {e}");
+                continue;
+            }
+        };
+        let db = Arc::clone(&db);
+        thread::spawn(move || {
+            let result = stream
+                .try_clone()
+                .and_then(|reader| handle_client(&db, id, BufReader::new(reader), stream));
+            if let Err(e) = result {
+                eprintln!("error in connection {id}: {e}");
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Runs one session until the client quits or disconnects. `id` goes in the message ID of the
+/// banner.
+pub fn handle_client(
+    db: &DictdDatabase,
+    id: usize,
+    reader: impl BufRead,
+    writer: impl Write,
+) -> io::Result<()> {
+    let mut session = Session {
+        db,
+        out: BufWriter::new(writer),
+        mime: false,
+    };
+    session.status(
+        220,
+        &format!(
+            "localhost {} <mime> <{}.{id}@localhost>",
+            env!("CARGO_PKG_NAME"),
+            std::process::id()
+        ),
+    )?;
+    session.out.flush()?;
+
+    for line in reader.lines() {
+        let line = line?;
+        let quit = session.command(&line)?;
+        session.out.flush()?;
+        if quit {
+            break;
+        }
+    }
+    Ok(())
+}
+
+struct Session<'a, W: Write> {
+    db: &'a DictdDatabase,
+    out: BufWriter<W>,
+    /// Whether to send MIME headers before every text response, after `OPTION MIME`
+    mime: bool,
+}
+
+impl<W: Write> Session<'_, W> {
+    /// Handles one command line, returning whether the client quit.
+    fn command(&mut self, line: &str) -> io::Result<bool> {
+        let Some(params) = parse_command(line) else {
+            self.status(501, "syntax error, illegal parameters")?;
+            return Ok(false);
+        };
+        let params: Vec<&str> = params.iter().map(String::as_str).collect();
+        let command = params.first().map(|c| c.to_uppercase()).unwrap_or_default();
+        let subcommand = params.get(1).map(|c| c.to_uppercase()).unwrap_or_default();
+
+        match (command.as_str(), subcommand.as_str(), &params[..]) {
+            ("DEFINE", _, [_, db, word]) => self.define(db, word)?,
+            ("MATCH", _, [_, db, strategy, word]) => self.match_words(db, strategy, word)?,
+            ("SHOW", "DB" | "DATABASES", [_, _]) => {
+                self.status(110, "1 databases present")?;
+                let line = format!("{} {}", self.db.name, quote(&self.db.short));
+                self.text(&line)?;
+                self.ok()?;
+            }
+            ("SHOW", "STRAT" | "STRATEGIES", [_, _]) => {
+                self.status(
+                    111,
+                    &format!("{} strategies available", Strategy::ALL.len()),
+                )?;
+                let lines: Vec<_> = Strategy::ALL
+                    .iter()
+                    .map(|s| format!("{} {}", s.name(), quote(s.description())))
+                    .collect();
+                self.text(&lines.join("\n"))?;
+                self.ok()?;
+            }
+            ("SHOW", "INFO", [_, _, db]) => {
+                if self.check_db(db)? {
+                    self.status(112, "database information follows")?;
+                    let info = self.db.info.clone();
+                    self.text(&info)?;
+                    self.ok()?;
+                }
+            }
+            ("SHOW", "SERVER", [_, _]) => {
+                self.status(114, "server information follows")?;
+                let info = format!(
+                    "{} {}, serving {}",
+                    env!("CARGO_PKG_NAME"),
+                    env!("CARGO_PKG_VERSION"),
+                    self.db.short
+                );
+                self.text(&info)?;
+                self.ok()?;
+            }
+            ("OPTION", "MIME", [_, _]) => {
+                self.mime = true;
+                self.ok()?;
+            }
+            ("CLIENT", _, [_, ..]) => self.ok()?,
+            ("STATUS", _, [_]) => self.status(210, "status ok")?,
+            ("HELP", _, [_]) => {
+                self.status(113, "help text follows")?;
+                self.text(HELP)?;
+                self.ok()?;
+            }
+            ("QUIT", _, [_]) => {
+                self.status(221, "bye")?;
+                return Ok(true);
+            }
+            ("AUTH" | "SASLAUTH", _, _) => self.status(502, "command not implemented")?,
+            (
+                "DEFINE" | "MATCH" | "SHOW" | "OPTION" | "CLIENT" | "STATUS" | "HELP" | "QUIT",
+                ..,
+            ) => self.status(501, "syntax error, illegal parameters")?,
+            _ => self.status(500, "unknown command")?,
+        }
+        Ok(false)
+    }
+
+    fn define(&mut self, db: &str, word: &str) -> io::Result<()> {
+        if !self.check_db(db)? {
+            return Ok(());
+        }
+        let database = self.db;
+        let definitions = database.define(word);
+        if definitions.is_empty() {
+            return self.status(552, "no match");
+        }
+        self.status(150, &format!("{} definitions retrieved", definitions.len()))?;
+        for (headword, text) in definitions {
+            let line = format!(
+                "{} {} {}",
+                quote(headword),
+                database.name,
+                quote(&database.short)
+            );
+            self.status(151, &line)?;
+            self.text(text)?;
+        }
+        self.ok()
+    }
+
+    fn match_words(&mut self, db: &str, strategy: &str, word: &str) -> io::Result<()> {
+        if !self.check_db(db)? {
+            return Ok(());
+        }
+        let strategy = if strategy == "." {
+            DEFAULT_STRATEGY
+        } else if let Some(strategy) = Strategy::from_name(strategy) {
+            strategy
+        } else {
+            return self.status(551, "invalid strategy, use \"SHOW STRAT\" for a list");
+        };
+        let database = self.db;
+        let matches = database.match_words(word, strategy);
+        if matches.is_empty() {
+            return self.status(552, "no match");
+        }
+        self.status(152, &format!("{} matches found", matches.len()))?;
+        let lines: Vec<_> = matches
+            .iter()
+            .map(|word| format!("{} {}", database.name, quote(word)))
+            .collect();
+        self.text(&lines.join("\n"))?;
+        self.ok()
+    }
+
+    /// Whether `db` names this database or all databases, sending a 550 if not.
+    fn check_db(&mut self, db: &str) -> io::Result<bool> {
+        let valid = db == "*" || db == "!" || db == self.db.name;
+        if !valid {
+            self.status(550, "invalid database, use \"SHOW DB\" for a list")?;
+        }
+        Ok(valid)
+    }
+
+    fn status(&mut self, code: u16, message: &str) -> io::Result<()> {
+        write!(self.out, "{code} {message}\r\n")
+    }
+
+    fn ok(&mut self) -> io::Result<()> {
+        self.status(250, "ok")
+    }
+
+    /// Sends a text response: MIME headers if the client asked for them with `OPTION MIME`, then
+    /// the text with CRLF line endings, dot-stuffed, ended by a line with a single dot.
+    fn text(&mut self, text: &str) -> io::Result<()> {
+        if self.mime {
+            self.out
+                .write_all(b"Content-Type: text/plain; charset=utf-8\r\n\r\n")?;
+        }
+        for line in text.lines() {
+            if line.starts_with('.') {
+                self.out.write_all(b".")?;
+            }
+            write!(self.out, "{line}\r\n")?;
+        }
+        self.out.write_all(b".\r\n")
+    }
+}
+
+/// Splits a command line into words, which may be quoted with `"` or `'` and contain
+/// backslash escapes. Returns `None` for unterminated quotes.
+fn parse_command(line: &str) -> Option<Vec<String>> {
+    let mut params = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(&first) = chars.peek() else {
+            break;
+        };
+        let mut param = String::new();
+        if first == '"' || first == '\'' {
+            chars.next();
+            loop {
+                match chars.next()? {
+                    '\\' => param.push(chars.next()?),
+                    c if c == first => break,
+                    c => param.push(c),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                if c == '\\' {
+                    param.push(chars.next()?);
+                } else {
+                    param.push(c);
+                }
+            }
+        }
+        params.push(param);
+    }
+    Some(params)
+}
+
+/// Quotes a word for a status line or list.
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
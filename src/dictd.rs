@@ -0,0 +1,477 @@
+//! [dictd] databases: a sorted `.index` of headwords with base64 offsets into a dictzipped
+//! `.dict` of plain-text definitions. See [`dict_server`](crate::dict_server) for serving them.
+//!
+//! [dictd]: https://linux.die.net/man/8/dictd
+
+use crate::backend::{Backend, Dictionary};
+use crate::dictzip::dictzip;
+use crate::page::get_attr;
+use anyhow::{bail, Context};
+use flate2::read::GzDecoder;
+use kuchikiki::traits::TendrilSink;
+use kuchikiki::NodeRef;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// Base name of the output files, and the database name.
+pub const NAME: &str = "pokedex";
+
+/// Prefix of the special headwords holding database metadata.
+const META_PREFIX: &str = "00-database-";
+
+/// Column definitions are wrapped at.
+const WIDTH: usize = 72;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Plain-text definitions from [`xhtml_to_text`], indexed under every index key. Links become
+/// `{Title}` cross-references, which DICT clients can follow.
+pub struct Dictd;
+
+impl Backend for Dictd {
+    fn name(&self) -> &'static str {
+        "dictd"
+    }
+
+    fn write(&self, dict: &Dictionary, out_dir: &Path) -> anyhow::Result<()> {
+        let entries = dict.rendered_entries()?;
+        let titles: HashMap<&str, &str> = entries
+            .iter()
+            .map(|entry| (entry.id.as_str(), entry.title.as_str()))
+            .collect();
+
+        let mut data = String::new();
+        let mut index = Vec::new();
+        let meta = [
+            ("short", "Pokédex"),
+            (
+                "info",
+                "Pokémon, abilities, moves, items and types from Bulbapedia.",
+            ),
+            ("url", "https://bulbapedia.bulbagarden.net"),
+            ("utf8", ""),
+            ("allchars", ""),
+        ];
+        for (name, value) in meta {
+            let word = format!("{META_PREFIX}{name}");
+            let offset = data.len();
+            writeln!(data, "{word}\n  {value}")?;
+            index.push((word, offset, data.len() - offset));
+        }
+
+        for entry in &entries {
+            let offset = data.len();
+            writeln!(data, "{}", entry.title)?;
+            data += &xhtml_to_text(&entry.html, |id| titles.get(id).copied());
+            for word in entry.words() {
+                // tabs and newlines would break the index
+                let word = word.split_whitespace().collect::<Vec<_>>().join(" ");
+                if !word.is_empty() {
+                    index.push((word, offset, data.len() - offset));
+                }
+            }
+        }
+
+        // `allchars` and `utf8` make dictd compare whole UTF-8 headwords, ignoring case
+        index.sort_by_key(|(word, offset, _)| (word.to_lowercase(), *offset));
+        index.dedup();
+        let mut out = String::new();
+        for (word, offset, len) in index {
+            writeln!(
+                out,
+                "{word}\t{}\t{}",
+                encode_number(offset as u64),
+                encode_number(len as u64)
+            )?;
+        }
+
+        fs::create_dir_all(out_dir)?;
+        fs::write(out_dir.join(format!("{NAME}.index")), out).context("error writing .index")?;
+        fs::write(
+            out_dir.join(format!("{NAME}.dict.dz")),
+            dictzip(data.as_bytes())?,
+        )
+        .context("error writing .dict.dz")?;
+        Ok(())
+    }
+}
+
+/// A database written by [`Dictd`], loaded into memory.
+pub struct DictdDatabase {
+    pub name: String,
+    /// Short description, e.g. for `SHOW DB`
+    pub short: String,
+    pub info: String,
+    /// Sorted by lowercase headword
+    words: Vec<IndexWord>,
+    data: Vec<u8>,
+}
+
+struct IndexWord {
+    word: String,
+    lowercase: String,
+    offset: usize,
+    len: usize,
+}
+
+/// How `MATCH` compares headwords to the query. All strategies ignore case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    Exact,
+    Prefix,
+    /// Headwords at most one insertion, deletion or substitution away
+    Levenshtein,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 3] = [Strategy::Exact, Strategy::Prefix, Strategy::Levenshtein];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::Exact => "exact",
+            Strategy::Prefix => "prefix",
+            Strategy::Levenshtein => "lev",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Strategy::Exact => "Match headwords exactly",
+            Strategy::Prefix => "Match prefixes",
+            Strategy::Levenshtein => "Match headwords within Levenshtein distance one",
+        }
+    }
+
+    /// Parses a strategy name, also accepting “levenshtein”.
+    pub fn from_name(name: &str) -> Option<Strategy> {
+        match name.to_lowercase().as_str() {
+            "exact" => Some(Strategy::Exact),
+            "prefix" => Some(Strategy::Prefix),
+            "lev" | "levenshtein" => Some(Strategy::Levenshtein),
+            _ => None,
+        }
+    }
+}
+
+impl DictdDatabase {
+    /// Loads `pokedex.index` and `pokedex.dict.dz` from `dir`.
+    pub fn open(dir: &Path) -> anyhow::Result<Self> {
+        let index_path = dir.join(format!("{NAME}.index"));
+        let index = fs::read_to_string(&index_path)
+            .with_context(|| format!("error reading {}", index_path.display()))?;
+        let dict_path = dir.join(format!("{NAME}.dict.dz"));
+        let mut data = Vec::new();
+        fs::File::open(&dict_path)
+            .and_then(|file| GzDecoder::new(file).read_to_end(&mut data))
+            .with_context(|| format!("error reading {}", dict_path.display()))?;
+
+        let mut words = Vec::new();
+        for line in index.lines() {
+            let mut fields = line.split('\t');
+            let (Some(word), Some(offset), Some(len)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                bail!("invalid index line {line:?}");
+            };
+            let (Some(offset), Some(len)) = (decode_number(offset), decode_number(len)) else {
+                bail!("invalid offset or length in index line {line:?}");
+            };
+            let (offset, len) = (offset as usize, len as usize);
+            if offset
+                .checked_add(len)
+                .and_then(|end| data.get(offset..end))
+                .is_none()
+            {
+                bail!("index line {line:?} points past the end of the data");
+            }
+            words.push(IndexWord {
+                word: word.to_string(),
+                lowercase: word.to_lowercase(),
+                offset,
+                len,
+            });
+        }
+        words.sort_by(|a, b| a.lowercase.cmp(&b.lowercase));
+
+        let mut db = DictdDatabase {
+            name: NAME.to_string(),
+            short: String::new(),
+            info: String::new(),
+            words,
+            data,
+        };
+        db.short = db.meta("short").unwrap_or_else(|| NAME.to_string());
+        db.info = db.meta("info").unwrap_or_else(|| db.short.clone());
+        Ok(db)
+    }
+
+    /// Definitions of `word`, as (headword, text) pairs. The text starts with the entry title.
+    pub fn define(&self, word: &str) -> Vec<(&str, &str)> {
+        let mut offsets = BTreeSet::new();
+        self.find(word, Strategy::Exact)
+            .filter(|w| offsets.insert(w.offset))
+            .map(|w| (w.word.as_str(), self.text(w)))
+            .collect()
+    }
+
+    /// Distinct headwords matching `word`, without the metadata headwords.
+    pub fn match_words(&self, word: &str, strategy: Strategy) -> Vec<&str> {
+        let mut matches: Vec<&str> = Vec::new();
+        for w in self.find(word, strategy) {
+            if !w.word.starts_with(META_PREFIX) && !matches.contains(&w.word.as_str()) {
+                matches.push(&w.word);
+            }
+        }
+        matches
+    }
+
+    fn find(&self, word: &str, strategy: Strategy) -> Box<dyn Iterator<Item = &IndexWord> + '_> {
+        let word = word.to_lowercase();
+        let start = self.words.partition_point(|w| w.lowercase < word);
+        let rest = self.words[start..].iter();
+        match strategy {
+            Strategy::Exact => Box::new(rest.take_while(move |w| w.lowercase == word)),
+            Strategy::Prefix => Box::new(rest.take_while(move |w| w.lowercase.starts_with(&word))),
+            Strategy::Levenshtein => {
+                let word: Vec<char> = word.chars().collect();
+                Box::new(self.words.iter().filter(move |w| {
+                    let other: Vec<char> = w.lowercase.chars().collect();
+                    other.len().abs_diff(word.len()) <= 1 && levenshtein(&word, &other) <= 1
+                }))
+            }
+        }
+    }
+
+    fn text(&self, word: &IndexWord) -> &str {
+        std::str::from_utf8(&self.data[word.offset..word.offset + word.len]).unwrap_or_default()
+    }
+
+    /// Value of a `00-database-` headword, without the headword line.
+    fn meta(&self, name: &str) -> Option<String> {
+        let (_, text) = self
+            .define(&format!("{META_PREFIX}{name}"))
+            .into_iter()
+            .next()?;
+        let value = text.split_once('\n').map_or("", |(_, value)| value).trim();
+        (!value.is_empty()).then(|| value.to_string())
+    }
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// dictd's base64 numbers: most significant digit first, without padding.
+pub fn encode_number(mut n: u64) -> String {
+    let mut digits = Vec::new();
+    loop {
+        digits.push(BASE64[(n % 64) as usize]);
+        n /= 64;
+        if n == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
+
+pub fn decode_number(s: &str) -> Option<u64> {
+    if s.is_empty() {
+        return None;
+    }
+    s.bytes().try_fold(0u64, |n, c| {
+        let digit = BASE64.iter().position(|&d| d == c)?;
+        n.checked_mul(64)?.checked_add(digit as u64)
+    })
+}
+
+/// Converts entry XHTML to indented, wrapped plain text lines. `entry_title` resolves
+/// `x-dictionary:r:` link targets to headwords, which become `{Title}` cross-references.
+///
+/// Paragraphs and headings are separated by blank lines, list items get bullets and are indented
+/// by nesting, and table rows become lines with cells separated by “ | ”. Images are left out.
+pub fn xhtml_to_text<'a>(html: &str, entry_title: impl Fn(&str) -> Option<&'a str>) -> String {
+    let doc = kuchikiki::parse_html().one(html);
+    let mut converter = Converter {
+        entry_title: &entry_title,
+        lines: Vec::new(),
+        line: String::new(),
+        depth: 0,
+        bullet: false,
+    };
+    converter.children(&doc);
+    converter.break_line();
+    while converter
+        .lines
+        .last()
+        .is_some_and(|line| line.text.is_empty())
+    {
+        converter.lines.pop();
+    }
+
+    let mut out = String::new();
+    for line in &converter.lines {
+        if line.text.is_empty() {
+            out.push('\n');
+            continue;
+        }
+        // bullets go in the indentation, so later lines of the item line up with the text
+        let indent = 2 + 2 * line.depth;
+        let mut column = 0;
+        for word in line.text.split(' ') {
+            let len = word.chars().count();
+            if column == 0 && line.bullet {
+                write!(out, "{:1$}* {word}", "", indent - 2).unwrap();
+                column = indent + len;
+            } else if column == 0 || column + 1 + len > WIDTH {
+                if column != 0 {
+                    out.push('\n');
+                }
+                write!(out, "{:indent$}{word}", "").unwrap();
+                column = indent + len;
+            } else {
+                write!(out, " {word}").unwrap();
+                column += 1 + len;
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[derive(Default)]
+struct Line {
+    /// List nesting depth
+    depth: usize,
+    /// Whether the line starts a list item
+    bullet: bool,
+    /// Words separated by single spaces, or empty for a blank line
+    text: String,
+}
+
+struct Converter<'a, 'b> {
+    entry_title: &'b dyn Fn(&str) -> Option<&'a str>,
+    lines: Vec<Line>,
+    line: String,
+    depth: usize,
+    /// Whether the next line starts a list item
+    bullet: bool,
+}
+
+impl Converter<'_, '_> {
+    fn children(&mut self, node: &NodeRef) {
+        for child in node.children() {
+            self.node(&child);
+        }
+    }
+
+    fn node(&mut self, node: &NodeRef) {
+        if let Some(text) = node.as_text() {
+            self.text(&text.borrow());
+            return;
+        }
+        let Some(el) = node.as_element() else {
+            self.children(node);
+            return;
+        };
+
+        match &*el.name.local {
+            "head" | "script" | "style" | "img" => (),
+            "br" => self.break_line(),
+            "a" => self.link(node),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "p" => {
+                self.blank_line();
+                self.children(node);
+                self.blank_line();
+            }
+            "li" | "dd" => {
+                self.break_line();
+                self.depth += 1;
+                self.bullet = &*el.name.local == "li";
+                self.children(node);
+                self.break_line();
+                self.depth -= 1;
+            }
+            "td" | "th" => {
+                if !self.line.trim().is_empty() {
+                    self.line.push_str(" | ");
+                }
+                self.children(node);
+            }
+            "div" | "ul" | "ol" | "dl" | "dt" | "table" | "tbody" | "thead" | "tr" | "caption"
+            | "figure" | "figcaption" | "blockquote" | "section" | "header" | "footer" => {
+                self.break_line();
+                self.children(node);
+                self.break_line();
+            }
+            _ => self.children(node),
+        }
+    }
+
+    fn link(&mut self, node: &NodeRef) {
+        let href = get_attr(node, "href").unwrap_or_default();
+        let target = href
+            .strip_prefix("x-dictionary:r:")
+            .map(|target| target.split('#').next().unwrap_or(target))
+            .and_then(|id| (self.entry_title)(id));
+        let Some(title) = target else {
+            self.children(node);
+            return;
+        };
+
+        let text = node.text_contents();
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        // clients show the cross-reference itself, so keep differing link text next to it
+        if text.is_empty() || text == title {
+            write!(self.line, "{{{title}}}").unwrap();
+        } else {
+            self.children(node);
+            write!(self.line, " ({{{title}}})").unwrap();
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if text.starts_with(char::is_whitespace) {
+            self.line.push(' ');
+        }
+        self.line
+            .push_str(&text.split_whitespace().collect::<Vec<_>>().join(" "));
+        if text.ends_with(char::is_whitespace) {
+            self.line.push(' ');
+        }
+    }
+
+    /// Ends the current line, if it has any content.
+    fn break_line(&mut self) {
+        let line = std::mem::take(&mut self.line);
+        let text = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !text.is_empty() {
+            self.lines.push(Line {
+                depth: self.depth,
+                bullet: std::mem::take(&mut self.bullet),
+                text,
+            });
+        }
+    }
+
+    /// Ends the current line and adds a blank line, unless there is one already.
+    fn blank_line(&mut self) {
+        self.break_line();
+        if self.lines.last().is_some_and(|line| !line.text.is_empty()) {
+            self.lines.push(Line::default());
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! [dictzip] compression: gzip with an index of independently compressed chunks, so readers can
+//! decompress single entries without reading the whole file. Plain gzip readers can still read
+//! it.
+//!
+//! [dictzip]: https://linux.die.net/man/1/dictzip
+
+use anyhow::ensure;
+use flate2::{Compress, Compression, Crc, FlushCompress, Status};
+
+/// Uncompressed chunk size. The compressed size of a chunk has to fit in 16 bits, which this
+/// leaves enough room for even with incompressible data.
+const CHUNK_LEN: usize = 58315;
+
+/// Compresses `data` into a `.dz` file.
+pub fn dictzip(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut compress = Compress::new(Compression::best(), false);
+    let mut body = Vec::with_capacity(data.len() / 2);
+    let mut chunk_sizes = Vec::new();
+    for chunk in data.chunks(CHUNK_LEN) {
+        let start = body.len();
+        deflate(&mut compress, chunk, FlushCompress::Full, &mut body)?;
+        chunk_sizes.push(u16::try_from(body.len() - start)?);
+    }
+    deflate(&mut compress, &[], FlushCompress::Finish, &mut body)?;
+
+    // “RA” extra field: version, chunk length, chunk count, compressed chunk sizes
+    let mut extra = Vec::new();
+    extra.extend_from_slice(b"RA");
+    let extra_len = 6 + 2 * chunk_sizes.len();
+    ensure!(
+        extra_len + 4 <= u16::MAX as usize,
+        "too much data for dictzip"
+    );
+    extra.extend_from_slice(&(extra_len as u16).to_le_bytes());
+    extra.extend_from_slice(&1u16.to_le_bytes());
+    extra.extend_from_slice(&(CHUNK_LEN as u16).to_le_bytes());
+    extra.extend_from_slice(&(chunk_sizes.len() as u16).to_le_bytes());
+    for size in chunk_sizes {
+        extra.extend_from_slice(&size.to_le_bytes());
+    }
+
+    let mut crc = Crc::new();
+    crc.update(data);
+
+    let mut out = Vec::with_capacity(body.len() + extra.len() + 20);
+    // magic, deflate, FEXTRA, no mtime, max compression, Unix
+    out.extend_from_slice(&[0x1f, 0x8b, 8, 4, 0, 0, 0, 0, 2, 3]);
+    out.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+    out.extend_from_slice(&extra);
+    out.extend_from_slice(&body);
+    out.extend_from_slice(&crc.sum().to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    Ok(out)
+}
+
+fn deflate(
+    compress: &mut Compress,
+    mut input: &[u8],
+    flush: FlushCompress,
+    out: &mut Vec<u8>,
+) -> anyhow::Result<()> {
+    loop {
+        out.reserve(input.len() + 1024);
+        let total_in = compress.total_in();
+        let status = compress.compress_vec(input, out, flush)?;
+        input = &input[(compress.total_in() - total_in) as usize..];
+        // the flush is done once there's output space left over
+        if status == Status::StreamEnd || (input.is_empty() && out.len() < out.capacity()) {
+            return Ok(());
+        }
+    }
+}
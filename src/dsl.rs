@@ -0,0 +1,359 @@
+//! ABBYY Lingvo DSL dictionaries, e.g. for GoldenDict.
+
+use crate::backend::{Backend, Dictionary};
+use crate::dictzip::dictzip;
+use crate::page::get_attr;
+use anyhow::Context;
+use kuchikiki::traits::TendrilSink;
+use kuchikiki::NodeRef;
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// Base name of the output files.
+const NAME: &str = "pokedex";
+
+/// Each entry has the title and other index keys as headwords, and a body converted from the
+/// entry's XHTML with [`xhtml_to_dsl`]. Written as dictzipped UTF-16, with the images in
+/// `pokedex.dsl.files`.
+pub struct Dsl;
+
+impl Backend for Dsl {
+    fn name(&self) -> &'static str {
+        "dsl"
+    }
+
+    fn write(&self, dict: &Dictionary, out_dir: &Path) -> anyhow::Result<()> {
+        let entries = dict.rendered_entries()?;
+        let titles: HashMap<&str, &str> = entries
+            .iter()
+            .map(|entry| (entry.id.as_str(), entry.title.as_str()))
+            .collect();
+
+        let mut out = String::from(
+            "#NAME \"Pokédex\"\n#INDEX_LANGUAGE \"English\"\n#CONTENTS_LANGUAGE \"English\"\n",
+        );
+        let mut images = BTreeSet::new();
+        for entry in &entries {
+            out.push('\n');
+            for word in entry.words() {
+                writeln!(out, "{}", escape_headword(word))?;
+            }
+            let lines = xhtml_to_dsl(&entry.html, |id| titles.get(id).copied(), &mut images);
+            for line in lines {
+                writeln!(out, "\t{line}")?;
+            }
+        }
+
+        // UTF-16LE with a byte order mark, as Lingvo expects
+        let data: Vec<u8> = std::iter::once('\u{feff}')
+            .chain(out.chars())
+            .collect::<String>()
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+
+        fs::create_dir_all(out_dir)?;
+        fs::write(out_dir.join(format!("{NAME}.dsl.dz")), dictzip(&data)?)
+            .context("error writing .dsl.dz")?;
+
+        let files_dir = out_dir.join(format!("{NAME}.dsl.files"));
+        if files_dir.exists() {
+            fs::remove_dir_all(&files_dir)?;
+        }
+        fs::create_dir_all(&files_dir)?;
+        for file in images {
+            fs::copy(dict.image_dir.join(&file), files_dir.join(&file))
+                .with_context(|| format!("error copying image {file}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Converts entry XHTML to DSL body lines (without the leading tab). `entry_title` resolves
+/// `x-dictionary:r:` link targets to headwords, and image file names are added to `images`.
+///
+/// Block elements become lines, indented with `[m1]`–`[m3]` by list nesting. Bold, italics,
+/// underline, super- and subscripts map to their DSL tags, entry links to `[ref]`, images to
+/// `[s]`, and table rows to lines with cells separated by “ | ”.
+pub fn xhtml_to_dsl<'a>(
+    html: &str,
+    entry_title: impl Fn(&str) -> Option<&'a str>,
+    images: &mut BTreeSet<String>,
+) -> Vec<String> {
+    let doc = kuchikiki::parse_html().one(html);
+    let mut converter = Converter {
+        entry_title: &entry_title,
+        images,
+        lines: Vec::new(),
+        line: String::new(),
+        open_tags: Vec::new(),
+        depth: 0,
+    };
+    converter.children(&doc);
+    converter.break_line();
+    converter.lines
+}
+
+struct Converter<'a, 'b> {
+    entry_title: &'b dyn Fn(&str) -> Option<&'a str>,
+    images: &'b mut BTreeSet<String>,
+    lines: Vec<String>,
+    line: String,
+    /// Inline tags open at the end of `line`, which get closed and reopened across lines
+    open_tags: Vec<&'static str>,
+    /// List nesting depth
+    depth: usize,
+}
+
+impl Converter<'_, '_> {
+    fn children(&mut self, node: &NodeRef) {
+        for child in node.children() {
+            self.node(&child);
+        }
+    }
+
+    fn node(&mut self, node: &NodeRef) {
+        if let Some(text) = node.as_text() {
+            self.text(&text.borrow());
+            return;
+        }
+        let Some(el) = node.as_element() else {
+            self.children(node);
+            return;
+        };
+
+        match &*el.name.local {
+            "head" | "script" | "style" => (),
+            "br" => self.break_line(),
+            "b" | "strong" => self.inline(node, "b"),
+            "i" | "em" | "cite" => self.inline(node, "i"),
+            "u" => self.inline(node, "u"),
+            "sup" => self.inline(node, "sup"),
+            "sub" => self.inline(node, "sub"),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "dt" | "caption" => {
+                self.block(|this| this.inline(node, "b"))
+            }
+            "img" => {
+                let src = get_attr(node, "src").unwrap_or_default();
+                if let Some(src) = src.strip_prefix("images/") {
+                    let file = urlencoding::decode(src)
+                        .unwrap_or(Cow::Borrowed(src))
+                        .into_owned();
+                    self.push_raw(&format!("[s]{}[/s]", escape(&file)));
+                    self.images.insert(file);
+                }
+            }
+            "a" => self.link(node),
+            "li" | "dd" => self.block(|this| {
+                this.depth += 1;
+                this.children(node);
+                this.break_line();
+                this.depth -= 1;
+            }),
+            "td" | "th" => {
+                if has_content(&self.line) {
+                    self.push_raw(" | ");
+                }
+                if &*el.name.local == "th" {
+                    self.inline(node, "b");
+                } else {
+                    self.children(node);
+                }
+            }
+            "p" | "div" | "ul" | "ol" | "dl" | "table" | "tbody" | "thead" | "tr" | "figure"
+            | "figcaption" | "blockquote" | "section" | "header" | "footer" => {
+                self.block(|this| this.children(node))
+            }
+            _ => self.children(node),
+        }
+    }
+
+    fn link(&mut self, node: &NodeRef) {
+        let href = get_attr(node, "href").unwrap_or_default();
+        let target = href
+            .strip_prefix("x-dictionary:r:")
+            .map(|target| target.split('#').next().unwrap_or(target))
+            .and_then(|id| (self.entry_title)(id));
+        let Some(title) = target else {
+            self.children(node);
+            return;
+        };
+
+        let text = node.text_contents();
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        // [ref] shows the headword it links to, so keep differing link text next to it
+        if text.is_empty() || text == title {
+            self.push_raw(&format!("[ref]{}[/ref]", escape(title)));
+        } else {
+            self.children(node);
+            self.push_raw(&format!(" ([ref]{}[/ref])", escape(title)));
+        }
+    }
+
+    fn inline(&mut self, node: &NodeRef, tag: &'static str) {
+        self.push_raw(&format!("[{tag}]"));
+        self.open_tags.push(tag);
+        self.children(node);
+        self.open_tags.pop();
+        self.push_raw(&format!("[/{tag}]"));
+    }
+
+    fn block(&mut self, f: impl FnOnce(&mut Self)) {
+        self.break_line();
+        f(self);
+        self.break_line();
+    }
+
+    fn text(&mut self, text: &str) {
+        let mut words = text.split(|c: char| c.is_ascii_whitespace()).peekable();
+        if text.starts_with(|c: char| c.is_ascii_whitespace()) {
+            self.space();
+        }
+        while let Some(word) = words.next() {
+            if !word.is_empty() {
+                self.line.push_str(&escape(word));
+                if words.peek().is_some() {
+                    self.space();
+                }
+            }
+        }
+        if text.ends_with(|c: char| c.is_ascii_whitespace()) {
+            self.space();
+        }
+    }
+
+    fn space(&mut self) {
+        if !self.line.is_empty() && !self.line.ends_with(' ') {
+            self.line.push(' ');
+        }
+    }
+
+    fn push_raw(&mut self, s: &str) {
+        self.line.push_str(s);
+    }
+
+    /// Ends the current line, if it has any content.
+    fn break_line(&mut self) {
+        let mut line = std::mem::take(&mut self.line);
+        if has_content(&line) {
+            for tag in self.open_tags.iter().rev() {
+                write!(line, "[/{tag}]").unwrap();
+            }
+            let indent = (self.depth + 1).min(3);
+            self.lines.push(format!("[m{indent}]{}[/m]", line.trim()));
+            for tag in &self.open_tags {
+                write!(self.line, "[{tag}]").unwrap();
+            }
+        } else {
+            self.line = line;
+        }
+    }
+}
+
+/// Whether a line has something besides tags and whitespace.
+fn has_content(line: &str) -> bool {
+    let mut in_tag = false;
+    let mut escaped = false;
+    for c in line.chars() {
+        match c {
+            _ if escaped => return true,
+            '\\' => escaped = true,
+            '[' => in_tag = true,
+            ']' => in_tag = false,
+            c if !in_tag && !c.is_whitespace() => return true,
+            _ => (),
+        }
+    }
+    false
+}
+
+/// Escapes characters that are markup in DSL bodies.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '[' | ']' | '{' | '}' | '~' | '@' | '<' | '>') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Escapes characters that are markup in DSL headwords, e.g. parentheses for optional parts.
+fn escape_headword(word: &str) -> String {
+    let mut out = String::with_capacity(word.len());
+    for (i, c) in word.chars().enumerate() {
+        if matches!(c, '\\' | '(' | ')' | '[' | ']' | '{' | '}' | '~' | '@')
+            || (i == 0 && matches!(c, '#' | ' ' | '\t'))
+        {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(html: &str) -> Vec<String> {
+        let titles = HashMap::from([("type-electric", "Electric (type)"), ("move-1", "Pound")]);
+        xhtml_to_dsl(html, |id| titles.get(id).copied(), &mut BTreeSet::new())
+    }
+
+    #[test]
+    fn reopens_inline_tags() {
+        assert_eq!(
+            convert("<p><b>bold <i>and<br>italic</i></b> text</p>"),
+            [
+                "[m1][b]bold [i]and[/i][/b][/m]",
+                "[m1][b][i]italic[/i][/b] text[/m]"
+            ]
+        );
+        // no empty line for tags left open at a block's end
+        assert_eq!(convert("<b><p>x</p></b>"), ["[m1][b]x[/b][/m]"]);
+    }
+
+    #[test]
+    fn nesting() {
+        assert_eq!(
+            convert("<p>top</p><ul><li>one<ul><li>two<ul><li>three<ul><li>four</li></ul></li></ul></li></ul></li></ul>"),
+            ["[m1]top[/m]", "[m2]one[/m]", "[m3]two[/m]", "[m3]three[/m]", "[m3]four[/m]"]
+        );
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(
+            convert(r"<p>[a] {b} ~c @d &lt;e&gt; \f</p>"),
+            [r"[m1]\[a\] \{b\} \~c \@d \<e\> \\f[/m]"]
+        );
+        assert_eq!(convert(r"<p>\</p>"), [r"[m1]\\[/m]"]);
+    }
+
+    #[test]
+    fn links() {
+        assert_eq!(
+            convert(concat!(
+                r#"<p><a href="x-dictionary:r:move-1#Ruby">Pound</a>, "#,
+                r#"<a href="x-dictionary:r:type-electric">Electric</a>, "#,
+                r#"<a href="x-dictionary:r:pokemon-0">missing</a>, "#,
+                r#"<a href="https://example.com/">elsewhere</a></p>"#,
+            )),
+            ["[m1][ref]Pound[/ref], Electric ([ref]Electric (type)[/ref]), missing, elsewhere[/m]"]
+        );
+    }
+
+    #[test]
+    fn headwords() {
+        assert_eq!(escape_headword("#025"), r"\#025");
+        assert_eq!(escape_headword("Pikachu #025"), "Pikachu #025");
+        assert_eq!(escape_headword("Pikachu (Partner)"), r"Pikachu \(Partner\)");
+        assert_eq!(escape_headword(r"{a} [b] ~ @ \"), r"\{a\} \[b\] \~ \@ \\");
+        assert_eq!(escape_headword(" x"), r"\ x");
+    }
+}
@@ -0,0 +1,243 @@
+//! EPUB 3 dictionaries, following [EPUB Dictionaries and Glossaries], for e-readers.
+//!
+//! [EPUB Dictionaries and Glossaries]: https://www.w3.org/submissions/epub-dict/
+
+use crate::backend::{Backend, Dictionary};
+use crate::date;
+use crate::rendered::{rewrite_images, rewrite_links};
+use crate::xhtml::XhtmlEscaped;
+use anyhow::Context;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const STYLESHEET: &str = include_str!("../ddk/Dictionary.css");
+
+/// One XHTML content document per entry (entries reuse element IDs, so they can't share a
+/// document), a search key map with every index key, and a navigation document listing the
+/// front matter and generation lists.
+pub struct Epub;
+
+impl Backend for Epub {
+    fn name(&self) -> &'static str {
+        "epub"
+    }
+
+    fn write(&self, dict: &Dictionary, out_dir: &Path) -> anyhow::Result<()> {
+        let entries = dict.rendered_entries()?;
+        let files: HashMap<&str, String> = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (entry.id.as_str(), format!("e{i}.xhtml")))
+            .collect();
+
+        fs::create_dir_all(out_dir)?;
+        let path = out_dir.join("pokedex.epub");
+        let mut zip = ZipWriter::new(File::create(&path).context("error creating pokedex.epub")?);
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let deflated = FileOptions::default();
+
+        // must come first, uncompressed
+        zip.start_file("mimetype", stored)?;
+        zip.write_all(b"application/epub+zip")?;
+        zip.start_file("META-INF/container.xml", deflated)?;
+        zip.write_all(CONTAINER_XML.as_bytes())?;
+        zip.start_file("OEBPS/style.css", deflated)?;
+        zip.write_all(STYLESHEET.as_bytes())?;
+
+        let mut images = BTreeSet::new();
+        let mut search_keys = String::new();
+        let mut nav_items = String::new();
+        for entry in &entries {
+            let file = &files[entry.id.as_str()];
+            let html = rewrite_links(&entry.html, |id, fragment| {
+                let Some(target) = files.get(id) else {
+                    return String::from("#");
+                };
+                match fragment {
+                    Some(fragment) => format!("{target}#{}", XhtmlEscaped(fragment, true)),
+                    None => target.clone(),
+                }
+            });
+            let html = rewrite_images(&html, |file| {
+                // readers needn't support other types, and there are none on Bulbapedia
+                if image_media_type(file).is_none() {
+                    return String::from("#");
+                }
+                images.insert(file.to_string());
+                format!("../images/{}", urlencoding::encode(file))
+            });
+
+            zip.start_file(format!("OEBPS/entries/{file}"), deflated)?;
+            write!(
+                zip,
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="en" lang="en">
+<head>
+<meta charset="UTF-8" />
+<title>{}</title>
+<link rel="stylesheet" type="text/css" href="../style.css" />
+</head>
+<body>
+<section epub:type="dictionary">
+<article epub:type="dictentry" id="{}">
+{html}
+</article>
+</section>
+</body>
+</html>
+"#,
+                XhtmlEscaped(&entry.title, false),
+                XhtmlEscaped(&entry.id, true),
+            )?;
+
+            let mut groups: Vec<(Option<&str>, Vec<&str>)> = vec![(None, vec![&entry.title])];
+            for key in &entry.keys {
+                let anchor = key.anchor.as_deref();
+                match groups.iter_mut().find(|(a, _)| *a == anchor) {
+                    Some((_, values)) if values.contains(&key.value.as_str()) => (),
+                    Some((_, values)) => values.push(&key.value),
+                    None => groups.push((anchor, vec![&key.value])),
+                }
+            }
+            for (anchor, values) in groups {
+                let target = anchor.unwrap_or(&entry.id);
+                writeln!(
+                    search_keys,
+                    r#"<search-key-group href="entries/{file}#{}">"#,
+                    XhtmlEscaped(target, true)
+                )?;
+                for value in values {
+                    writeln!(
+                        search_keys,
+                        r#"<match value="{}" />"#,
+                        XhtmlEscaped(value, true)
+                    )?;
+                }
+                writeln!(search_keys, "</search-key-group>")?;
+            }
+
+            if entry.id == "front-matter" || entry.id.starts_with("list-of-pokemon-gen-") {
+                writeln!(
+                    nav_items,
+                    r#"<li><a href="entries/{file}">{}</a></li>"#,
+                    XhtmlEscaped(&entry.title, false)
+                )?;
+            }
+        }
+
+        zip.start_file("OEBPS/search-key-map.xml", deflated)?;
+        write!(
+            zip,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<search-key-map xmlns="http://www.idpf.org/2007/ops" xml:lang="en">
+{search_keys}</search-key-map>
+"#
+        )?;
+
+        zip.start_file("OEBPS/nav.xhtml", deflated)?;
+        write!(
+            zip,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="en" lang="en">
+<head>
+<meta charset="UTF-8" />
+<title>Pokédex</title>
+</head>
+<body>
+<nav epub:type="toc">
+<ol>
+{nav_items}</ol>
+</nav>
+</body>
+</html>
+"#
+        )?;
+
+        let mut manifest = String::new();
+        let mut spine = String::new();
+        let mut links = String::new();
+        for i in 0..entries.len() {
+            writeln!(
+                manifest,
+                r#"<item id="e{i}" href="entries/e{i}.xhtml" media-type="application/xhtml+xml" properties="dictionary" />"#
+            )?;
+            writeln!(spine, r#"<itemref idref="e{i}" />"#)?;
+            writeln!(links, r#"<link href="entries/e{i}.xhtml" />"#)?;
+        }
+
+        for (i, file) in images.iter().enumerate() {
+            writeln!(
+                manifest,
+                r#"<item id="img{i}" href="images/{}" media-type="{}" />"#,
+                urlencoding::encode(file),
+                image_media_type(file).unwrap_or_default()
+            )?;
+            let data = fs::read(dict.image_dir.join(file))
+                .with_context(|| format!("error reading image {file}"))?;
+            zip.start_file(format!("OEBPS/images/{file}"), stored)?;
+            zip.write_all(&data)?;
+        }
+
+        zip.start_file("OEBPS/package.opf", deflated)?;
+        write!(
+            zip,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="uid" xml:lang="en">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+<dc:identifier id="uid">urn:x-pokedex-dictgen:pokedex</dc:identifier>
+<dc:title>Pokédex</dc:title>
+<dc:language>en</dc:language>
+<dc:type>dictionary</dc:type>
+<dc:source>https://bulbapedia.bulbagarden.net</dc:source>
+<meta property="dcterms:modified">{}</meta>
+</metadata>
+<manifest>
+<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav" />
+<item id="style" href="style.css" media-type="text/css" />
+<item id="search-key-map" href="search-key-map.xml" media-type="application/vnd.epub.search-key-map+xml" properties="search-key-map" />
+{manifest}</manifest>
+<spine>
+{spine}</spine>
+<collection role="dictionary">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+<dc:type>dictionary</dc:type>
+<meta property="source-language">en</meta>
+<meta property="target-language">en</meta>
+</metadata>
+<link href="search-key-map.xml" />
+{links}</collection>
+</package>
+"#,
+            date::timestamp()
+        )?;
+
+        zip.finish().context("error writing pokedex.epub")?;
+        Ok(())
+    }
+}
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+<rootfiles>
+<rootfile full-path="OEBPS/package.opf" media-type="application/oebps-package+xml" />
+</rootfiles>
+</container>
+"#;
+
+/// The media type of an image, if it's one of the EPUB core media types.
+fn image_media_type(file: &str) -> Option<&'static str> {
+    let ext = file.rsplit_once('.').map_or("", |(_, ext)| ext);
+    match &*ext.to_ascii_lowercase() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        "svg" => Some("image/svg+xml"),
+        _ => None,
+    }
+}
@@ -0,0 +1,346 @@
+use crate::index::DexId;
+use crate::links::EntryRef;
+use crate::page::{get_attr, is_tag, read_sections, Page};
+use kuchikiki::NodeRef;
+use serde::{Deserialize, Serialize};
+
+/// Stage labels in the boxes of the evolution table, by stage.
+const STAGE_LABELS: &[&str] = &[
+    "Unevolved",
+    "First Evolution",
+    "Second Evolution",
+    "Third Evolution",
+];
+
+/// One step of an evolution chain, e.g. Pichu into Pikachu.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Evolution {
+    pub from: DexId,
+    pub to: DexId,
+    /// How, as plain text, e.g. “Thunder Stone”; empty if the table doesn't say
+    pub method: String,
+}
+
+/// A Pokémon in the evolution table, in page order.
+struct Member {
+    dex_id: DexId,
+    /// Stage from the label in its box, if there was exactly one
+    stage: Option<usize>,
+    /// Text between the previous Pokémon and this one
+    method: String,
+}
+
+/// Reads the evolution chain the page's Pokémon is part of, from the first table in the
+/// “Evolution” section. Empty if it doesn't evolve or there's no such table.
+///
+/// The table's shape differs between linear, branched and form-dependent evolutions, so rather
+/// than following its layout, this goes through the Pokémon links in page order. Each one
+/// evolves from the latest Pokémon one stage below, as labelled in their boxes (“Unevolved”,
+/// “First Evolution”, …), or else from the Pokémon before it. The text between two Pokémon is
+/// the method.
+pub fn read_evolutions(page: &Page, root: &NodeRef) -> anyhow::Result<Vec<Evolution>> {
+    let (_, sections) = read_sections(root);
+    let table = sections
+        .iter()
+        .flat_map(|section| &section.subsections)
+        .filter(|(title, _)| title == "Evolution")
+        .flat_map(|(_, nodes)| nodes)
+        .find_map(|node| {
+            if is_tag(node, "table") {
+                Some(node.clone())
+            } else {
+                node.select_first("table")
+                    .ok()
+                    .map(|table| table.as_node().clone())
+            }
+        });
+    let Some(table) = table else {
+        return Ok(Vec::new());
+    };
+
+    let mut reader = ChainReader {
+        page,
+        table: &table,
+        members: Vec::new(),
+        text: String::new(),
+    };
+    reader.visit(&table)?;
+    let members = reader.members;
+
+    let mut evolutions = Vec::new();
+    for (i, member) in members.iter().enumerate().skip(1) {
+        if member.stage == Some(0) {
+            continue;
+        }
+        let from = member
+            .stage
+            .and_then(|stage| {
+                members[..i]
+                    .iter()
+                    .rev()
+                    .find(|m| m.stage == Some(stage - 1))
+            })
+            .unwrap_or(&members[i - 1]);
+        if from.dex_id == member.dex_id {
+            continue;
+        }
+        let evolution = Evolution {
+            from: from.dex_id,
+            to: member.dex_id,
+            method: member.method.clone(),
+        };
+        if !evolutions.contains(&evolution) {
+            evolutions.push(evolution);
+        }
+    }
+    Ok(evolutions)
+}
+
+struct ChainReader<'a, 'p> {
+    page: &'a Page<'p>,
+    table: &'a NodeRef,
+    members: Vec<Member>,
+    /// Text since the last Pokémon link
+    text: String,
+}
+
+impl ChainReader<'_, '_> {
+    fn visit(&mut self, node: &NodeRef) -> anyhow::Result<()> {
+        if let Some(text) = node.as_text() {
+            self.text.push_str(&text.borrow());
+            return Ok(());
+        }
+        let Some(el) = node.as_element() else {
+            return Ok(());
+        };
+        match &*el.name.local {
+            "img" | "sup" | "style" | "script" => return Ok(()),
+            "a" => {
+                if let Some((dex_id, _)) = self.page.pokemon_link(node)? {
+                    self.push_member(dex_id, node);
+                    return Ok(());
+                }
+                if self.is_type_link(node)? {
+                    // the types in a Pokémon's box
+                    return Ok(());
+                }
+            }
+            _ => (),
+        }
+        for child in node.children() {
+            self.visit(&child)?;
+        }
+        self.text.push(' ');
+        Ok(())
+    }
+
+    fn push_member(&mut self, dex_id: DexId, link: &NodeRef) {
+        let method = clean_method(&std::mem::take(&mut self.text));
+        if method.is_empty() && self.members.last().is_some_and(|m| m.dex_id == dex_id) {
+            // the name after the image, in the same box
+            return;
+        }
+        self.members.push(Member {
+            dex_id,
+            stage: self.box_stage(link),
+            method,
+        });
+    }
+
+    /// The stage label of the box around a Pokémon link: the innermost ancestor with a label, if
+    /// it has only one.
+    fn box_stage(&self, link: &NodeRef) -> Option<usize> {
+        for ancestor in link.ancestors() {
+            let text = ancestor.text_contents();
+            let labels: Vec<_> = STAGE_LABELS
+                .iter()
+                .enumerate()
+                .flat_map(|(stage, label)| text.matches(label).map(move |_| stage))
+                .collect();
+            match labels[..] {
+                [] if ancestor != *self.table => (),
+                [stage] => return Some(stage),
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    fn is_type_link(&self, link: &NodeRef) -> anyhow::Result<bool> {
+        let Some(href) = get_attr(link, "href") else {
+            return Ok(false);
+        };
+        let url = self.page.base_url.join(&href)?;
+        Ok(matches!(
+            self.page.index.links.resolve(&url, false),
+            Some(EntryRef::Type(_))
+        ))
+    }
+}
+
+/// The text between two Pokémon without stage labels and arrows, e.g. “Thunder Stone”.
+fn clean_method(text: &str) -> String {
+    let mut text = text.to_string();
+    for label in STAGE_LABELS {
+        text = text.replace(label, " ");
+    }
+    text.split(|c: char| c.is_whitespace() || "→←↓↑".contains(c))
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, Fetcher, ImageCache, Index, TypeId};
+    use kuchikiki::traits::TendrilSink;
+
+    const WIKI: &str = "https://bulbapedia.bulbagarden.net/wiki/";
+
+    /// A box in the evolution table, like Bulbapedia's: image, name, types and stage.
+    fn mon_box(name: &str, stage: &str) -> String {
+        format!(
+            r#"<td><table><tr><td><a href="/wiki/{name}_(Pok%C3%A9mon)"><img src="x.png"></a></td></tr>
+            <tr><td><small>{stage}</small></td></tr>
+            <tr><td><a href="/wiki/{name}_(Pok%C3%A9mon)"><span>{name}</span></a></td></tr>
+            <tr><td><a href="/wiki/Normal_(type)"><span>Normal</span></a></td></tr></table></td>"#
+        )
+    }
+
+    fn method(text: &str) -> String {
+        format!("<td><span>→</span><br>{text}<br><span>→</span></td>")
+    }
+
+    fn read(index: &Index, table: &str) -> Vec<Evolution> {
+        let html = format!(
+            r#"<div class="mw-parser-output">
+            <h2><span class="mw-headline">Game data</span></h2>
+            <h3><span class="mw-headline">Base stats</span></h3>
+            <p>Not part of any chain: <a href="/wiki/Ditto_(Pok%C3%A9mon)">Ditto</a></p>
+            <h3><span class="mw-headline">Evolution</span></h3>
+            {table}
+            <h3><span class="mw-headline">Sprites</span></h3>
+            </div>"#
+        );
+        let doc = kuchikiki::parse_html().one(html);
+        let root = doc.select_first(".mw-parser-output").unwrap();
+        let dir = std::env::temp_dir();
+        let fetcher = Fetcher::new(dir.clone());
+        let images = ImageCache::new(dir);
+        let config = Config::default();
+        let page = Page::new(
+            &fetcher,
+            index,
+            &images,
+            &config,
+            &format!("{WIKI}Pikachu_(Pok%C3%A9mon)"),
+            &doc,
+        );
+        read_evolutions(&page, root.as_node()).unwrap()
+    }
+
+    fn index(mons: &[(&str, u32)]) -> Index {
+        let mut index = Index::default();
+        for (name, id) in mons {
+            index.links.insert(
+                &format!("{WIKI}{name}_(Pok%C3%A9mon)"),
+                EntryRef::Pokemon(DexId(*id)),
+            );
+        }
+        index
+            .links
+            .insert(&format!("{WIKI}Normal_(type)"), EntryRef::Type(TypeId(0)));
+        index
+    }
+
+    fn evolution(from: u32, to: u32, method: &str) -> Evolution {
+        Evolution {
+            from: DexId(from),
+            to: DexId(to),
+            method: method.to_string(),
+        }
+    }
+
+    #[test]
+    fn linear_chain() {
+        let index = index(&[
+            ("Pichu", 172),
+            ("Pikachu", 25),
+            ("Raichu", 26),
+            ("Ditto", 132),
+        ]);
+        let table = format!(
+            r#"<table><tr>{}{}{}{}{}</tr></table>"#,
+            mon_box("Pichu", "Unevolved"),
+            method(r#"<a href="/wiki/Friendship">High Friendship</a>"#),
+            mon_box("Pikachu", "First Evolution"),
+            method(
+                r#"<a href="/wiki/Thunder_Stone"><img src="y.png"></a> <a href="/wiki/Thunder_Stone">Thunder Stone</a>"#
+            ),
+            mon_box("Raichu", "Second Evolution"),
+        );
+        assert_eq!(
+            read(&index, &table),
+            [
+                evolution(172, 25, "High Friendship"),
+                evolution(25, 26, "Thunder Stone"),
+            ]
+        );
+    }
+
+    #[test]
+    fn branched_chain() {
+        // like Wurmple's, where both branches evolve again, and Eevee's with many branches
+        let index = index(&[
+            ("Wurmple", 265),
+            ("Silcoon", 266),
+            ("Beautifly", 267),
+            ("Cascoon", 268),
+            ("Dustox", 269),
+        ]);
+        let table = format!(
+            r#"<table><tr><td rowspan="2"><table><tr>{}</tr></table></td>{}{}{}{}</tr>
+            <tr>{}{}{}{}</tr></table>"#,
+            mon_box("Wurmple", "Unevolved"),
+            method("Level 7 (random)"),
+            mon_box("Silcoon", "First Evolution"),
+            method("Level 10"),
+            mon_box("Beautifly", "Second Evolution"),
+            method("Level 7 (random)"),
+            mon_box("Cascoon", "First Evolution"),
+            method("Level 10"),
+            mon_box("Dustox", "Second Evolution"),
+        );
+        assert_eq!(
+            read(&index, &table),
+            [
+                evolution(265, 266, "Level 7 (random)"),
+                evolution(266, 267, "Level 10"),
+                evolution(265, 268, "Level 7 (random)"),
+                evolution(268, 269, "Level 10"),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_evolutions() {
+        let index = index(&[("Pikachu", 25)]);
+        assert_eq!(read(&index, "<p>Pikachu is not known to evolve.</p>"), []);
+        let table = format!(
+            "<table><tr>{}</tr></table>",
+            mon_box("Pikachu", "Unevolved")
+        );
+        assert_eq!(read(&index, &table), []);
+    }
+
+    #[test]
+    fn methods() {
+        assert_eq!(clean_method(" → \n Level 16 \n → "), "Level 16");
+        assert_eq!(
+            clean_method("First Evolution ↓ Thunder Stone"),
+            "Thunder Stone"
+        );
+        assert_eq!(clean_method("Unevolved"), "");
+    }
+}
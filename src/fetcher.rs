@@ -2,6 +2,10 @@ use anyhow::bail;
 use reqwest::{blocking::Client, header::HeaderMap, Method};
 use std::{fs, io, path::PathBuf, sync::Mutex};
 
+/// Longest cache file name that is just the URL. File systems limit names to 255 bytes, which
+/// e.g. MediaWiki API queries for many titles go past, so longer URLs are hashed.
+const MAX_CACHE_NAME_LEN: usize = 200;
+
 #[derive(Debug)]
 pub struct Fetcher {
     dir: PathBuf,
@@ -21,8 +25,23 @@ impl Fetcher {
         }
     }
 
+    /// Where the response for `url` is cached: the URL with `/` replaced by `~`, or for long
+    /// URLs, the start of that followed by a hash of the whole URL.
+    pub fn cache_path(&self, url: &str) -> PathBuf {
+        let name = url.replace('/', "~");
+        if name.len() <= MAX_CACHE_NAME_LEN {
+            return self.dir.join(name);
+        }
+        let mut prefix_len = MAX_CACHE_NAME_LEN / 2;
+        while !name.is_char_boundary(prefix_len) {
+            prefix_len -= 1;
+        }
+        let hash = blake3::hash(url.as_bytes()).to_hex();
+        self.dir.join(format!("{}~{hash}", &name[..prefix_len]))
+    }
+
     pub fn get(&self, url: &str, document: bool) -> anyhow::Result<Vec<u8>> {
-        let cache_path = self.dir.join(url.replace('/', "~"));
+        let cache_path = self.cache_path(url);
 
         match fs::read(&cache_path) {
             Ok(data) => Ok(data),
@@ -0,0 +1,449 @@
+use crate::index::AbilityId;
+use crate::links::EntryRef;
+use crate::mon::MonImage;
+use crate::page::{get_attr, is_hidden_within, Page};
+use kuchikiki::NodeRef;
+use serde::{Deserialize, Serialize};
+
+/// Base stat names, in the order the games list them.
+pub const STAT_NAMES: &[&str] = &["HP", "Attack", "Defense", "Sp. Atk", "Sp. Def", "Speed"];
+
+/// A form of a Pokémon with its own types, abilities or stats (regional forms, Megas, etc.).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonForm {
+    /// Form name, e.g. “Alolan Form”, or the Pokémon's name if it only has one form
+    pub name: String,
+    /// Index into the entry's images
+    pub image: Option<usize>,
+    pub types: Vec<String>,
+    pub abilities: Vec<FormAbility>,
+    /// Base stats in the order of [`STAT_NAMES`]; empty if unknown
+    pub stats: Vec<(String, u32)>,
+}
+
+impl MonForm {
+    /// Title for the form's entry, e.g. “Mega Charizard X” or “Deerling - Summer Form”.
+    pub fn title(&self, mon_name: &str) -> String {
+        if self.name.contains(mon_name) {
+            self.name.clone()
+        } else {
+            format!("{mon_name} - {}", self.name)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormAbility {
+    pub name: String,
+    pub id: Option<AbilityId>,
+    pub hidden: bool,
+}
+
+/// Info box cells (or stat tables) and the form label they were marked with, if any.
+type Labelled<T> = Vec<(Option<String>, T)>;
+
+/// Reads per-form information from the info box rows and the base stats section.
+///
+/// Bulbapedia lists every form's types and abilities in the same info box row, labelled with a
+/// `<small>` caption and sometimes hidden with `display: none`. We match those captions against
+/// the image captions, which name all the forms the page shows.
+pub fn read_forms(
+    page: &Page,
+    mon_name: &str,
+    images: &[MonImage],
+    type_row: Option<&NodeRef>,
+    ability_row: Option<&NodeRef>,
+    doc: &NodeRef,
+) -> anyhow::Result<Vec<MonForm>> {
+    let types = type_row.map(read_type_cells).unwrap_or_default();
+    let abilities = match ability_row {
+        Some(row) => read_ability_cells(page, row)?,
+        None => Vec::new(),
+    };
+    let stats = read_base_stats(doc);
+
+    let mut forms: Vec<MonForm> = Vec::new();
+    let add_form = |forms: &mut Vec<MonForm>, name: &str, image: Option<usize>| {
+        if let Some(form) = forms
+            .iter_mut()
+            .find(|f| labels_match(mon_name, &f.name, name))
+        {
+            form.image = form.image.or(image);
+        } else {
+            forms.push(MonForm {
+                name: name.to_string(),
+                image,
+                types: Vec::new(),
+                abilities: Vec::new(),
+                stats: Vec::new(),
+            });
+        }
+    };
+
+    if images
+        .first()
+        .is_some_and(|image| image.caption_text.is_none())
+    {
+        // the base form usually doesn't have a caption
+        add_form(&mut forms, mon_name, Some(0));
+    }
+    for (i, image) in images.iter().enumerate() {
+        if let Some(caption) = &image.caption_text {
+            let caption = caption.trim();
+            if !caption.is_empty() {
+                add_form(&mut forms, caption, Some(i));
+            }
+        }
+    }
+    // stats headings aren't form names often enough (e.g. “Generation I-V”) to add forms
+    let labels = types
+        .iter()
+        .map(|(label, _)| label)
+        .chain(abilities.iter().map(|(label, _)| label));
+    for label in labels.flatten() {
+        add_form(&mut forms, label, None);
+    }
+    if forms.is_empty() {
+        add_form(&mut forms, mon_name, images.first().map(|_| 0));
+    }
+
+    for (i, form) in forms.iter_mut().enumerate() {
+        let applies = |label: &Option<String>| match label {
+            Some(label) => labels_match(mon_name, &form.name, label),
+            None => true,
+        };
+        for (_, types) in types.iter().filter(|(label, _)| applies(label)) {
+            for t in types {
+                if !form.types.contains(t) {
+                    form.types.push(t.clone());
+                }
+            }
+        }
+        for (_, ability) in abilities.iter().filter(|(label, _)| applies(label)) {
+            if !form.abilities.iter().any(|a| a.name == ability.name) {
+                form.abilities.push(ability.clone());
+            }
+        }
+        if let Some(stats) = form_stats(&stats, mon_name, &form.name, i == 0) {
+            form.stats = stats.clone();
+        }
+    }
+
+    Ok(forms)
+}
+
+/// Compares form labels, which aren't always written the same way: the image might say
+/// “Alolan Form” where the abilities say “Alolan Vulpix”.
+pub fn labels_match(mon_name: &str, a: &str, b: &str) -> bool {
+    fn normalize(mon_name: &str, label: &str) -> String {
+        label
+            .replace(mon_name, "")
+            .replace("Form", "")
+            .replace("form", "")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase()
+    }
+    normalize(mon_name, a) == normalize(mon_name, b)
+}
+
+/// Finds the table cell a `<small>` label belongs to: the nearest cell containing a match.
+fn labelled_cell(small: &NodeRef, row: &NodeRef, selector: &str) -> Option<NodeRef> {
+    small
+        .ancestors()
+        .take_while(|node| node != row)
+        .find(|node| {
+            node.as_element().is_some_and(|el| &*el.name.local == "td")
+                && node.select_first(selector).is_ok()
+        })
+}
+
+fn read_type_cells(row: &NodeRef) -> Labelled<Vec<String>> {
+    const SELECTOR: &str = "a[href$='_(type)']";
+    let mut cells = Vec::new();
+    let mut labelled_links = Vec::new();
+
+    if let Ok(smalls) = row.select("small") {
+        for small in smalls {
+            let Some(cell) = labelled_cell(small.as_node(), row, SELECTOR) else {
+                continue;
+            };
+            let label = small.text_contents().trim().to_string();
+            let mut types = Vec::new();
+            for link in cell.select(SELECTOR).into_iter().flatten() {
+                let name = link.text_contents().trim().to_string();
+                if !name.is_empty() && !types.contains(&name) {
+                    types.push(name);
+                }
+                labelled_links.push(link.as_node().clone());
+            }
+            cells.push((Some(label), types));
+        }
+    }
+
+    // types that apply regardless of form
+    let mut types = Vec::new();
+    for link in row.select(SELECTOR).into_iter().flatten() {
+        if labelled_links.contains(link.as_node()) || is_hidden_within(link.as_node(), row) {
+            continue;
+        }
+        let name = link.text_contents().trim().to_string();
+        if !name.is_empty() && !types.contains(&name) {
+            types.push(name);
+        }
+    }
+    if !types.is_empty() {
+        cells.push((None, types));
+    }
+
+    cells
+}
+
+fn read_ability_cells(page: &Page, row: &NodeRef) -> anyhow::Result<Labelled<FormAbility>> {
+    const SELECTOR: &str = "a[href$='_(Ability)']";
+    let mut abilities = Vec::new();
+
+    for link in row.select(SELECTOR).into_iter().flatten() {
+        let link = link.as_node();
+        let name = link.text_contents().trim().to_string();
+        if name.is_empty() {
+            continue;
+        }
+        let id = match get_attr(link, "href") {
+            Some(href) => match page.index.links.resolve(&page.base_url.join(&href)?, false) {
+                Some(EntryRef::Ability(id)) => Some(id),
+                _ => None,
+            },
+            None => None,
+        };
+
+        let cell = link
+            .ancestors()
+            .take_while(|node| node != row)
+            .find(|node| node.as_element().is_some_and(|el| &*el.name.local == "td"));
+        let caption = cell
+            .and_then(|cell| cell.select_first("small").ok())
+            .map(|small| small.text_contents())
+            .unwrap_or_default();
+        let hidden = caption.contains("Hidden Ability");
+        let label = caption.replace("Hidden Ability", "").trim().to_string();
+
+        abilities.push((
+            (!label.is_empty()).then_some(label),
+            FormAbility { name, id, hidden },
+        ));
+    }
+
+    Ok(abilities)
+}
+
+/// The stat table for a form: the one labelled with its name, or else the unlabelled one. The
+/// base form (`is_base`) falls back to the first table, whatever its label.
+fn form_stats<'a>(
+    stats: &'a Labelled<Vec<(String, u32)>>,
+    mon_name: &str,
+    form_name: &str,
+    is_base: bool,
+) -> Option<&'a Vec<(String, u32)>> {
+    let labelled = stats.iter().find(|(label, _)| {
+        label
+            .as_ref()
+            .is_some_and(|label| labels_match(mon_name, form_name, label))
+    });
+    labelled
+        .or(stats.iter().find(|(label, _)| label.is_none()))
+        .or(stats.first().filter(|_| is_base))
+        .map(|(_, stats)| stats)
+}
+
+/// Whether a heading in the “Base stats” section names the games some stats apply to, e.g.
+/// “Generation I-V” or “Generation VI onward”, rather than a form.
+fn is_generation_heading(text: &str) -> bool {
+    text.starts_with("Generation")
+}
+
+/// Reads the “Base stats” section, which has one stat table per form, each under a heading
+/// with the form name if there's more than one. Stats that changed between games are split
+/// under generation headings, of which only the latest table is kept.
+fn read_base_stats(doc: &NodeRef) -> Labelled<Vec<(String, u32)>> {
+    let mut tables = Vec::new();
+
+    let Some(heading) = doc
+        .select_first("#Base_stats")
+        .ok()
+        .and_then(|headline| headline.as_node().parent())
+    else {
+        return tables;
+    };
+    let Some(level) = heading_level(&heading) else {
+        return tables;
+    };
+
+    // the form heading the current table is under, with its level
+    let mut form: Option<(u32, String)> = None;
+    let mut sibling = heading.next_sibling();
+    while let Some(node) = sibling {
+        sibling = node.next_sibling();
+        match heading_level(&node) {
+            Some(l) if l <= level => break,
+            Some(l) => {
+                let text = node
+                    .select_first(".mw-headline")
+                    .map(|h| h.text_contents())
+                    .unwrap_or_else(|()| node.text_contents());
+                let text = text.trim().to_string();
+                if form
+                    .as_ref()
+                    .is_some_and(|(form_level, _)| *form_level >= l)
+                {
+                    form = None;
+                }
+                if !is_generation_heading(&text) {
+                    form = Some((l, text));
+                }
+                continue;
+            }
+            None => (),
+        }
+
+        let stats = read_stat_table(&node);
+        if stats.is_empty() {
+            continue;
+        }
+        let label = form.as_ref().map(|(_, text)| text.clone());
+        // tables for later games come later
+        match tables.iter_mut().find(|(l, _)| *l == label) {
+            Some((_, old)) => *old = stats,
+            None => tables.push((label, stats)),
+        }
+    }
+
+    tables
+}
+
+fn heading_level(node: &NodeRef) -> Option<u32> {
+    let el = node.as_element()?;
+    let level = el.name.local.strip_prefix('h')?.parse().ok()?;
+    (1..=6).contains(&level).then_some(level)
+}
+
+fn read_stat_table(node: &NodeRef) -> Vec<(String, u32)> {
+    let mut stats = Vec::new();
+    let Ok(rows) = node.select("tr") else {
+        return stats;
+    };
+    for row in rows {
+        let text = row.text_contents();
+        let Some((name, value)) = text.split_once(':') else {
+            continue;
+        };
+        let name = name.trim();
+        if !STAT_NAMES.contains(&name) || stats.iter().any(|(n, _)| n == name) {
+            continue;
+        }
+        let digits: String = value
+            .trim_start()
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        if let Ok(value) = digits.parse() {
+            stats.push((name.to_string(), value));
+        }
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kuchikiki::traits::TendrilSink;
+
+    fn stat_table(values: [u32; 6]) -> String {
+        let rows: String = STAT_NAMES
+            .iter()
+            .zip(values)
+            .map(|(name, value)| format!("<tr><th>{name}:</th><td>{value}</td></tr>"))
+            .collect();
+        format!("<table>{rows}</table>")
+    }
+
+    fn stats(values: [u32; 6]) -> Vec<(String, u32)> {
+        STAT_NAMES
+            .iter()
+            .zip(values)
+            .map(|(name, value)| (name.to_string(), value))
+            .collect()
+    }
+
+    #[test]
+    fn generation_split_stats() {
+        // like Pikachu's page, whose stats changed in Generation VI
+        let html = format!(
+            r#"<h3><span class="mw-headline" id="Base_stats">Base stats</span></h3>
+            <h4><span class="mw-headline" id="Generation_I-V">Generation I-V</span></h4>
+            {}
+            <h4><span class="mw-headline" id="Generation_VI_onward">Generation VI onward</span></h4>
+            {}
+            <h3><span class="mw-headline" id="Type_effectiveness">Type effectiveness</span></h3>
+            {}"#,
+            stat_table([35, 55, 30, 50, 40, 90]),
+            stat_table([35, 55, 40, 50, 50, 90]),
+            stat_table([1, 1, 1, 1, 1, 1]),
+        );
+        let doc = kuchikiki::parse_html().one(html);
+        let tables = read_base_stats(&doc);
+        assert_eq!(tables, [(None, stats([35, 55, 40, 50, 50, 90]))]);
+        assert_eq!(
+            form_stats(&tables, "Pikachu", "Pikachu", true),
+            Some(&stats([35, 55, 40, 50, 50, 90]))
+        );
+    }
+
+    #[test]
+    fn form_and_generation_split_stats() {
+        // forms with their own stats, one of which changed between generations
+        let html = format!(
+            r#"<h3><span class="mw-headline" id="Base_stats">Base stats</span></h3>
+            <h4><span class="mw-headline">Raichu</span></h4>
+            <h5><span class="mw-headline">Generation I-V</span></h5>
+            {}
+            <h5><span class="mw-headline">Generation VI onward</span></h5>
+            {}
+            <h4><span class="mw-headline">Alolan Raichu</span></h4>
+            {}"#,
+            stat_table([60, 90, 55, 90, 80, 100]),
+            stat_table([60, 90, 55, 90, 80, 110]),
+            stat_table([60, 85, 50, 95, 85, 110]),
+        );
+        let doc = kuchikiki::parse_html().one(html);
+        let tables = read_base_stats(&doc);
+        assert_eq!(
+            tables,
+            [
+                (Some("Raichu".to_string()), stats([60, 90, 55, 90, 80, 110])),
+                (
+                    Some("Alolan Raichu".to_string()),
+                    stats([60, 85, 50, 95, 85, 110])
+                ),
+            ]
+        );
+        assert_eq!(
+            form_stats(&tables, "Raichu", "Alolan Form", false),
+            Some(&stats([60, 85, 50, 95, 85, 110]))
+        );
+        assert_eq!(
+            form_stats(&tables, "Raichu", "Raichu", true),
+            Some(&stats([60, 90, 55, 90, 80, 110]))
+        );
+    }
+
+    #[test]
+    fn generation_headings() {
+        assert!(is_generation_heading("Generation I-V"));
+        assert!(is_generation_heading("Generations II-V"));
+        assert!(is_generation_heading("Generation VI onward"));
+        assert!(!is_generation_heading("Partner Pikachu"));
+        assert!(!is_generation_heading("Mega Charizard X"));
+    }
+}
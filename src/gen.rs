@@ -1,14 +1,30 @@
-use crate::index::{DexId, Index};
+use crate::ability::{AbilityEntry, AbilityMon};
+use crate::forms::MonForm;
+use crate::index::{DexId, Index, TypeId};
+use crate::items::{ItemEntry, ItemRelation};
+use crate::kana::{simplify_long_vowels, to_hepburn, to_hiragana};
+use crate::links::fix_entry_links;
 use crate::mon::{MonEntry, MonImage};
+use crate::moves::MoveEntry;
+use crate::names::name_variants;
+use crate::page::Reference;
+use crate::types::{defensive_matchups, offensive_matchups, Effectiveness, TypeEntry};
 use crate::xhtml::XhtmlEscaped;
+use crate::Entries;
 use anyhow::{anyhow, bail, Context};
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Write;
 
-pub fn generate_dictionary(
-    index: &Index,
-    pokemon: &BTreeMap<DexId, MonEntry>,
-) -> anyhow::Result<String> {
+/// Renders all entries as Apple DictionaryService XML (`Dictionary.xml`).
+pub fn generate_dictionary(index: &Index, entries: &Entries) -> anyhow::Result<String> {
+    let Entries {
+        pokemon,
+        abilities,
+        moves,
+        items,
+        types,
+    } = entries;
+
     let mut out = String::from(
         r#"<?xml version="1.0" encoding="UTF-8"?>
 <!-- generated file -->
@@ -20,18 +36,55 @@ pub fn generate_dictionary(
 
     for (id, mon) in pokemon {
         generate_mon(&mut out, mon).with_context(|| format!("error generating entry {id}"))?;
+        for i in (0..mon.forms.len()).filter(|i| mon.has_form_entry(*i)) {
+            generate_mon_form(&mut out, mon, i)
+                .with_context(|| format!("error generating form {i} of entry {id}"))?;
+        }
+    }
+
+    let mut categories: BTreeMap<&str, Vec<&MonEntry>> = BTreeMap::new();
+    for mon in pokemon.values() {
+        for category in &mon.categories_text {
+            categories.entry(category).or_default().push(mon);
+        }
+    }
+    for (category, members) in categories {
+        if members.len() > 1 {
+            generate_category(&mut out, category, &members, pokemon)
+                .with_context(|| format!("error generating category {category}"))?;
+        }
+    }
+
+    for (id, ability) in abilities {
+        generate_ability(&mut out, ability, pokemon)
+            .with_context(|| format!("error generating ability {id}"))?;
+    }
+
+    for (id, move_entry) in moves {
+        generate_move(&mut out, move_entry, pokemon)
+            .with_context(|| format!("error generating move {id}"))?;
+    }
+
+    for (id, item) in items {
+        generate_item(&mut out, item, pokemon)
+            .with_context(|| format!("error generating item {id}"))?;
+    }
+
+    for (id, type_entry) in types {
+        generate_type(&mut out, index, type_entry, pokemon)
+            .with_context(|| format!("error generating type {id}"))?;
     }
 
     write!(out, "</d:dictionary>")?;
 
-    Ok(out)
+    Ok(fix_entry_links(&out))
 }
 
-fn text(s: &str) -> XhtmlEscaped {
+fn text(s: &str) -> XhtmlEscaped<'_> {
     XhtmlEscaped(s, false)
 }
 
-fn attr(s: &str) -> XhtmlEscaped {
+fn attr(s: &str) -> XhtmlEscaped<'_> {
     XhtmlEscaped(s, true)
 }
 
@@ -76,16 +129,19 @@ fn generate_front_matter(
         <p>An index of {} Pokémon across {} generations.</p>
         <ul>"#,
         index.pokemon_pages.len(),
-        index.pokemon_gens.len(),
+        index.selected_gens().len(),
     )?;
-    for gen in 0..index.pokemon_gens.len() {
-        let gen = gen + 1;
+    for gen in index.selected_gens() {
         writeln!(
             out,
             r#"<li><a href="x-dictionary:r:list-of-pokemon-gen-{gen}">Generation {}</a></li>"#,
             text(&roman_numerals(gen)),
         )?;
     }
+    writeln!(out, r#"</ul><h2>Types</h2><ul class="type-list">"#)?;
+    for id in index.type_pages.keys() {
+        writeln!(out, r#"<li>{}</li>"#, type_link(id.name()))?;
+    }
     writeln!(
         out,
         r#"</ul>
@@ -98,11 +154,7 @@ fn generate_front_matter(
     let mut prev_gen = 0;
     for (id, entry) in pokemon {
         let gen = index
-            .pokemon_gens
-            .iter()
-            .enumerate()
-            .find(|(_, i)| i.contains(id))
-            .map(|(i, _)| i + 1)
+            .generation_of(*id)
             .ok_or(anyhow!("could not find generation for {id}"))?;
 
         if gen != prev_gen {
@@ -125,32 +177,129 @@ fn generate_front_matter(
             writeln!(out, r#"<ul class="list-of-pokemon">"#)?;
         }
 
-        let (menu_id, menu_image_id) = pokemon
-            .get(&id.next())
-            .and_then(|entry| entry.prev_entry.as_ref())
-            .or(id
-                .prev()
-                .and_then(|id| pokemon.get(&id).and_then(|entry| entry.next_entry.as_ref())))
-            .ok_or(anyhow!("could not find menu image for {id}"))?;
-        if menu_id != id {
-            bail!("missing entry before or after {id}??");
-        }
-        let image_url = format!("images/{}", urlencoding::encode(menu_image_id));
+        generate_pokemon_list_item(out, pokemon, entry, None, None)?;
+    }
+
+    if prev_gen != 0 {
+        writeln!(out, r#"</ul></div></d:entry>"#)?;
+    }
+
+    Ok(())
+}
+
+/// The `d:entry` ID of the list of Pokémon in a category.
+fn category_entry_id(category: &str) -> String {
+    let slug: String = category
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    format!("category-{slug}")
+}
+
+/// Generates a list entry for a category shared by several Pokémon, e.g. “Mouse Pokémon”.
+fn generate_category(
+    out: &mut String,
+    category: &str,
+    members: &[&MonEntry],
+    pokemon: &BTreeMap<DexId, MonEntry>,
+) -> anyhow::Result<()> {
+    writeln!(
+        out,
+        r#"<d:entry id="{}" d:title="{}">"#,
+        attr(&category_entry_id(category)),
+        attr(category)
+    )?;
+    writeln!(out, r#"<d:index d:value="{}" />"#, attr(category))?;
+    writeln!(
+        out,
+        r#"<div class="outer-container"><h1>{}</h1>"#,
+        text(category)
+    )?;
+    writeln!(out, r#"<ul class="list-of-pokemon">"#)?;
+    for mon in members {
+        generate_pokemon_list_item(out, pokemon, mon, None, None)?;
+    }
+    writeln!(out, r#"</ul></div></d:entry>"#)?;
+    Ok(())
+}
 
-        writeln!(out, r#"<li data-id="{id}">"#)?;
-        writeln!(out, r#"<div class="dex-id">{id}</div>"#)?;
-        writeln!(out, r#"<img src="{}" alt="" />"#, attr(&image_url))?;
+/// The small sprite shown in lists of Pokémon, which we only get from the neighboring entries.
+/// Falls back to the entry's own image if neither neighbor was selected.
+fn menu_image_url(pokemon: &BTreeMap<DexId, MonEntry>, id: DexId) -> anyhow::Result<String> {
+    let menu_image = pokemon
+        .get(&id.next())
+        .and_then(|entry| entry.prev_entry.as_ref())
+        .or(id
+            .prev()
+            .and_then(|id| pokemon.get(&id).and_then(|entry| entry.next_entry.as_ref())));
+    let Some((menu_id, menu_image_id)) = menu_image else {
+        return pokemon
+            .get(&id)
+            .and_then(|entry| entry.images.first())
+            .map(|image| image.src.clone())
+            .ok_or(anyhow!("could not find menu image for {id}"));
+    };
+    if *menu_id != id {
+        bail!("missing entry before or after {id}??");
+    }
+    Ok(format!("images/{}", urlencoding::encode(menu_image_id)))
+}
+
+/// Writes an item for a `list-of-pokemon` list, with an optional note next to the name.
+fn generate_pokemon_list_item(
+    out: &mut String,
+    pokemon: &BTreeMap<DexId, MonEntry>,
+    entry: &MonEntry,
+    form: Option<&str>,
+    note: Option<&str>,
+) -> anyhow::Result<()> {
+    let id = entry.dex_id;
+    let image_url = menu_image_url(pokemon, id)?;
+
+    // link straight to the form's entry if it has one
+    let target = form
+        .and_then(|form| entry.find_form(form))
+        .filter(|i| entry.has_form_entry(*i))
+        .map(|i| entry.form_entry_id(i))
+        .unwrap_or_else(|| format!("pokemon-{}", id.0));
+
+    writeln!(out, r#"<li data-id="{id}">"#)?;
+    writeln!(out, r#"<div class="dex-id">{id}</div>"#)?;
+    writeln!(out, r#"<img src="{}" alt="" />"#, attr(&image_url))?;
+    let link = format!(
+        r#"<a href="x-dictionary:r:{}" class="entry-name">{}</a>"#,
+        attr(&target),
+        text(&entry.name)
+    );
+    if let Some(note) = note {
         writeln!(
             out,
-            r#"<a href="x-dictionary:r:pokemon-{}" class="entry-name">{}</a>"#,
-            id.0,
-            text(&entry.name)
+            r#"<div class="entry-label">{link} <small class="entry-note">{}</small></div>"#,
+            text(note)
         )?;
-        writeln!(out, r#"</li>"#)?;
+    } else {
+        writeln!(out, "{link}")?;
     }
+    writeln!(out, r#"</li>"#)?;
+    Ok(())
+}
 
-    writeln!(out, r#"</ul></div></d:entry>"#)?;
-
+fn generate_references(out: &mut String, references: &[Reference]) -> anyhow::Result<()> {
+    if references.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, r#"<div class="references"><h2>References</h2><ol>"#)?;
+    for reference in references {
+        writeln!(
+            out,
+            r##"<li id="{}"><a href="#{}" class="reference-backlink">↑</a> {}</li>"##,
+            attr(&reference.id),
+            attr(&reference.backlink),
+            raw(&reference.html)
+        )?;
+    }
+    writeln!(out, r#"</ol></div>"#)?;
     Ok(())
 }
 
@@ -166,7 +315,19 @@ fn generate_mon(out: &mut String, mon: &MonEntry) -> anyhow::Result<()> {
         .into_iter()
         .collect();
     writeln!(out, r#"<d:index d:value="{}" />"#, attr(&mon.name))?;
-    writeln!(out, r#"<d:index d:value="{}" />"#, attr(&mon.name_jp_text))?;
+    generate_japanese_keys(out, mon, &mut names_seen)?;
+    for category in &mon.categories_text {
+        if names_seen.insert(category.clone()) {
+            writeln!(
+                out,
+                r#"<d:index d:value="{}" d:title="{}" d:anchor="xpointer(//*[@id='pokemon-categories'])" />"#,
+                attr(category),
+                attr(&mon.name)
+            )?;
+        }
+    }
+    generate_name_variants(out, &mon.name, &mut names_seen)?;
+    generate_dex_number_keys(out, mon, &mut names_seen)?;
 
     for (i, img) in mon.images.iter().enumerate() {
         if let Some(text) = img.caption_text.as_deref() {
@@ -180,11 +341,17 @@ fn generate_mon(out: &mut String, mon: &MonEntry) -> anyhow::Result<()> {
             if names_seen.contains(&name) {
                 continue;
             }
+            let anchor = match mon.forms.iter().position(|form| form.image == Some(i)) {
+                // indexed by the form's own entry instead
+                Some(form) if mon.has_form_entry(form) => continue,
+                Some(form) => format!("pokemon-form-{form}"),
+                None => format!("pokemon-image-{i}"),
+            };
             writeln!(
                 out,
-                r#"<d:index d:value="{}" d:anchor="xpointer(//*[@id='pokemon-image-{}'])" />"#,
+                r#"<d:index d:value="{}" d:anchor="xpointer(//*[@id='{}'])" />"#,
                 attr(&name),
-                i
+                anchor
             )?;
             names_seen.insert(name);
         }
@@ -193,7 +360,10 @@ fn generate_mon(out: &mut String, mon: &MonEntry) -> anyhow::Result<()> {
     writeln!(out, r#"<div class="outer-container">"#)?;
     writeln!(out, r#"<div class="pokedex-id">{}</div>"#, mon.dex_id)?;
     writeln!(out, r#"<h1 class="pokemon-name">{}</h1>"#, text(&mon.name))?;
-    writeln!(out, r#"<ul class="pokemon-categories">"#)?;
+    writeln!(
+        out,
+        r#"<ul class="pokemon-categories" id="pokemon-categories">"#
+    )?;
     for category in &mon.categories_html {
         writeln!(out, r#"<li>{}</li>"#, raw(category))?;
     }
@@ -225,7 +395,7 @@ fn generate_mon(out: &mut String, mon: &MonEntry) -> anyhow::Result<()> {
     let mut i = 0;
     while i < mon.images.len() {
         let image = &mon.images[i];
-        if image.flex && mon.images.get(i + 1).map_or(false, |i| i.flex) {
+        if image.flex && mon.images.get(i + 1).is_some_and(|i| i.flex) {
             writeln!(out, r#"<li class="pokemon-images-flex"><ul>"#)?;
             while i < mon.images.len() && mon.images[i].flex {
                 render_image(out, &mon.images[i], i)?;
@@ -255,6 +425,8 @@ fn generate_mon(out: &mut String, mon: &MonEntry) -> anyhow::Result<()> {
     }
     writeln!(out, r#"</tbody></table>"#)?;
 
+    generate_forms(out, mon)?;
+
     writeln!(out, "{}", raw(&mon.summary_html))?;
 
     writeln!(
@@ -269,6 +441,8 @@ fn generate_mon(out: &mut String, mon: &MonEntry) -> anyhow::Result<()> {
 
     writeln!(out, "{}", raw(&mon.body_html))?;
 
+    generate_references(out, &mon.references)?;
+
     writeln!(
         out,
         r#"<div class="footer-read-more"><a href="{}">Read more on Bulbapedia</a></div>"#,
@@ -279,3 +453,622 @@ fn generate_mon(out: &mut String, mon: &MonEntry) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Adds index keys for alternate spellings of a name, titled with the real name.
+fn generate_name_variants(
+    out: &mut String,
+    name: &str,
+    names_seen: &mut BTreeSet<String>,
+) -> anyhow::Result<()> {
+    for variant in name_variants(name) {
+        generate_index_key(out, &variant, name, names_seen)?;
+    }
+    Ok(())
+}
+
+/// Adds index keys for the Japanese name with its reading, the hiragana spelling and romaji
+/// (both the trademarked romanization and Hepburn).
+fn generate_japanese_keys(
+    out: &mut String,
+    mon: &MonEntry,
+    names_seen: &mut BTreeSet<String>,
+) -> anyhow::Result<()> {
+    let name_jp = &mon.name_jp_text;
+    let yomi = to_hiragana(name_jp);
+    writeln!(
+        out,
+        r#"<d:index d:value="{}" d:yomi="{}" />"#,
+        attr(name_jp),
+        attr(&yomi)
+    )?;
+    if names_seen.insert(yomi.clone()) {
+        writeln!(
+            out,
+            r#"<d:index d:value="{}" d:yomi="{}" d:title="{}" />"#,
+            attr(&yomi),
+            attr(&yomi),
+            attr(&mon.name)
+        )?;
+    }
+
+    let mut romaji = vec![mon.name_jp_translit_text.clone()];
+    if let Some(hepburn) = to_hepburn(name_jp) {
+        romaji.push(simplify_long_vowels(&hepburn));
+        romaji.push(hepburn);
+    }
+    for key in romaji.iter().filter(|key| !key.is_empty()) {
+        generate_index_key(out, key, &mon.name, names_seen)?;
+    }
+    Ok(())
+}
+
+/// Adds index keys for the national and regional Pokédex numbers, e.g. “#25”, “#0025”, “025”
+/// and “Galar #025”.
+fn generate_dex_number_keys(
+    out: &mut String,
+    mon: &MonEntry,
+    names_seen: &mut BTreeSet<String>,
+) -> anyhow::Result<()> {
+    let n = mon.dex_id.0;
+    for key in [format!("#{n}"), format!("#{n:04}"), format!("{n:03}")] {
+        generate_index_key(out, &key, &mon.name, names_seen)?;
+    }
+    for id in &mon.regional_ids {
+        let (dex, n) = (&id.dex, id.number);
+        for key in [
+            format!("{dex} #{n:03}"),
+            format!("{dex} {n:03}"),
+            format!("{dex} #{n}"),
+        ] {
+            generate_index_key(out, &key, &mon.name, names_seen)?;
+        }
+    }
+    Ok(())
+}
+
+fn generate_index_key(
+    out: &mut String,
+    key: &str,
+    title: &str,
+    names_seen: &mut BTreeSet<String>,
+) -> anyhow::Result<()> {
+    if names_seen.insert(key.to_string()) {
+        writeln!(
+            out,
+            r#"<d:index d:value="{}" d:title="{}" />"#,
+            attr(key),
+            attr(title)
+        )?;
+    }
+    Ok(())
+}
+
+/// Renders a block per form with its types, abilities and base stats.
+fn generate_forms(out: &mut String, mon: &MonEntry) -> anyhow::Result<()> {
+    writeln!(out, r#"<div class="pokemon-forms">"#)?;
+    for (i, form) in mon.forms.iter().enumerate() {
+        writeln!(out, r#"<div class="pokemon-form" id="pokemon-form-{i}">"#)?;
+        if mon.has_form_entry(i) {
+            writeln!(
+                out,
+                r#"<h3 class="form-name"><a href="x-dictionary:r:{}">{}</a></h3>"#,
+                attr(&mon.form_entry_id(i)),
+                text(&form.name)
+            )?;
+        } else if mon.forms.len() > 1 {
+            writeln!(out, r#"<h3 class="form-name">{}</h3>"#, text(&form.name))?;
+        }
+        // with a single form, the entry's main image shows it already
+        let image = form.image.filter(|_| mon.forms.len() > 1);
+        if let Some(image) = image.and_then(|i| mon.images.get(i)) {
+            writeln!(
+                out,
+                r#"<img class="form-image" alt="{}" src="{}" />"#,
+                attr(&image.alt),
+                attr(&image.src)
+            )?;
+        }
+        generate_form_info(out, form)?;
+        writeln!(out, r#"</div>"#)?;
+    }
+    writeln!(out, r#"</div>"#)?;
+    Ok(())
+}
+
+fn generate_form_info(out: &mut String, form: &MonForm) -> anyhow::Result<()> {
+    writeln!(out, r#"<table class="form-info"><tbody>"#)?;
+    if !form.types.is_empty() {
+        write!(out, r#"<tr><th>Type</th><td>"#)?;
+        for (j, name) in form.types.iter().enumerate() {
+            if j > 0 {
+                write!(out, " / ")?;
+            }
+            write!(out, "{}", type_link(name))?;
+        }
+        writeln!(out, r#"</td></tr>"#)?;
+    }
+    if !form.abilities.is_empty() {
+        write!(
+            out,
+            r#"<tr><th>Abilities</th><td><ul class="form-abilities">"#
+        )?;
+        for ability in &form.abilities {
+            write!(out, "<li>")?;
+            match ability.id {
+                Some(id) => write!(
+                    out,
+                    r#"<a href="x-dictionary:r:ability-{}">{}</a>"#,
+                    id.0,
+                    text(&ability.name)
+                )?,
+                None => write!(out, "{}", text(&ability.name))?,
+            }
+            if ability.hidden {
+                write!(out, r#" <small class="entry-note">Hidden Ability</small>"#)?;
+            }
+            write!(out, "</li>")?;
+        }
+        writeln!(out, r#"</ul></td></tr>"#)?;
+    }
+    if !form.stats.is_empty() {
+        for (name, value) in &form.stats {
+            writeln!(out, r#"<tr><th>{}</th><td>{value}</td></tr>"#, text(name))?;
+        }
+        let total: u32 = form.stats.iter().map(|(_, value)| value).sum();
+        writeln!(
+            out,
+            r#"<tr class="stat-total"><th>Total</th><td>{total}</td></tr>"#
+        )?;
+    }
+    writeln!(out, r#"</tbody></table>"#)?;
+    Ok(())
+}
+
+/// Generates a separate entry for an alternate form (Mega Evolution, Gigantamax, etc.), so
+/// looking it up doesn't land at the top of the base entry.
+fn generate_mon_form(out: &mut String, mon: &MonEntry, i: usize) -> anyhow::Result<()> {
+    let form = &mon.forms[i];
+    let title = form.title(&mon.name);
+    writeln!(
+        out,
+        r#"<d:entry id="{}" d:title="{}">"#,
+        attr(&mon.form_entry_id(i)),
+        attr(&title),
+    )?;
+    writeln!(out, r#"<d:index d:value="{}" />"#, attr(&title))?;
+    let mut names_seen = BTreeSet::from([title.clone()]);
+    generate_name_variants(out, &title, &mut names_seen)?;
+
+    writeln!(out, r#"<div class="outer-container">"#)?;
+    writeln!(out, r#"<div class="pokedex-id">{}</div>"#, mon.dex_id)?;
+    writeln!(out, r#"<h1 class="pokemon-name">{}</h1>"#, text(&title))?;
+    writeln!(
+        out,
+        r#"<div class="form-of">Form of <a href="x-dictionary:r:pokemon-{}">{}</a></div>"#,
+        mon.dex_id.0,
+        text(&mon.name)
+    )?;
+
+    if let Some(image) = form.image.and_then(|i| mon.images.get(i)) {
+        writeln!(
+            out,
+            r#"<ul class="pokemon-images"><li class="pokemon-image">"#
+        )?;
+        writeln!(
+            out,
+            r#"<img alt="{}" src="{}" style="width: {}px" />"#,
+            attr(&image.alt),
+            attr(&image.src),
+            image.width
+        )?;
+        writeln!(out, r#"</li></ul>"#)?;
+    }
+
+    writeln!(out, r#"<div class="pokemon-form">"#)?;
+    generate_form_info(out, form)?;
+    writeln!(out, r#"</div>"#)?;
+
+    writeln!(
+        out,
+        r#"<div class="footer-read-more"><a href="{}">Read more on Bulbapedia</a></div>"#,
+        attr(&mon.url)
+    )?;
+
+    writeln!(out, r#"</div></d:entry>"#)?;
+
+    Ok(())
+}
+
+fn generate_ability(
+    out: &mut String,
+    ability: &AbilityEntry,
+    pokemon: &BTreeMap<DexId, MonEntry>,
+) -> anyhow::Result<()> {
+    writeln!(
+        out,
+        r#"<d:entry id="ability-{}" d:title="{}">"#,
+        ability.id.0,
+        attr(&ability.name),
+    )?;
+    writeln!(out, r#"<d:index d:value="{}" />"#, attr(&ability.name))?;
+    if let Some(name_jp) = &ability.name_jp_text {
+        writeln!(out, r#"<d:index d:value="{}" />"#, attr(name_jp))?;
+    }
+
+    writeln!(out, r#"<div class="outer-container">"#)?;
+    writeln!(
+        out,
+        r#"<div class="ability-id">Ability {}</div>"#,
+        ability.id
+    )?;
+    writeln!(
+        out,
+        r#"<h1 class="ability-name">{}</h1>"#,
+        text(&ability.name)
+    )?;
+    if let Some(name_jp) = &ability.name_jp_html {
+        writeln!(
+            out,
+            r#"<div class="ability-name-jp">{}</div>"#,
+            raw(name_jp)
+        )?;
+    }
+
+    writeln!(out, "{}", raw(&ability.summary_html))?;
+
+    if !ability.effects.is_empty() {
+        writeln!(out, r#"<h2>Effect</h2>"#)?;
+        for effect in &ability.effects {
+            if !effect.title.is_empty() {
+                writeln!(out, r#"<h3>{}</h3>"#, text(&effect.title))?;
+            }
+            writeln!(out, "{}", raw(&effect.html))?;
+        }
+    }
+
+    let holders: Vec<_> = ability
+        .pokemon
+        .iter()
+        .filter_map(|mon| Some((mon, pokemon.get(&mon.dex_id)?)))
+        .collect();
+    if !holders.is_empty() {
+        writeln!(out, r#"<h2>Pokémon with {}</h2>"#, text(&ability.name))?;
+        writeln!(out, r#"<ul class="list-of-pokemon">"#)?;
+        for (mon, entry) in holders {
+            let note = ability_mon_note(mon);
+            generate_pokemon_list_item(out, pokemon, entry, mon.form.as_deref(), note.as_deref())?;
+        }
+        writeln!(out, r#"</ul>"#)?;
+    }
+
+    generate_references(out, &ability.references)?;
+
+    writeln!(
+        out,
+        r#"<div class="footer-read-more"><a href="{}">Read more on Bulbapedia</a></div>"#,
+        attr(&ability.url)
+    )?;
+
+    writeln!(out, r#"</div></d:entry>"#)?;
+
+    Ok(())
+}
+
+fn ability_mon_note(mon: &AbilityMon) -> Option<String> {
+    match (&mon.form, mon.hidden) {
+        (Some(form), true) => Some(format!("{form}, Hidden Ability")),
+        (Some(form), false) => Some(form.clone()),
+        (None, true) => Some("Hidden Ability".to_string()),
+        (None, false) => None,
+    }
+}
+
+fn generate_move(
+    out: &mut String,
+    move_entry: &MoveEntry,
+    pokemon: &BTreeMap<DexId, MonEntry>,
+) -> anyhow::Result<()> {
+    writeln!(
+        out,
+        r#"<d:entry id="move-{}" d:title="{}">"#,
+        move_entry.id.0,
+        attr(&move_entry.name),
+    )?;
+    writeln!(out, r#"<d:index d:value="{}" />"#, attr(&move_entry.name))?;
+    if let Some(name_jp) = &move_entry.name_jp_text {
+        writeln!(out, r#"<d:index d:value="{}" />"#, attr(name_jp))?;
+    }
+
+    writeln!(out, r#"<div class="outer-container">"#)?;
+    writeln!(out, r#"<div class="move-id">Move {}</div>"#, move_entry.id)?;
+    writeln!(
+        out,
+        r#"<h1 class="move-name">{}</h1>"#,
+        text(&move_entry.name)
+    )?;
+    if let Some(name_jp) = &move_entry.name_jp_html {
+        writeln!(out, r#"<div class="move-name-jp">{}</div>"#, raw(name_jp))?;
+    }
+
+    writeln!(out, r#"<table class="roundy move-info-box"><tbody>"#)?;
+    for (label, value) in [
+        ("Type", &move_entry.type_name),
+        ("Category", &move_entry.category),
+        ("Power", &move_entry.power),
+        ("Accuracy", &move_entry.accuracy),
+        ("PP", &move_entry.pp),
+    ] {
+        writeln!(
+            out,
+            r#"<tr><th>{}</th><td>{}</td></tr>"#,
+            text(label),
+            text(value)
+        )?;
+    }
+    writeln!(out, r#"</tbody></table>"#)?;
+
+    writeln!(out, "{}", raw(&move_entry.summary_html))?;
+
+    if !move_entry.effects.is_empty() {
+        writeln!(out, r#"<h2>Effect</h2>"#)?;
+        for effect in &move_entry.effects {
+            if !effect.title.is_empty() {
+                writeln!(out, r#"<h3>{}</h3>"#, text(&effect.title))?;
+            }
+            writeln!(out, "{}", raw(&effect.html))?;
+        }
+    }
+
+    let mut methods: Vec<&str> = Vec::new();
+    for learner in &move_entry.learners {
+        if !methods.contains(&&*learner.method) {
+            methods.push(&learner.method);
+        }
+    }
+    if !methods.is_empty() {
+        writeln!(out, r#"<h2>Learnset</h2>"#)?;
+    }
+    for method in methods {
+        let learners: Vec<_> = move_entry
+            .learners
+            .iter()
+            .filter(|learner| learner.method == method)
+            .filter_map(|learner| Some((learner, pokemon.get(&learner.dex_id)?)))
+            .collect();
+        if learners.is_empty() {
+            continue;
+        }
+        writeln!(out, r#"<h3>By {}</h3>"#, text(method))?;
+        writeln!(out, r#"<ul class="list-of-pokemon">"#)?;
+        for (learner, entry) in learners {
+            let form = learner.form.as_deref();
+            generate_pokemon_list_item(out, pokemon, entry, form, form)?;
+        }
+        writeln!(out, r#"</ul>"#)?;
+    }
+
+    generate_references(out, &move_entry.references)?;
+
+    writeln!(
+        out,
+        r#"<div class="footer-read-more"><a href="{}">Read more on Bulbapedia</a></div>"#,
+        attr(&move_entry.url)
+    )?;
+
+    writeln!(out, r#"</div></d:entry>"#)?;
+
+    Ok(())
+}
+
+fn generate_item(
+    out: &mut String,
+    item: &ItemEntry,
+    pokemon: &BTreeMap<DexId, MonEntry>,
+) -> anyhow::Result<()> {
+    writeln!(
+        out,
+        r#"<d:entry id="item-{}" d:title="{}">"#,
+        attr(&item.id.0),
+        attr(&item.name),
+    )?;
+    writeln!(out, r#"<d:index d:value="{}" />"#, attr(&item.name))?;
+    if let Some(name_jp) = &item.name_jp_text {
+        writeln!(out, r#"<d:index d:value="{}" />"#, attr(name_jp))?;
+    }
+
+    writeln!(out, r#"<div class="outer-container">"#)?;
+    if let Some(sprite) = &item.sprite {
+        writeln!(
+            out,
+            r#"<img class="item-sprite" src="{}" alt="" />"#,
+            attr(sprite)
+        )?;
+    }
+    writeln!(out, r#"<h1 class="item-name">{}</h1>"#, text(&item.name))?;
+    if let Some(name_jp) = &item.name_jp_html {
+        writeln!(out, r#"<div class="item-name-jp">{}</div>"#, raw(name_jp))?;
+    }
+
+    writeln!(out, "{}", raw(&item.summary_html))?;
+
+    for (title, sections) in [
+        ("Description", &item.description),
+        ("Effect", &item.effects),
+    ] {
+        if sections.is_empty() {
+            continue;
+        }
+        writeln!(out, r#"<h2>{}</h2>"#, text(title))?;
+        for section in sections {
+            if !section.title.is_empty() {
+                writeln!(out, r#"<h3>{}</h3>"#, text(&section.title))?;
+            }
+            writeln!(out, "{}", raw(&section.html))?;
+        }
+    }
+
+    for (relation, title) in [
+        (ItemRelation::Held, "Held by Pokémon"),
+        (ItemRelation::Evolution, "Evolves Pokémon"),
+    ] {
+        let mons: Vec<_> = item
+            .pokemon
+            .iter()
+            .filter(|mon| mon.relation == relation)
+            .filter_map(|mon| Some((mon, pokemon.get(&mon.dex_id)?)))
+            .collect();
+        if mons.is_empty() {
+            continue;
+        }
+        writeln!(out, r#"<h2>{}</h2>"#, text(title))?;
+        writeln!(out, r#"<ul class="list-of-pokemon">"#)?;
+        for (mon, entry) in mons {
+            let form = mon.form.as_deref();
+            generate_pokemon_list_item(out, pokemon, entry, form, form)?;
+        }
+        writeln!(out, r#"</ul>"#)?;
+    }
+
+    generate_references(out, &item.references)?;
+
+    writeln!(
+        out,
+        r#"<div class="footer-read-more"><a href="{}">Read more on Bulbapedia</a></div>"#,
+        attr(&item.url)
+    )?;
+
+    writeln!(out, r#"</div></d:entry>"#)?;
+
+    Ok(())
+}
+
+fn type_link(name: &str) -> String {
+    match TypeId::from_name(name) {
+        Some(id) => format!(
+            r#"<a href="x-dictionary:r:type-{}" class="type-link">{}</a>"#,
+            id.slug(),
+            text(name)
+        ),
+        None => text(name).to_string(),
+    }
+}
+
+fn generate_type(
+    out: &mut String,
+    index: &Index,
+    type_entry: &TypeEntry,
+    pokemon: &BTreeMap<DexId, MonEntry>,
+) -> anyhow::Result<()> {
+    let name = &type_entry.name;
+    writeln!(
+        out,
+        r#"<d:entry id="type-{}" d:title="{} (type)">"#,
+        type_entry.id.slug(),
+        attr(name),
+    )?;
+    writeln!(out, r#"<d:index d:value="{}" />"#, attr(name))?;
+    writeln!(out, r#"<d:index d:value="{} type" />"#, attr(name))?;
+    writeln!(out, r#"<d:index d:value="{}-type" />"#, attr(name))?;
+    if let Some(name_jp) = &type_entry.name_jp_text {
+        writeln!(out, r#"<d:index d:value="{}" />"#, attr(name_jp))?;
+    }
+
+    writeln!(out, r#"<div class="outer-container">"#)?;
+    writeln!(out, r#"<h1 class="type-name">{} (type)</h1>"#, text(name))?;
+    if let Some(name_jp) = &type_entry.name_jp_html {
+        writeln!(out, r#"<div class="type-name-jp">{}</div>"#, raw(name_jp))?;
+    }
+
+    writeln!(out, "{}", raw(&type_entry.summary_html))?;
+
+    writeln!(out, r#"<h2>Type matchups</h2>"#)?;
+    writeln!(out, r#"<table class="roundy type-matchups"><tbody>"#)?;
+    for (label, types) in [
+        (
+            "Super effective against",
+            offensive_matchups(name, Effectiveness::SuperEffective),
+        ),
+        (
+            "Not very effective against",
+            offensive_matchups(name, Effectiveness::NotVeryEffective),
+        ),
+        (
+            "No effect against",
+            offensive_matchups(name, Effectiveness::NoEffect),
+        ),
+        (
+            "Weak to",
+            defensive_matchups(name, Effectiveness::SuperEffective),
+        ),
+        (
+            "Resistant to",
+            defensive_matchups(name, Effectiveness::NotVeryEffective),
+        ),
+        (
+            "Immune to",
+            defensive_matchups(name, Effectiveness::NoEffect),
+        ),
+    ] {
+        let types = if types.is_empty() {
+            "—".to_string()
+        } else {
+            types
+                .iter()
+                .map(|t| type_link(t))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        writeln!(out, r#"<tr><th>{}</th><td>{types}</td></tr>"#, text(label))?;
+    }
+    writeln!(out, r#"</tbody></table>"#)?;
+
+    if !type_entry.history.is_empty() {
+        writeln!(out, r#"<h2>Battle properties</h2>"#)?;
+        for section in &type_entry.history {
+            if !section.title.is_empty() {
+                writeln!(out, r#"<h3>{}</h3>"#, text(&section.title))?;
+            }
+            writeln!(out, "{}", raw(&section.html))?;
+        }
+    }
+
+    writeln!(out, r#"<h2>{}-type Pokémon</h2>"#, text(name))?;
+    for gen in 1..=index.pokemon_gens.len() {
+        let members: Vec<_> = pokemon
+            .values()
+            .filter(|mon| mon.forms.iter().any(|form| form.types.contains(name)))
+            .filter(|mon| index.generation_of(mon.dex_id) == Some(gen))
+            .collect();
+        if members.is_empty() {
+            continue;
+        }
+        writeln!(out, r#"<h3>Generation {}</h3>"#, text(&roman_numerals(gen)))?;
+        writeln!(out, r#"<ul class="list-of-pokemon">"#)?;
+        for mon in members {
+            // the first form with this type, e.g. Alolan Vulpix for Ice
+            let form = mon.forms.iter().find(|form| form.types.contains(name));
+            let form_types = form.map_or(&mon.types, |form| &form.types);
+            let other_types: Vec<_> = form_types.iter().filter(|t| *t != name).cloned().collect();
+            let note = match form.filter(|form| form.name != mon.forms[0].name) {
+                Some(form) if other_types.is_empty() => Some(form.name.clone()),
+                Some(form) => Some(format!("{}, {}", form.name, other_types.join("/"))),
+                None => (!other_types.is_empty()).then(|| other_types.join("/")),
+            };
+            let form_name = form.map(|form| form.name.as_str());
+            generate_pokemon_list_item(out, pokemon, mon, form_name, note.as_deref())?;
+        }
+        writeln!(out, r#"</ul>"#)?;
+    }
+
+    generate_references(out, &type_entry.references)?;
+
+    writeln!(
+        out,
+        r#"<div class="footer-read-more"><a href="{}">Read more on Bulbapedia</a></div>"#,
+        attr(&type_entry.url)
+    )?;
+
+    writeln!(out, r#"</div></d:entry>"#)?;
+
+    Ok(())
+}
@@ -1,30 +1,56 @@
 use crate::fetcher::Fetcher;
 use anyhow::{bail, Context};
+#[cfg(target_os = "macos")]
 use core_foundation::base::{CFRelease, CFTypeRef, TCFType};
+#[cfg(target_os = "macos")]
 use core_foundation::data::{
     CFDataCreateMutable, CFDataGetBytePtr, CFDataGetLength, CFMutableDataRef,
 };
+#[cfg(target_os = "macos")]
 use core_foundation::dictionary::{
     kCFTypeDictionaryKeyCallBacks, kCFTypeDictionaryValueCallBacks, CFDictionaryCreate,
     CFDictionaryRef,
 };
+#[cfg(target_os = "macos")]
 use core_foundation::number::{kCFNumberCGFloatType, CFNumberCreate};
+#[cfg(target_os = "macos")]
 use core_foundation::string::{CFString, CFStringRef};
+#[cfg(target_os = "macos")]
 use core_graphics::base::{kCGRenderingIntentDefault, CGFloat};
+#[cfg(target_os = "macos")]
 use core_graphics::color_space::{kCGColorSpaceSRGB, CGColorSpace};
+#[cfg(target_os = "macos")]
 use core_graphics::data_provider::CGDataProvider;
+#[cfg(target_os = "macos")]
 use core_graphics::image::CGImage;
+#[cfg(target_os = "macos")]
 use core_graphics::image::CGImageAlphaInfo::CGImageAlphaLast;
+#[cfg(target_os = "macos")]
 use foreign_types::ForeignType;
+#[cfg(target_os = "macos")]
 use image::codecs::png::PngDecoder;
+#[cfg(target_os = "macos")]
 use image::{DynamicImage, ImageDecoder};
-use std::path::PathBuf;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+#[cfg(target_os = "macos")]
+use std::ptr;
+#[cfg(target_os = "macos")]
 use std::sync::Arc;
-use std::{fs, ptr};
+use std::sync::Mutex;
+use std::{fs, io};
 use url::Url;
 
 pub struct ImageCache {
     dir: PathBuf,
+    /// Images used in this run, see [`ImageCache::prune`]
+    used: Mutex<BTreeSet<String>>,
+}
+
+thread_local! {
+    /// Images requested on this thread while recording, see [`ImageCache::record`]
+    static RECORDED: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
 }
 
 fn get_image_id_ext(url: &Url) -> anyhow::Result<(String, String)> {
@@ -41,46 +67,113 @@ fn get_image_id_ext(url: &Url) -> anyhow::Result<(String, String)> {
     Ok((parts.join("-"), ext.to_string()))
 }
 
+/// Extension of images compressed by [`try_compress`]
 const COMPRESSED_EXT: &str = "heif";
 
 impl ImageCache {
     pub fn new(dir: PathBuf) -> Self {
-        Self { dir }
+        Self {
+            dir,
+            used: Default::default(),
+        }
     }
 
     pub fn get(&self, fetcher: &Fetcher, url: &Url) -> anyhow::Result<String> {
-        let (id, ext) = get_image_id_ext(url)?;
+        let id = self.load(fetcher, url)?;
+        self.mark_used([id.clone()]);
+        RECORDED.with_borrow_mut(|recorded| {
+            if let Some(recorded) = recorded {
+                recorded.push(id.clone());
+            }
+        });
+        Ok(id)
+    }
+
+    /// Runs `f` and returns the images it requested on this thread.
+    /// Doesn't see requests made on other threads, so `f` shouldn't spawn parallel work.
+    pub fn record<T>(&self, f: impl FnOnce() -> T) -> (T, Vec<String>) {
+        let outer = RECORDED.replace(Some(Vec::new()));
+        let result = f();
+        let recorded = RECORDED.replace(outer).unwrap_or_default();
+        (result, recorded)
+    }
+
+    /// Whether the image file is (still) in the cache.
+    pub fn contains(&self, id: &str) -> bool {
+        self.dir.join(id).exists()
+    }
 
-        let cache_path_ext = self.dir.join(format!("{id}.{ext}"));
-        let cache_path_compressed = self.dir.join(format!("{id}.{COMPRESSED_EXT}"));
+    /// Marks images as used without loading them, e.g. for entries read from a previous run.
+    pub fn mark_used(&self, ids: impl IntoIterator<Item = String>) {
+        self.used.lock().unwrap().extend(ids);
+    }
 
-        if cache_path_compressed.exists() {
-            Ok(format!("{id}.{COMPRESSED_EXT}"))
-        } else if cache_path_ext.exists() {
-            Ok(format!("{id}.{ext}"))
-        } else {
+    /// Deletes images that weren't used in this run, so only current images get copied into the
+    /// dictionary (e.g. after switching between HQ and regular images).
+    pub fn prune(&self) -> io::Result<()> {
+        let used = self.used.lock().unwrap();
+        for file in fs::read_dir(&self.dir)? {
+            let file = file?;
+            if !used.contains(&*file.file_name().to_string_lossy()) {
+                fs::remove_file(file.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn load(&self, fetcher: &Fetcher, url: &Url) -> anyhow::Result<String> {
+        let (id, ext) = get_image_id_ext(url)?;
+        let file = format!("{id}.{ext}");
+
+        let cache_path = self.dir.join(&file);
+        if !cache_path.exists() {
             let data = fetcher
                 .get(url.as_ref(), false)
                 .context("error loading image")?;
-            if let Some(compressed) =
-                try_compress(&ext, &data).context("error compressing image")?
-            {
-                fs::write(cache_path_compressed, compressed)?;
-                Ok(format!("{id}.{COMPRESSED_EXT}"))
-            } else {
-                fs::write(cache_path_ext, &data)?;
-                Ok(format!("{id}.{ext}"))
-            }
+            fs::write(cache_path, data)?;
         }
+        Ok(file)
     }
 }
 
+/// Copies the image at `source` into `to_dir`, as HEIF if [`try_compress`] can compress it, and
+/// returns the new file name. Skips the work if the copy is newer than the original already.
+pub fn copy_compressed(source: &Path, to_dir: &Path) -> anyhow::Result<String> {
+    let file = source
+        .file_name()
+        .context("image path has no file name")?
+        .to_string_lossy();
+    let (stem, ext) = file.rsplit_once('.').unwrap_or((&file, ""));
+    let compressed = format!("{stem}.{COMPRESSED_EXT}");
+
+    let modified = fs::metadata(source)?.modified()?;
+    for name in [&compressed, &*file] {
+        let copied = fs::metadata(to_dir.join(name)).and_then(|meta| meta.modified());
+        if copied.is_ok_and(|copied| copied >= modified) {
+            return Ok(name.to_string());
+        }
+    }
+
+    let data = fs::read(source)?;
+    if let Some(data) = try_compress(ext, &data).context("error compressing image")? {
+        fs::write(to_dir.join(&compressed), data)?;
+        Ok(compressed)
+    } else {
+        fs::write(to_dir.join(&*file), data)?;
+        Ok(file.into_owned())
+    }
+}
+
+#[cfg(target_os = "macos")]
 #[allow(non_camel_case_types)]
 type size_t = isize;
 
+#[cfg(target_os = "macos")]
 enum CGImageDestination {}
+#[cfg(target_os = "macos")]
 type CGImageDestinationRef = *mut CGImageDestination;
 
+#[cfg(target_os = "macos")]
 #[link(name = "CoreGraphics", kind = "framework")]
 extern "C" {
     fn CGColorSpaceCreateICCBased(
@@ -91,6 +184,7 @@ extern "C" {
     ) -> *mut core_graphics::sys::CGColorSpace;
 }
 
+#[cfg(target_os = "macos")]
 #[link(name = "ImageIO", kind = "framework")]
 extern "C" {
     static kCGImageDestinationLossyCompressionQuality: CFStringRef;
@@ -108,6 +202,13 @@ extern "C" {
     fn CGImageDestinationFinalize(dest: CGImageDestinationRef) -> bool;
 }
 
+/// HEIF compression goes through ImageIO, so other platforms keep the original images.
+#[cfg(not(target_os = "macos"))]
+pub fn try_compress(_file_ext: &str, _image: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+    Ok(None)
+}
+
+#[cfg(target_os = "macos")]
 pub fn try_compress(file_ext: &str, image: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
     if file_ext != "png" {
         return Ok(None);
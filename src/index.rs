@@ -1,14 +1,26 @@
 use crate::fetcher::Fetcher;
-use anyhow::{anyhow, bail};
+use crate::links::{base_title, EntryRef, LinkResolver};
+use crate::types::TYPE_NAMES;
+use anyhow::{anyhow, bail, Context};
 use html5ever::tendril::TendrilSink;
+use kuchikiki::NodeRef;
 use reqwest::Url;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::ops::RangeInclusive;
+use std::path::Path;
 use std::{fmt, str::FromStr};
 
 const POKEMON_INDEX_URL: &str =
     "https://bulbapedia.bulbagarden.net/wiki/List_of_Pokémon_by_National_Pokédex_number";
+const ABILITY_INDEX_URL: &str = "https://bulbapedia.bulbagarden.net/wiki/Ability";
+const MOVE_INDEX_URL: &str = "https://bulbapedia.bulbagarden.net/wiki/List_of_moves";
+const ITEM_INDEX_URL: &str = "https://bulbapedia.bulbagarden.net/wiki/List_of_items_by_name";
+const TYPE_PAGE_URL_BASE: &str = "https://bulbapedia.bulbagarden.net/wiki/";
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
 pub struct DexId(pub u32);
 
 impl FromStr for DexId {
@@ -49,10 +61,305 @@ impl DexId {
     }
 }
 
-#[derive(Debug)]
+/// A number in a regional Pokédex, e.g. Paldea #001.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct RegionalDexId {
+    /// Name of the Pokédex, e.g. “Paldea” or “Kalos (Central)”
+    pub dex: String,
+    pub number: u32,
+}
+
+impl fmt::Display for RegionalDexId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} #{:03}", self.dex, self.number)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct AbilityId(pub u32);
+
+impl fmt::Display for AbilityId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:03}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct MoveId(pub u32);
+
+impl fmt::Display for MoveId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:03}", self.0)
+    }
+}
+
+/// Items have no useful numbering, so they're identified by a slug of their page name.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ItemId(pub String);
+
+impl ItemId {
+    fn from_page_url(url: &Url) -> Option<Self> {
+        let page = url.path().strip_prefix("/wiki/")?;
+        let page = urlencoding::decode(page).ok()?;
+        let slug = page
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() {
+                    c.to_lowercase().next().unwrap()
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        Some(Self(slug))
+    }
+}
+
+impl fmt::Display for ItemId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Index into [`TYPE_NAMES`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct TypeId(pub usize);
+
+impl TypeId {
+    pub fn name(&self) -> &'static str {
+        TYPE_NAMES[self.0]
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        TYPE_NAMES.iter().position(|n| *n == name).map(Self)
+    }
+    /// ID used in dictionary entry IDs, e.g. “fire”
+    pub fn slug(&self) -> String {
+        self.name().to_lowercase()
+    }
+}
+
+impl fmt::Display for TypeId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A row in the list of moves.
+#[derive(Debug, Serialize)]
+pub struct MovePage {
+    pub url: String,
+    pub type_name: String,
+    pub category: String,
+    pub pp: String,
+    pub power: String,
+    pub accuracy: String,
+}
+
+#[derive(Debug, Default, Serialize)]
 pub struct Index {
     pub pokemon_pages: BTreeMap<DexId, String>,
     pub pokemon_gens: Vec<Vec<DexId>>,
+    pub ability_pages: BTreeMap<AbilityId, String>,
+    pub move_pages: BTreeMap<MoveId, MovePage>,
+    pub item_pages: BTreeMap<ItemId, String>,
+    pub type_pages: BTreeMap<TypeId, String>,
+    /// Generation each ability was introduced in, if the index says
+    #[serde(skip)]
+    pub ability_gens: BTreeMap<AbilityId, usize>,
+    /// Generation each move was introduced in, if the index says
+    #[serde(skip)]
+    pub move_gens: BTreeMap<MoveId, usize>,
+    /// Generation each item was introduced in, if the index says
+    #[serde(skip)]
+    pub item_gens: BTreeMap<ItemId, usize>,
+    /// Maps page URLs to the entries above
+    #[serde(skip)]
+    pub links: LinkResolver,
+}
+
+impl Index {
+    pub fn generation_of(&self, id: DexId) -> Option<usize> {
+        self.pokemon_gens
+            .iter()
+            .position(|ids| ids.contains(&id))
+            .map(|i| i + 1)
+    }
+
+    /// Whether the Pokémon has an entry, i.e. it's in the index and was selected.
+    pub fn has_pokemon(&self, id: DexId) -> bool {
+        self.pokemon_pages.contains_key(&id)
+    }
+
+    /// Generations with at least one selected Pokémon (1-based).
+    pub fn selected_gens(&self) -> Vec<usize> {
+        (1..=self.pokemon_gens.len())
+            .filter(|gen| {
+                self.pokemon_gens[gen - 1]
+                    .iter()
+                    .any(|id| self.has_pokemon(*id))
+            })
+            .collect()
+    }
+
+    /// URLs of all pages with entries.
+    pub fn pages(&self) -> impl Iterator<Item = &str> {
+        self.pokemon_pages
+            .values()
+            .chain(self.ability_pages.values())
+            .chain(self.move_pages.values().map(|page| &page.url))
+            .chain(self.item_pages.values())
+            .chain(self.type_pages.values())
+            .map(String::as_str)
+    }
+
+    /// Looks up the redirects linked from the pages, see [`LinkResolver::learn_redirects`].
+    pub fn learn_redirects(&mut self, fetcher: &Fetcher, cache_dir: &Path) -> anyhow::Result<()> {
+        let pages: Vec<String> = self.pages().map(str::to_string).collect();
+        self.links.learn_redirects(fetcher, &pages, cache_dir)
+    }
+
+    /// What the links on a page resolve to, one line per link, or `None` if
+    /// [`Self::learn_redirects`] didn't look at the page. Links to Pokémon that weren't selected
+    /// are marked, since they are dropped. Apart from move list rows, this is all of the index
+    /// that an entry read from the page depends on.
+    pub fn link_targets(&self, page: &str) -> Option<String> {
+        let mut out = String::new();
+        for link in self.links.page_links(page)? {
+            let Ok(url) = Url::parse(&link.url) else {
+                continue;
+            };
+            let target = |is_redirect| match self.links.resolve(&url, is_redirect) {
+                Some(EntryRef::Pokemon(id)) if !self.has_pokemon(id) => {
+                    format!("pokemon-{} (not selected)", id.0)
+                }
+                Some(entry) => entry.entry_id(),
+                None => String::new(),
+            };
+            // page readers resolve some links without the redirect fallback
+            writeln!(
+                out,
+                "{}\t{}\t{}",
+                link.url,
+                target(false),
+                target(link.redirect)
+            )
+            .unwrap();
+        }
+        Some(out)
+    }
+
+    /// Drops Pokémon that weren't selected. Generations are kept so numbering stays the same,
+    /// and links to dropped Pokémon still resolve so they can be turned into plain text.
+    /// Fails if no Pokémon are selected.
+    pub fn select(&mut self, selection: &Selection) -> anyhow::Result<()> {
+        let selected: BTreeMap<_, _> = std::mem::take(&mut self.pokemon_pages)
+            .into_iter()
+            .filter(|(id, url)| selection.includes(self, *id, url))
+            .collect();
+        self.pokemon_pages = selected;
+        if self.pokemon_pages.is_empty() {
+            bail!("no Pokémon match the selection");
+        }
+        Ok(())
+    }
+}
+
+/// Which Pokémon to include in the dictionary. Everything is included by default.
+#[derive(Debug, Default)]
+pub struct Selection {
+    /// Exclude generations after this one
+    pub max_gen: Option<usize>,
+    /// Only include these generations
+    pub gens: Vec<usize>,
+    /// Only include these national dex numbers (together with `names`)
+    pub dex_ranges: Vec<RangeInclusive<u32>>,
+    /// Only include Pokémon with these names (together with `dex_ranges`)
+    pub names: Vec<String>,
+}
+
+impl Selection {
+    /// Whether this is the default selection of everything.
+    pub fn is_all(&self) -> bool {
+        self.max_gen.is_none()
+            && self.gens.is_empty()
+            && self.dex_ranges.is_empty()
+            && self.names.is_empty()
+    }
+
+    /// Whether abilities, moves and items introduced in the generation are included even if no
+    /// selected Pokémon has them. Selecting Pokémon by number or name only includes theirs.
+    pub fn includes_gen(&self, gen: usize) -> bool {
+        self.dex_ranges.is_empty()
+            && self.names.is_empty()
+            && self.max_gen.is_none_or(|max| gen <= max)
+            && (self.gens.is_empty() || self.gens.contains(&gen))
+    }
+
+    fn includes(&self, index: &Index, id: DexId, url: &str) -> bool {
+        let gen = index.generation_of(id).unwrap_or_default();
+        if self.max_gen.is_some_and(|max| gen > max) {
+            return false;
+        }
+        if !self.gens.is_empty() && !self.gens.contains(&gen) {
+            return false;
+        }
+        if self.dex_ranges.is_empty() && self.names.is_empty() {
+            return true;
+        }
+        let in_range = self.dex_ranges.iter().any(|range| range.contains(&id.0));
+        let has_name = Url::parse(url)
+            .ok()
+            .and_then(|url| base_title(&url))
+            .is_some_and(|title| {
+                self.names
+                    .iter()
+                    .any(|name| name.eq_ignore_ascii_case(&title))
+            });
+        in_range || has_name
+    }
+}
+
+/// Parses a generation number like “IV”, or returns 0 if there are no numerals.
+fn parse_roman_numerals(s: &str) -> usize {
+    let mut roman_numerals: Vec<_> = s
+        .chars()
+        .map(|c| match c {
+            'I' => 1,
+            'V' => 5,
+            'X' => 10,
+            'L' => 50,
+            'C' => 100,
+            'D' => 500,
+            'M' => 1000,
+            _ => 0,
+        })
+        .filter(|i| *i != 0)
+        .collect();
+
+    for i in 1..roman_numerals.len() {
+        if roman_numerals[i] > roman_numerals[i - 1] {
+            roman_numerals[i] -= roman_numerals[i - 1];
+            roman_numerals[i - 1] = 0;
+        }
+    }
+    roman_numerals.into_iter().sum()
+}
+
+/// Parses a dex number range like “1-151”, or a single number.
+pub fn parse_dex_range(s: &str) -> Result<RangeInclusive<u32>, String> {
+    let parse = |s: &str| {
+        s.trim()
+            .trim_start_matches('#')
+            .parse::<u32>()
+            .map_err(|e| format!("invalid dex number “{s}”: {e}"))
+    };
+    match s.split_once('-') {
+        Some((start, end)) => Ok(parse(start)?..=parse(end)?),
+        None => {
+            let n = parse(s)?;
+            Ok(n..=n)
+        }
+    }
 }
 
 pub fn read_index(fetcher: &Fetcher) -> anyhow::Result<Index> {
@@ -87,7 +394,7 @@ pub fn read_index(fetcher: &Fetcher) -> anyhow::Result<Index> {
             let mut prev_sibling = parent_table.previous_sibling();
             while prev_sibling
                 .as_ref()
-                .map_or(false, |cursor| cursor.as_element().is_none())
+                .is_some_and(|cursor| cursor.as_element().is_none())
             {
                 prev_sibling = prev_sibling.unwrap().previous_sibling();
             }
@@ -103,28 +410,7 @@ pub fn read_index(fetcher: &Fetcher) -> anyhow::Result<Index> {
             if !gen_title.starts_with("Generation ") {
                 bail!("generation title does not start with “Generation”: {gen_title}");
             }
-            let mut roman_numerals: Vec<_> = gen_title[11..]
-                .chars()
-                .map(|c| match c {
-                    'I' => 1,
-                    'V' => 5,
-                    'X' => 10,
-                    'L' => 50,
-                    'C' => 100,
-                    'D' => 500,
-                    'M' => 1000,
-                    _ => 0,
-                })
-                .filter(|i| *i != 0)
-                .collect();
-
-            for i in 0..roman_numerals.len() - 1 {
-                if roman_numerals[i + 1] > roman_numerals[i] {
-                    roman_numerals[i + 1] -= roman_numerals[i];
-                    roman_numerals[i] = 0;
-                }
-            }
-            roman_numerals.into_iter().sum()
+            parse_roman_numerals(&gen_title[11..])
         };
         if pokemon_gens.len() < generation {
             pokemon_gens.resize_with(generation, Default::default);
@@ -142,8 +428,352 @@ pub fn read_index(fetcher: &Fetcher) -> anyhow::Result<Index> {
         pokemon_pages.insert(dex_id, base_url.join(href).unwrap().to_string());
     }
 
+    let (ability_pages, ability_gens) =
+        read_ability_index(fetcher).context("error reading ability index")?;
+    let (move_pages, move_gens) = read_move_index(fetcher).context("error reading move index")?;
+    let (item_pages, item_gens) = read_item_index(fetcher).context("error reading item index")?;
+
+    // there's only a handful of types and they all have pages with the same naming scheme
+    let type_pages: BTreeMap<_, _> = (0..TYPE_NAMES.len())
+        .map(|i| {
+            let url = Url::parse(TYPE_PAGE_URL_BASE)
+                .unwrap()
+                .join(&format!("{}_(type)", TYPE_NAMES[i]))
+                .unwrap();
+            (TypeId(i), url.to_string())
+        })
+        .collect();
+
+    let mut links = LinkResolver::default();
+    for (id, url) in &pokemon_pages {
+        links.insert(url, EntryRef::Pokemon(*id));
+    }
+    for (id, url) in &ability_pages {
+        links.insert(url, EntryRef::Ability(*id));
+    }
+    for (id, page) in &move_pages {
+        links.insert(&page.url, EntryRef::Move(*id));
+    }
+    for (id, url) in &item_pages {
+        links.insert(url, EntryRef::Item(id.clone()));
+    }
+    for (id, url) in &type_pages {
+        links.insert(url, EntryRef::Type(*id));
+    }
+
     Ok(Index {
         pokemon_pages,
         pokemon_gens,
+        ability_pages,
+        move_pages,
+        item_pages,
+        type_pages,
+        ability_gens,
+        move_gens,
+        item_gens,
+        links,
     })
 }
+
+fn read_ability_index(
+    fetcher: &Fetcher,
+) -> anyhow::Result<(BTreeMap<AbilityId, String>, BTreeMap<AbilityId, usize>)> {
+    let html = String::from_utf8(fetcher.get(ABILITY_INDEX_URL, true)?)?;
+    let doc = kuchikiki::parse_html().one(html);
+
+    let base_url = Url::parse(ABILITY_INDEX_URL).unwrap();
+    let mut ability_pages = BTreeMap::new();
+    let mut ability_gens = BTreeMap::new();
+
+    // column titles from the most recent header row
+    let mut columns: Vec<String> = Vec::new();
+
+    for tr in doc
+        .select("tr")
+        .map_err(|()| anyhow!("could not find <tr>"))?
+    {
+        let tr = tr.as_node();
+        let cells: Vec<_> = tr
+            .children()
+            .filter(|node| node.as_element().is_some())
+            .collect();
+
+        let Ok(td) = tr.select_first("td") else {
+            columns = cells
+                .iter()
+                .map(|cell| cell.text_contents().trim().to_string())
+                .collect();
+            continue;
+        };
+        let Ok(id) = td.text_contents().trim().parse() else {
+            continue;
+        };
+        let Ok(link) = tr.select_first("a[href$='_(Ability)']") else {
+            continue;
+        };
+        let link_attrs = link.as_node().as_element().unwrap().attributes.borrow();
+        let Some(href) = link_attrs.get("href") else {
+            bail!("missing href on link for ability {id}");
+        };
+
+        ability_pages.insert(AbilityId(id), base_url.join(href).unwrap().to_string());
+        if let Some(gen) = column_generation(&columns, &cells) {
+            ability_gens.insert(AbilityId(id), gen);
+        }
+    }
+
+    Ok((ability_pages, ability_gens))
+}
+
+/// The generation in a “Gen” column, e.g. “III”.
+fn column_generation(columns: &[String], cells: &[NodeRef]) -> Option<usize> {
+    let i = columns
+        .iter()
+        .position(|c| c.trim_end_matches('.') == "Gen")?;
+    let gen = parse_roman_numerals(cells.get(i)?.text_contents().trim());
+    (gen > 0).then_some(gen)
+}
+
+fn read_move_index(
+    fetcher: &Fetcher,
+) -> anyhow::Result<(BTreeMap<MoveId, MovePage>, BTreeMap<MoveId, usize>)> {
+    let html = String::from_utf8(fetcher.get(MOVE_INDEX_URL, true)?)?;
+    let doc = kuchikiki::parse_html().one(html);
+
+    let base_url = Url::parse(MOVE_INDEX_URL).unwrap();
+    let mut move_pages = BTreeMap::new();
+    let mut move_gens = BTreeMap::new();
+
+    // column titles from the most recent header row
+    let mut columns: Vec<String> = Vec::new();
+
+    for tr in doc
+        .select("tr")
+        .map_err(|()| anyhow!("could not find <tr>"))?
+    {
+        let tr = tr.as_node();
+        let cells: Vec<_> = tr
+            .children()
+            .filter(|node| node.as_element().is_some())
+            .collect();
+
+        if tr.select_first("td").is_err() {
+            columns = cells
+                .iter()
+                .map(|cell| cell.text_contents().trim().to_string())
+                .collect();
+            continue;
+        }
+
+        let Some(Ok(id)) = cells.first().map(|td| td.text_contents().trim().parse()) else {
+            continue;
+        };
+        let Ok(link) = tr.select_first("a[href$='_(move)']") else {
+            continue;
+        };
+        let link_attrs = link.as_node().as_element().unwrap().attributes.borrow();
+        let Some(href) = link_attrs.get("href") else {
+            bail!("missing href on link for move {id}");
+        };
+
+        let column = |title: &str| {
+            columns
+                .iter()
+                .position(|c| c == title)
+                .and_then(|i| cells.get(i))
+                .map(|cell| cell.text_contents().trim().to_string())
+                .unwrap_or_default()
+        };
+
+        if let Some(gen) = column_generation(&columns, &cells) {
+            move_gens.insert(MoveId(id), gen);
+        }
+        move_pages.insert(
+            MoveId(id),
+            MovePage {
+                url: base_url.join(href).unwrap().to_string(),
+                type_name: column("Type"),
+                category: column("Category"),
+                pp: column("PP"),
+                power: column("Power"),
+                accuracy: column("Accuracy"),
+            },
+        );
+    }
+
+    Ok((move_pages, move_gens))
+}
+
+fn read_item_index(
+    fetcher: &Fetcher,
+) -> anyhow::Result<(BTreeMap<ItemId, String>, BTreeMap<ItemId, usize>)> {
+    let html = String::from_utf8(fetcher.get(ITEM_INDEX_URL, true)?)?;
+    let doc = kuchikiki::parse_html().one(html);
+
+    let base_url = Url::parse(ITEM_INDEX_URL).unwrap();
+    let mut item_pages = BTreeMap::new();
+    let mut item_gens = BTreeMap::new();
+
+    for tr in doc
+        .select(".mw-parser-output tr")
+        .map_err(|()| anyhow!("could not find <tr>"))?
+    {
+        let tr = tr.as_node();
+        if tr.select_first("td").is_err() {
+            continue;
+        }
+
+        // the first text link in the row is the item name; others link to sprites or generations
+        let Some(href) = tr
+            .select("a[href^='/wiki/']")
+            .map_err(|()| anyhow!("could not find <a>"))?
+            .filter(|link| link.as_node().select_first("img").is_err())
+            .find_map(|link| link.attributes.borrow().get("href").map(|s| s.to_string()))
+        else {
+            continue;
+        };
+        if href.contains('#') || href.contains(':') || href.starts_with("/wiki/Generation_") {
+            continue;
+        }
+
+        let url = base_url.join(&href).unwrap();
+        let Some(id) = ItemId::from_page_url(&url) else {
+            continue;
+        };
+        // the generation column links to the generation's page
+        let gen = tr
+            .select("a[href^='/wiki/Generation_']")
+            .map_err(|()| anyhow!("could not find <a>"))?
+            .find_map(|link| {
+                let href = link.attributes.borrow().get("href")?.to_string();
+                let gen = parse_roman_numerals(href.strip_prefix("/wiki/Generation_")?);
+                (gen > 0).then_some(gen)
+            });
+        if let Some(gen) = gen {
+            item_gens.entry(id.clone()).or_insert(gen);
+        }
+        item_pages.entry(id).or_insert_with(|| url.to_string());
+    }
+
+    Ok((item_pages, item_gens))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn roman_numerals() {
+        assert_eq!(parse_roman_numerals("I"), 1);
+        assert_eq!(parse_roman_numerals("IV"), 4);
+        assert_eq!(parse_roman_numerals("VIII"), 8);
+        assert_eq!(parse_roman_numerals("IX"), 9);
+        assert_eq!(parse_roman_numerals(""), 0);
+        assert_eq!(parse_roman_numerals("Unknown"), 0);
+    }
+
+    #[test]
+    fn selected_gens() {
+        assert!(Selection::default().is_all());
+        assert!(Selection::default().includes_gen(9));
+
+        let max = Selection {
+            max_gen: Some(4),
+            ..Default::default()
+        };
+        assert!(!max.is_all());
+        assert!(max.includes_gen(4));
+        assert!(!max.includes_gen(5));
+
+        let gens = Selection {
+            gens: vec![1, 3],
+            ..Default::default()
+        };
+        assert!(gens.includes_gen(3));
+        assert!(!gens.includes_gen(2));
+
+        // only the abilities, moves and items of these Pokémon
+        let names = Selection {
+            max_gen: Some(4),
+            names: vec!["Pikachu".to_string()],
+            ..Default::default()
+        };
+        assert!(!names.includes_gen(1));
+        let dex = Selection {
+            dex_ranges: vec![1..=151],
+            ..Default::default()
+        };
+        assert!(!dex.includes_gen(1));
+    }
+
+    #[test]
+    fn link_targets() {
+        let dir = std::env::temp_dir().join(format!("pokedex-index-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let fetcher = Fetcher::new(dir.clone());
+        let page = "https://bulbapedia.bulbagarden.net/wiki/Pichu_(Pok%C3%A9mon)";
+        fs::write(
+            fetcher.cache_path(page),
+            r##"<a href="/wiki/Pikachu_(Pok%C3%A9mon)#Biology">Pikachu</a>
+            <a href="/wiki/Raichu_(Pok%C3%A9mon)">Raichu</a>
+            <a href="/wiki/Thunder_Shock_(move)">Thunder Shock</a>
+            <a href="#Evolution">Evolution</a>"##,
+        )
+        .unwrap();
+
+        let mut index = Index::default();
+        for (id, name) in [(25, "Pikachu"), (26, "Raichu"), (172, "Pichu")] {
+            let url = format!("https://bulbapedia.bulbagarden.net/wiki/{name}_(Pok%C3%A9mon)");
+            if id != 172 {
+                fs::write(fetcher.cache_path(&url), "").unwrap();
+            }
+            index.links.insert(&url, EntryRef::Pokemon(DexId(id)));
+            index.pokemon_pages.insert(DexId(id), url);
+        }
+        index.pokemon_gens = vec![vec![DexId(25), DexId(26), DexId(172)]];
+        assert_eq!(index.link_targets(page), None);
+        index.learn_redirects(&fetcher, &dir.join("links")).unwrap();
+        let targets = index.link_targets(page).unwrap();
+        assert_eq!(
+            targets,
+            concat!(
+                "https://bulbapedia.bulbagarden.net/wiki/Pikachu_(Pok%C3%A9mon)\tpokemon-25\tpokemon-25\n",
+                "https://bulbapedia.bulbagarden.net/wiki/Raichu_(Pok%C3%A9mon)\tpokemon-26\tpokemon-26\n",
+                "https://bulbapedia.bulbagarden.net/wiki/Thunder_Shock_(move)\t\t\n",
+            )
+        );
+
+        // redirects that the page doesn't link to don't matter
+        let redirects = dir.join("redirects.tsv");
+        fs::write(
+            &redirects,
+            "bulbapedia.bulbagarden.net/Pika\tbulbapedia.bulbagarden.net/Pikachu_(Pokémon)\n",
+        )
+        .unwrap();
+        index.links.load_redirects(&redirects).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(index.link_targets(page).unwrap(), targets);
+
+        // dropping a linked Pokémon does
+        index
+            .select(&Selection {
+                names: vec!["Pichu".to_string(), "Pikachu".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(index
+            .link_targets(page)
+            .unwrap()
+            .contains("\tpokemon-26 (not selected)\t"));
+
+        // selecting nothing is an error
+        let error = index
+            .select(&Selection {
+                names: vec!["MissingNo.".to_string()],
+                ..Default::default()
+            })
+            .unwrap_err();
+        assert_eq!(error.to_string(), "no Pokémon match the selection");
+    }
+}
@@ -0,0 +1,178 @@
+use crate::fetcher::Fetcher;
+use crate::image::ImageCache;
+use crate::index::{DexId, Index, ItemId};
+use crate::page::{
+    get_highest_quality_src, is_tag, outer_xhtml, read_sections, Page, Reference, SectionHtml,
+};
+use crate::Config;
+use anyhow::{anyhow, Context};
+use html5ever::tendril::TendrilSink;
+use kuchikiki::NodeRef;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ItemEntry {
+    /// Page URL
+    pub url: String,
+
+    pub id: ItemId,
+    pub name: String,
+    pub name_jp_text: Option<String>,
+    pub name_jp_html: Option<String>,
+    /// Sprite image path
+    pub sprite: Option<String>,
+
+    /// Summary HTML contents
+    pub summary_html: String,
+    /// In-game descriptions
+    pub description: Vec<SectionHtml>,
+    /// Effect descriptions
+    pub effects: Vec<SectionHtml>,
+    /// Pokémon that hold this item or evolve with it
+    pub pokemon: Vec<ItemMon>,
+    /// Footnotes cited in the summary, description or effects, in citation order
+    pub references: Vec<Reference>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ItemRelation {
+    /// Wild Pokémon may hold this item
+    Held,
+    /// The Pokémon evolves using this item
+    Evolution,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ItemMon {
+    pub dex_id: DexId,
+    /// Form name, if it only applies to some forms
+    pub form: Option<String>,
+    pub relation: ItemRelation,
+}
+
+pub fn read_item(
+    fetcher: &Fetcher,
+    index: &Index,
+    image_cache: &ImageCache,
+    config: &Config,
+    id: &ItemId,
+    url: &str,
+) -> anyhow::Result<ItemEntry> {
+    let html = String::from_utf8(fetcher.get(url, true)?)?;
+    let doc = kuchikiki::parse_html().one(html);
+    let mut page = Page::new(fetcher, index, image_cache, config, url, &doc);
+
+    let title = doc
+        .select_first("#firstHeading")
+        .map_err(|()| anyhow!("could not find page title"))?
+        .text_contents();
+    let name = title.trim().to_string();
+
+    let info_box = doc.select_first(".mw-parser-output table.roundy").ok();
+
+    let name_jp = info_box
+        .as_ref()
+        .and_then(|info_box| info_box.as_node().select_first("[lang='ja']").ok());
+    let name_jp_text = name_jp
+        .as_ref()
+        .map(|node| node.text_contents().trim().to_string());
+    let name_jp_html = name_jp.as_ref().map(|node| outer_xhtml(node.as_node()));
+
+    let sprite = match info_box
+        .as_ref()
+        .and_then(|info_box| info_box.as_node().select_first("img").ok())
+    {
+        Some(img) => {
+            let src = get_highest_quality_src(img.as_node(), &page.base_url, false)
+                .ok_or(anyhow!("could not resolve sprite source"))?;
+            let image_id = image_cache.get(fetcher, &src)?;
+            Some(format!("images/{}", urlencoding::encode(&image_id)))
+        }
+        None => None,
+    };
+
+    let mw_parser_output = doc
+        .select_first(".mw-parser-output")
+        .map_err(|()| anyhow!("no mw-parser-output"))?;
+
+    let (intro, sections) = read_sections(mw_parser_output.as_node());
+
+    let mut summary_html = String::new();
+    for node in intro {
+        if is_tag(&node, "p") {
+            page.fix_links(&node)
+                .context("error fixing summary links")?;
+            summary_html += &outer_xhtml(&node);
+        }
+    }
+
+    let mut description = Vec::new();
+    let mut effects = Vec::new();
+    let mut pokemon = Vec::new();
+    for section in &sections {
+        if section.title == "Description" {
+            description = page.section_html(section)?;
+        } else if section.title == "Effect" {
+            effects = page.section_html(section)?;
+        }
+
+        let untitled = (section.title.clone(), section.nodes.clone());
+        for (title, nodes) in std::iter::once(&untitled).chain(&section.subsections) {
+            let relation = if title.contains("Held") || title.contains("held") {
+                ItemRelation::Held
+            } else if title.contains("Evolution") || title.contains("evolve") {
+                ItemRelation::Evolution
+            } else {
+                continue;
+            };
+            for node in nodes {
+                read_pokemon_links(&page, node, relation, &mut pokemon)?;
+            }
+        }
+    }
+
+    let references = page.references()?;
+
+    Ok(ItemEntry {
+        url: url.to_string(),
+        id: id.clone(),
+        name,
+        name_jp_text,
+        name_jp_html,
+        sprite,
+        summary_html,
+        description,
+        effects,
+        pokemon,
+        references,
+    })
+}
+
+/// Collects the Pokémon linked from table rows or list items in the node.
+fn read_pokemon_links(
+    page: &Page,
+    node: &NodeRef,
+    relation: ItemRelation,
+    pokemon: &mut Vec<ItemMon>,
+) -> anyhow::Result<()> {
+    let Ok(links) = node.select("a[href$='_(Pok%C3%A9mon)']") else {
+        return Ok(());
+    };
+    for link in links {
+        let Some((dex_id, form)) = page.pokemon_link(link.as_node())? else {
+            continue;
+        };
+
+        let is_duplicate = pokemon
+            .iter()
+            .any(|mon| mon.dex_id == dex_id && mon.form == form && mon.relation == relation);
+        if !is_duplicate {
+            pokemon.push(ItemMon {
+                dex_id,
+                form,
+                relation,
+            });
+        }
+    }
+    Ok(())
+}
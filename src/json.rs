@@ -0,0 +1,214 @@
+//! The scraped data as JSON, for other tools. The output is described by a versioned JSON Schema
+//! in `schema/`, which is written next to it.
+//!
+//! Links between entries in the HTML fields become `#<entry ID>`, since `x-dictionary:r:` only
+//! works in Apple's Dictionary. Links to entries that aren't in the output, e.g. because
+//! [`Entries::select`] dropped them, are removed.
+
+use crate::backend::{Backend, Dictionary};
+use crate::links::EntryRef;
+use crate::rendered::{rewrite_images, rewrite_links, strip_links_where};
+use crate::xhtml::XhtmlEscaped;
+use crate::{AbilityEntry, Entries, Index, ItemEntry, MonEntry, MoveEntry, TypeEntry};
+use anyhow::Context;
+use serde::Serialize;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Version of the output format. Bump this, and add a new schema file, whenever the output
+/// changes in a way that could break consumers.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// File name of the schema for [`SCHEMA_VERSION`].
+pub const SCHEMA_FILE: &str = "pokedex-v1.schema.json";
+
+/// The schema itself, for validating output.
+pub const SCHEMA: &str = include_str!("../schema/pokedex-v1.schema.json");
+
+/// Base name of the output files.
+const NAME: &str = "pokedex";
+
+/// The [`Index`] and all entries as a single JSON document (`pokedex.json`) and as a JSON Lines
+/// stream of [`Record`]s (`pokedex.jsonl`), with the schema both follow. The images they refer to
+/// are copied into `images`.
+pub struct Json;
+
+impl Backend for Json {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn write(&self, dict: &Dictionary, out_dir: &Path) -> anyhow::Result<()> {
+        fs::create_dir_all(out_dir)?;
+        let entry_ids = entry_ids(dict.entries);
+
+        let file =
+            File::create(out_dir.join(format!("{NAME}.json"))).context("error creating .json")?;
+        let mut out = BufWriter::new(file);
+        let mut document = serde_json::to_value(Document::new(dict))?;
+        fix_links(&mut document, &entry_ids);
+        serde_json::to_writer(&mut out, &document)?;
+        out.flush().context("error writing .json")?;
+
+        let file =
+            File::create(out_dir.join(format!("{NAME}.jsonl"))).context("error creating .jsonl")?;
+        let mut out = BufWriter::new(file);
+        for record in records(dict) {
+            let mut record = serde_json::to_value(record)?;
+            fix_links(&mut record, &entry_ids);
+            serde_json::to_writer(&mut out, &record)?;
+            out.write_all(b"\n")?;
+        }
+        out.flush().context("error writing .jsonl")?;
+
+        fs::write(out_dir.join(SCHEMA_FILE), SCHEMA).context("error writing schema")?;
+
+        let image_dir = out_dir.join("images");
+        if image_dir.exists() {
+            fs::remove_dir_all(&image_dir)?;
+        }
+        fs::create_dir_all(&image_dir)?;
+        for file in image_files(dict)? {
+            fs::copy(dict.image_dir.join(&file), image_dir.join(&file))
+                .with_context(|| format!("error copying image {file}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// The IDs of all entries, which links in the output can point to.
+fn entry_ids(entries: &Entries) -> BTreeSet<String> {
+    let pokemon = entries.pokemon.keys().map(|id| EntryRef::Pokemon(*id));
+    let abilities = entries.abilities.keys().map(|id| EntryRef::Ability(*id));
+    let moves = entries.moves.keys().map(|id| EntryRef::Move(*id));
+    let items = entries.items.keys().map(|id| EntryRef::Item(id.clone()));
+    let types = entries.types.keys().map(|id| EntryRef::Type(*id));
+    pokemon
+        .chain(abilities)
+        .chain(moves)
+        .chain(items)
+        .chain(types)
+        .map(|entry| entry.entry_id())
+        .collect()
+}
+
+/// Rewrites the links between entries in every HTML string to `#<entry ID>`, removing those to
+/// entries that aren't in `entry_ids`.
+fn fix_links(value: &mut Value, entry_ids: &BTreeSet<String>) {
+    match value {
+        Value::String(s) if s.contains("x-dictionary:r:") => {
+            let html = strip_links_where(s, |entry| !entry_ids.contains(entry));
+            *s = rewrite_links(&html, |entry, _| format!("#{}", XhtmlEscaped(entry, true)));
+        }
+        Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| fix_links(value, entry_ids)),
+        Value::Object(map) => map
+            .values_mut()
+            .for_each(|value| fix_links(value, entry_ids)),
+        _ => (),
+    }
+}
+
+/// Files in the image directory that the output refers to: as `images/<file>` paths, in HTML or
+/// in fields of their own, and as the bare file names of Pokémon's neighbor images.
+fn image_files(dict: &Dictionary) -> anyhow::Result<BTreeSet<String>> {
+    fn collect(value: &Value, files: &mut BTreeSet<String>) {
+        match value {
+            Value::String(s) => {
+                if let Some(file) = s.strip_prefix("images/") {
+                    let file = urlencoding::decode(file).unwrap_or(Cow::Borrowed(file));
+                    files.insert(file.into_owned());
+                } else if s.contains("<img") {
+                    rewrite_images(s, |file| {
+                        files.insert(file.to_string());
+                        String::new()
+                    });
+                }
+            }
+            Value::Array(values) => values.iter().for_each(|value| collect(value, files)),
+            Value::Object(map) => map.values().for_each(|value| collect(value, files)),
+            _ => (),
+        }
+    }
+
+    let mut files = BTreeSet::new();
+    collect(&serde_json::to_value(Document::new(dict))?, &mut files);
+    for mon in dict.entries.pokemon.values() {
+        for (_, file) in mon.prev_entry.iter().chain(&mon.next_entry) {
+            files.insert(file.clone());
+        }
+    }
+    Ok(files)
+}
+
+/// The whole `pokedex.json` document. Entries are in ID order.
+#[derive(Debug, Serialize)]
+pub struct Document<'a> {
+    #[serde(rename = "$schema")]
+    pub schema: &'static str,
+    pub version: u32,
+    pub index: &'a Index,
+    pub pokemon: Vec<&'a MonEntry>,
+    pub abilities: Vec<&'a AbilityEntry>,
+    pub moves: Vec<&'a MoveEntry>,
+    pub items: Vec<&'a ItemEntry>,
+    pub types: Vec<&'a TypeEntry>,
+}
+
+impl<'a> Document<'a> {
+    pub fn new(dict: &Dictionary<'a>) -> Self {
+        let entries = dict.entries;
+        Self {
+            schema: SCHEMA_FILE,
+            version: SCHEMA_VERSION,
+            index: dict.index,
+            pokemon: entries.pokemon.values().collect(),
+            abilities: entries.abilities.values().collect(),
+            moves: entries.moves.values().collect(),
+            items: entries.items.values().collect(),
+            types: entries.types.values().collect(),
+        }
+    }
+}
+
+/// One line of `pokedex.jsonl`, e.g. `{"kind":"pokemon","data":{…}}`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum Record<'a> {
+    /// Always the first line
+    Header {
+        #[serde(rename = "$schema")]
+        schema: &'static str,
+        version: u32,
+    },
+    /// Always the second line
+    Index(&'a Index),
+    Pokemon(&'a MonEntry),
+    Ability(&'a AbilityEntry),
+    Move(&'a MoveEntry),
+    Item(&'a ItemEntry),
+    Type(&'a TypeEntry),
+}
+
+/// All records of `pokedex.jsonl`, in order: header, index, then entries by kind and ID.
+pub fn records<'a>(dict: &Dictionary<'a>) -> impl Iterator<Item = Record<'a>> {
+    let entries = dict.entries;
+    [
+        Record::Header {
+            schema: SCHEMA_FILE,
+            version: SCHEMA_VERSION,
+        },
+        Record::Index(dict.index),
+    ]
+    .into_iter()
+    .chain(entries.pokemon.values().map(Record::Pokemon))
+    .chain(entries.abilities.values().map(Record::Ability))
+    .chain(entries.moves.values().map(Record::Move))
+    .chain(entries.items.values().map(Record::Item))
+    .chain(entries.types.values().map(Record::Type))
+}
@@ -0,0 +1,228 @@
+//! Kana conversions for Japanese index keys.
+
+/// Converts katakana to hiragana, leaving anything else (including ー) as is.
+pub fn to_hiragana(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+            c => c,
+        })
+        .collect()
+}
+
+/// Romanizes kana using Hepburn, spelling long vowels out (“Pikachuu” for ピカチュウ).
+/// Returns `None` if the text contains anything other than kana.
+pub fn to_hepburn(s: &str) -> Option<String> {
+    let kana: Vec<char> = to_hiragana(s).chars().collect();
+    let mut out = String::new();
+    let mut double_next = false;
+
+    let mut i = 0;
+    while i < kana.len() {
+        let c = kana[i];
+        i += 1;
+
+        match c {
+            'っ' => {
+                double_next = true;
+                continue;
+            }
+            'ー' => {
+                let vowel = out.chars().last().filter(|c| "aiueo".contains(*c))?;
+                out.push(vowel);
+                continue;
+            }
+            _ => (),
+        }
+
+        let mut syllable = syllable(c)?.to_string();
+
+        // combine with a following small kana, e.g. きゃ → kya, ふぁ → fa, うぃ → wi. A small
+        // vowel that repeats the syllable's vowel lengthens it instead, e.g. ピィ → pii
+        if let Some(&small) = kana.get(i) {
+            let vowel = match small {
+                'ゃ' => Some(('a', true)),
+                'ゅ' => Some(('u', true)),
+                'ょ' => Some(('o', true)),
+                'ぁ' => Some(('a', false)),
+                'ぃ' => Some(('i', false)),
+                'ぅ' => Some(('u', false)),
+                'ぇ' => Some(('e', false)),
+                'ぉ' => Some(('o', false)),
+                _ => None,
+            };
+            if let Some((vowel, is_yoon)) = vowel.filter(|&(vowel, _)| !syllable.ends_with(vowel)) {
+                i += 1;
+                if syllable == "u" {
+                    syllable = String::from("w");
+                } else {
+                    syllable.pop();
+                }
+                if is_yoon && !["sh", "ch", "j"].iter().any(|s| syllable.ends_with(s)) {
+                    syllable.push('y');
+                }
+                syllable.push(vowel);
+            }
+        }
+
+        if double_next {
+            double_next = false;
+            if syllable.starts_with("ch") {
+                out.push('t');
+            } else if let Some(first) = syllable.chars().next().filter(|c| !"aiueon".contains(*c)) {
+                out.push(first);
+            }
+        }
+        out.push_str(&syllable);
+    }
+
+    let mut chars = out.chars();
+    let first = chars.next()?;
+    Some(first.to_uppercase().chain(chars).collect())
+}
+
+/// Romanization without long vowels, the way most people type names (“Pikachu”). A vowel is
+/// dropped if it repeats the one before it (except “ii”), or is a “u” after an “o”.
+pub fn simplify_long_vowels(romaji: &str) -> String {
+    let mut out = String::new();
+    let mut last = None;
+    for c in romaji.chars() {
+        let lower = c.to_ascii_lowercase();
+        let is_long = match (last, lower) {
+            (Some(a), b) if a == b && "aeuo".contains(b) => true,
+            (Some('o'), 'u') => true,
+            _ => false,
+        };
+        if !is_long {
+            out.push(c);
+        }
+        last = Some(lower);
+    }
+    out
+}
+
+fn syllable(c: char) -> Option<&'static str> {
+    Some(match c {
+        'あ' | 'ぁ' => "a",
+        'い' | 'ぃ' => "i",
+        'う' | 'ぅ' => "u",
+        'え' | 'ぇ' => "e",
+        'お' | 'ぉ' => "o",
+        'か' => "ka",
+        'き' => "ki",
+        'く' => "ku",
+        'け' => "ke",
+        'こ' => "ko",
+        'が' => "ga",
+        'ぎ' => "gi",
+        'ぐ' => "gu",
+        'げ' => "ge",
+        'ご' => "go",
+        'さ' => "sa",
+        'し' => "shi",
+        'す' => "su",
+        'せ' => "se",
+        'そ' => "so",
+        'ざ' => "za",
+        'じ' => "ji",
+        'ず' => "zu",
+        'ぜ' => "ze",
+        'ぞ' => "zo",
+        'た' => "ta",
+        'ち' => "chi",
+        'つ' => "tsu",
+        'て' => "te",
+        'と' => "to",
+        'だ' => "da",
+        'ぢ' => "ji",
+        'づ' => "zu",
+        'で' => "de",
+        'ど' => "do",
+        'な' => "na",
+        'に' => "ni",
+        'ぬ' => "nu",
+        'ね' => "ne",
+        'の' => "no",
+        'は' => "ha",
+        'ひ' => "hi",
+        'ふ' => "fu",
+        'へ' => "he",
+        'ほ' => "ho",
+        'ば' => "ba",
+        'び' => "bi",
+        'ぶ' => "bu",
+        'べ' => "be",
+        'ぼ' => "bo",
+        'ぱ' => "pa",
+        'ぴ' => "pi",
+        'ぷ' => "pu",
+        'ぺ' => "pe",
+        'ぽ' => "po",
+        'ま' => "ma",
+        'み' => "mi",
+        'む' => "mu",
+        'め' => "me",
+        'も' => "mo",
+        'や' | 'ゃ' => "ya",
+        'ゆ' | 'ゅ' => "yu",
+        'よ' | 'ょ' => "yo",
+        'ら' => "ra",
+        'り' => "ri",
+        'る' => "ru",
+        'れ' => "re",
+        'ろ' => "ro",
+        'わ' => "wa",
+        'ゐ' => "i",
+        'ゑ' => "e",
+        'を' => "o",
+        'ん' => "n",
+        'ゔ' => "vu",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hiragana() {
+        assert_eq!(to_hiragana("ピカチュウ"), "ぴかちゅう");
+        assert_eq!(to_hiragana("ポリゴンＺ"), "ぽりごんＺ");
+        assert_eq!(to_hiragana("ホウオウ"), "ほうおう");
+        assert_eq!(to_hiragana("オーロット"), "おーろっと");
+        assert_eq!(to_hiragana("ヴ"), "ゔ");
+    }
+
+    #[test]
+    fn hepburn() {
+        assert_eq!(to_hepburn("ピカチュウ").as_deref(), Some("Pikachuu"));
+        assert_eq!(to_hepburn("ホウオウ").as_deref(), Some("Houou"));
+        assert_eq!(to_hepburn("フラベベ").as_deref(), Some("Furabebe"));
+        assert_eq!(to_hepburn("オーロット").as_deref(), Some("Oorotto"));
+        assert_eq!(to_hepburn("マッチョ").as_deref(), Some("Matcho"));
+        assert_eq!(to_hepburn("シャワーズ").as_deref(), Some("Shawaazu"));
+        assert_eq!(to_hepburn("ウィ").as_deref(), Some("Wi"));
+        assert_eq!(to_hepburn("ウォーグル").as_deref(), Some("Wooguru"));
+        assert_eq!(to_hepburn("ヴィ").as_deref(), Some("Vi"));
+        assert_eq!(to_hepburn("ファイヤー").as_deref(), Some("Faiyaa"));
+        assert_eq!(to_hepburn("ピィ").as_deref(), Some("Pii"));
+        assert_eq!(to_hepburn("ププリン").as_deref(), Some("Pupurin"));
+        // not kana only
+        assert_eq!(to_hepburn("ポリゴンＺ"), None);
+        assert_eq!(to_hepburn("ポリゴンZ"), None);
+        assert_eq!(to_hepburn("ー"), None);
+        assert_eq!(to_hepburn(""), None);
+    }
+
+    #[test]
+    fn long_vowels() {
+        assert_eq!(simplify_long_vowels("Pikachuu"), "Pikachu");
+        assert_eq!(simplify_long_vowels("Houou"), "Hoo");
+        assert_eq!(simplify_long_vowels("Oorotto"), "Orotto");
+        assert_eq!(simplify_long_vowels("Wooguru"), "Woguru");
+        assert_eq!(simplify_long_vowels("Pii"), "Pii");
+        assert_eq!(simplify_long_vowels("Furabebe"), "Furabebe");
+        assert_eq!(simplify_long_vowels("Shawaazu"), "Shawazu");
+    }
+}
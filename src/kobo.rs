@@ -0,0 +1,121 @@
+//! Kobo dictionaries (`dicthtml`), as documented by [dictutil].
+//!
+//! [dictutil]: https://pgaskin.net/dictutil/dicthtml.html
+
+use crate::backend::{Backend, Dictionary};
+use crate::marisa::build_trie;
+use crate::rendered::{rewrite_images, strip_links};
+use crate::xhtml::XhtmlEscaped;
+use anyhow::Context;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Image file extensions Kobo readers can show.
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif"];
+
+/// A zip with one gzipped HTML shard per word prefix, a MARISA trie of all words and the images
+/// (referenced as `dict:///<file>`). Index keys become variants, and entries are repeated in the
+/// shard of every prefix their words have. Kobo can't follow links between entries, so those are
+/// removed.
+pub struct Kobo;
+
+impl Backend for Kobo {
+    fn name(&self) -> &'static str {
+        "kobo"
+    }
+
+    fn write(&self, dict: &Dictionary, out_dir: &Path) -> anyhow::Result<()> {
+        let entries = dict.rendered_entries()?;
+
+        let mut shards: BTreeMap<String, String> = BTreeMap::new();
+        let mut words = BTreeSet::new();
+        let mut images = BTreeSet::new();
+        for entry in &entries {
+            let html = rewrite_images(&strip_links(&entry.html), |file| {
+                let ext = file.rsplit_once('.').map_or("", |(_, ext)| ext);
+                if !IMAGE_EXTENSIONS.contains(&&*ext.to_ascii_lowercase()) {
+                    return String::from("#");
+                }
+                images.insert(file.to_string());
+                format!("dict:///{}", XhtmlEscaped(file, true))
+            });
+
+            // Kobo lowercases the word it looks up
+            let mut entry_words: Vec<String> = Vec::new();
+            for word in entry.words() {
+                let word = word.trim().to_lowercase();
+                if !word.is_empty() && !entry_words.contains(&word) {
+                    entry_words.push(word);
+                }
+            }
+            let Some((headword, variants)) = entry_words.split_first() else {
+                continue;
+            };
+
+            let mut w = format!(r#"<w><a name="{}" />"#, XhtmlEscaped(headword, true));
+            if !variants.is_empty() {
+                w += "<var>";
+                for variant in variants {
+                    write!(w, r#"<variant name="{}" />"#, XhtmlEscaped(variant, true))?;
+                }
+                w += "</var>";
+            }
+            w += &html;
+            w += "</w>";
+
+            let prefixes: BTreeSet<_> = entry_words.iter().map(|word| word_prefix(word)).collect();
+            for prefix in prefixes {
+                shards.entry(prefix).or_default().push_str(&w);
+            }
+            words.extend(entry_words);
+        }
+
+        fs::create_dir_all(out_dir)?;
+        let path = out_dir.join("dicthtml.zip");
+        let mut zip = ZipWriter::new(File::create(&path).context("error creating dicthtml.zip")?);
+        // shards and images are compressed already
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+
+        let words: Vec<_> = words.into_iter().collect();
+        zip.start_file("words", options)?;
+        zip.write_all(&build_trie(&words)?)?;
+
+        for (prefix, html) in shards {
+            let mut gz = GzEncoder::new(Vec::new(), Compression::best());
+            gz.write_all(b"<html>")?;
+            gz.write_all(html.as_bytes())?;
+            gz.write_all(b"</html>")?;
+            zip.start_file(format!("{prefix}.html"), options)?;
+            zip.write_all(&gz.finish()?)?;
+        }
+
+        for file in images {
+            let data = fs::read(dict.image_dir.join(&file))
+                .with_context(|| format!("error reading image {file}"))?;
+            zip.start_file(file, options)?;
+            zip.write_all(&data)?;
+        }
+        zip.finish().context("error writing dicthtml.zip")?;
+        Ok(())
+    }
+}
+
+/// The shard a word goes in: its first two characters, with “a” standing in for a missing or
+/// non-letter second character and “11” for words that don't start with a letter.
+fn word_prefix(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) if first.is_alphabetic() => {
+            let second = chars.next().filter(|c| c.is_alphabetic()).unwrap_or('a');
+            format!("{first}{second}")
+        }
+        _ => String::from("11"),
+    }
+}
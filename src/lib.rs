@@ -0,0 +1,273 @@
+//! Scrapes Pokémon, abilities, moves, items and types from Bulbapedia and turns them into
+//! dictionary entries.
+//!
+//! The usual flow is:
+//! 1. [`read_index`] to find all pages, optionally followed by [`Index::select`],
+//! 2. [`read_entries`] to fetch and extract every page into entry structs like [`MonEntry`],
+//!    followed by [`Entries::select`] if Pokémon were selected,
+//! 3. a [`Backend`] to render the entries, e.g. [`backend::AppleDictionary`] for Apple
+//!    Dictionary XML.
+//!
+//! ```no_run
+//! use pokedex_dictgen::{read_entries, read_index, Config, Fetcher, ImageCache};
+//!
+//! let fetcher = Fetcher::new("data/fetch_cache".into());
+//! let images = ImageCache::new("data/images".into());
+//! let config = Config::default();
+//!
+//! let index = read_index(&fetcher)?;
+//! let entries = read_entries(&fetcher, &index, &images, &config, None)?;
+//! for mon in entries.pokemon.values() {
+//!     println!("{} {}", mon.dex_id, mon.name);
+//! }
+//! # anyhow::Ok(())
+//! ```
+
+use anyhow::Context;
+use rayon::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+pub mod ability;
+pub mod backend;
+pub mod cache;
+mod date;
+pub mod dict_server;
+pub mod dictd;
+pub mod dictzip;
+pub mod dsl;
+pub mod epub;
+pub mod evolution;
+pub mod fetcher;
+pub mod forms;
+pub mod gen;
+pub mod image;
+pub mod index;
+pub mod items;
+pub mod json;
+mod kana;
+pub mod kobo;
+pub mod links;
+pub mod marisa;
+pub mod mdict;
+pub mod mon;
+pub mod moves;
+mod names;
+pub mod page;
+pub mod rendered;
+pub mod stardict;
+pub mod types;
+pub mod xdxf;
+mod xhtml;
+pub mod yomitan;
+
+pub use ability::{read_ability, AbilityEntry};
+pub use backend::{Backend, Dictionary};
+pub use cache::EntryCache;
+pub use fetcher::Fetcher;
+pub use gen::generate_dictionary;
+pub use image::ImageCache;
+pub use index::{read_index, AbilityId, DexId, Index, ItemId, MoveId, Selection, TypeId};
+pub use items::{read_item, ItemEntry};
+pub use mon::{read_mon, MonEntry};
+pub use moves::{read_move, MoveEntry};
+pub use types::{read_type, TypeEntry};
+
+/// Extraction options.
+#[derive(Debug, Serialize)]
+pub struct Config {
+    /// Load full-size Pokémon images instead of thumbnails
+    pub hq_pokemon_images: bool,
+    /// Load full-size images in the summary and body instead of thumbnails
+    pub hq_body_images: bool,
+    /// How many body sections (“Biology”, “In the anime”, etc.) to keep
+    pub max_body_sections: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            hq_pokemon_images: false,
+            hq_body_images: false,
+            max_body_sections: 1,
+        }
+    }
+}
+
+/// All entries extracted from the pages in an [`Index`].
+#[derive(Debug, Default)]
+pub struct Entries {
+    pub pokemon: BTreeMap<DexId, MonEntry>,
+    pub abilities: BTreeMap<AbilityId, AbilityEntry>,
+    pub moves: BTreeMap<MoveId, MoveEntry>,
+    pub items: BTreeMap<ItemId, ItemEntry>,
+    pub types: BTreeMap<TypeId, TypeEntry>,
+}
+
+impl Entries {
+    /// Drops abilities, moves and items that no selected Pokémon has and that weren't introduced
+    /// in a selected generation (see [`Selection::includes_gen`]), along with their pages in the
+    /// index. Links to them are removed when generating the dictionary.
+    pub fn select(&mut self, index: &mut Index, selection: &Selection) {
+        if selection.is_all() {
+            return;
+        }
+        let in_gen = |gen: Option<&usize>| gen.is_some_and(|gen| selection.includes_gen(*gen));
+
+        let form_abilities: BTreeSet<AbilityId> = self
+            .pokemon
+            .values()
+            .flat_map(|mon| &mon.forms)
+            .flat_map(|form| &form.abilities)
+            .filter_map(|ability| ability.id)
+            .collect();
+        self.abilities.retain(|id, ability| {
+            form_abilities.contains(id)
+                || ability
+                    .pokemon
+                    .iter()
+                    .any(|mon| index.has_pokemon(mon.dex_id))
+                || in_gen(index.ability_gens.get(id))
+        });
+        self.moves.retain(|id, move_entry| {
+            move_entry
+                .learners
+                .iter()
+                .any(|learner| index.has_pokemon(learner.dex_id))
+                || in_gen(index.move_gens.get(id))
+        });
+        self.items.retain(|id, item| {
+            item.pokemon.iter().any(|mon| index.has_pokemon(mon.dex_id))
+                || in_gen(index.item_gens.get(id))
+        });
+
+        index
+            .ability_pages
+            .retain(|id, _| self.abilities.contains_key(id));
+        index.move_pages.retain(|id, _| self.moves.contains_key(id));
+        index.item_pages.retain(|id, _| self.items.contains_key(id));
+    }
+}
+
+/// Reads every page in the index, in parallel. Entries are reused from `cache` where possible.
+pub fn read_entries(
+    fetcher: &Fetcher,
+    index: &Index,
+    image_cache: &ImageCache,
+    config: &Config,
+    cache: Option<&EntryCache>,
+) -> anyhow::Result<Entries> {
+    let cached = |name: String, url: &str| CachedRead {
+        fetcher,
+        image_cache,
+        cache,
+        name,
+        url: url.to_string(),
+        dependencies: index.link_targets(url),
+    };
+
+    let pokemon = index
+        .pokemon_pages
+        .par_iter()
+        .map(|(id, url)| {
+            let mon = cached(format!("pokemon-{}", id.0), url)
+                .run(|| read_mon(fetcher, index, image_cache, config, url))
+                .with_context(|| format!("error reading {id}"))?;
+            Ok((*id, mon))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    let abilities = index
+        .ability_pages
+        .par_iter()
+        .map(|(id, url)| {
+            let ability = cached(format!("ability-{}", id.0), url)
+                .run(|| read_ability(fetcher, index, image_cache, config, *id, url))
+                .with_context(|| format!("error reading ability {id}"))?;
+            Ok((*id, ability))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    let moves = index
+        .move_pages
+        .par_iter()
+        .map(|(id, page)| {
+            let move_entry = cached(format!("move-{}", id.0), &page.url)
+                .depending_on(page)?
+                .run(|| read_move(fetcher, index, image_cache, config, *id))
+                .with_context(|| format!("error reading move {id}"))?;
+            Ok((*id, move_entry))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    let items = index
+        .item_pages
+        .par_iter()
+        .map(|(id, url)| {
+            let item = cached(format!("item-{id}"), url)
+                .run(|| read_item(fetcher, index, image_cache, config, id, url))
+                .with_context(|| format!("error reading item {id}"))?;
+            Ok((id.clone(), item))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    let types = index
+        .type_pages
+        .par_iter()
+        .map(|(id, url)| {
+            let type_entry = cached(format!("type-{}", id.slug()), url)
+                .run(|| read_type(fetcher, index, image_cache, config, *id, url))
+                .with_context(|| format!("error reading type {id}"))?;
+            Ok((*id, type_entry))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok(Entries {
+        pokemon,
+        abilities,
+        moves,
+        items,
+        types,
+    })
+}
+
+/// Reads one entry, going through the entry cache if there is one.
+struct CachedRead<'a> {
+    fetcher: &'a Fetcher,
+    image_cache: &'a ImageCache,
+    cache: Option<&'a EntryCache>,
+    name: String,
+    url: String,
+    /// What the entry depends on besides the page, see [`Index::link_targets`]
+    dependencies: Option<String>,
+}
+
+impl CachedRead<'_> {
+    /// Adds something from outside the page that the entry depends on.
+    fn depending_on(mut self, value: &impl Serialize) -> anyhow::Result<Self> {
+        if let Some(dependencies) = &mut self.dependencies {
+            dependencies.push_str(&serde_json::to_string(value)?);
+            dependencies.push('\n');
+        }
+        Ok(self)
+    }
+
+    fn run<T: Serialize + DeserializeOwned>(
+        self,
+        read: impl FnOnce() -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        match (self.cache, &self.dependencies) {
+            (Some(cache), Some(dependencies)) => cache.get_or_read(
+                self.fetcher,
+                self.image_cache,
+                &self.name,
+                &self.url,
+                dependencies,
+                read,
+            ),
+            // without the page's links, there's no telling whether a cached entry is still valid
+            _ => read(),
+        }
+    }
+}
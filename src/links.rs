@@ -0,0 +1,519 @@
+use crate::fetcher::Fetcher;
+use crate::index::{AbilityId, DexId, ItemId, MoveId, TypeId};
+use crate::page::get_attr;
+use anyhow::Context;
+use kuchikiki::traits::TendrilSink;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+use std::path::Path;
+use std::{fs, io};
+use url::Url;
+
+/// MediaWiki API of the wiki, for looking up redirects.
+const API_URL: &str = "https://bulbapedia.bulbagarden.net/w/api.php";
+
+/// Titles per API query, MediaWiki's limit for normal users.
+const API_BATCH_SIZE: usize = 50;
+
+/// Bump this whenever [`read_page_links`] changes what it finds, to invalidate cached link lists.
+const PAGE_LINKS_VERSION: u32 = 1;
+
+/// A page that has an entry in the dictionary.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EntryRef {
+    Pokemon(DexId),
+    Ability(AbilityId),
+    Move(MoveId),
+    Item(ItemId),
+    Type(TypeId),
+}
+
+impl EntryRef {
+    /// The `d:entry` ID.
+    pub fn entry_id(&self) -> String {
+        match self {
+            EntryRef::Pokemon(id) => format!("pokemon-{}", id.0),
+            EntryRef::Ability(id) => format!("ability-{}", id.0),
+            EntryRef::Move(id) => format!("move-{}", id.0),
+            EntryRef::Item(id) => format!("item-{id}"),
+            EntryRef::Type(id) => format!("type-{}", id.slug()),
+        }
+    }
+}
+
+/// Maps wiki URLs to dictionary entries.
+///
+/// Wiki pages are often linked through redirects (e.g. `/wiki/Pikachu` instead of
+/// `/wiki/Pikachu_(Pokémon)`). Redirects linked from the pages are looked up before reading them
+/// (see [`LinkResolver::learn_redirects`]) and kept in a table on disk, so later runs only look up
+/// new ones.
+#[derive(Debug, Default)]
+pub struct LinkResolver {
+    /// Page key → entry
+    entries: HashMap<String, EntryRef>,
+    /// Page title → entry, for titles that belong to exactly one entry
+    titles: HashMap<String, Option<EntryRef>>,
+    /// Redirect page key → target page key
+    redirects: BTreeMap<String, String>,
+    /// Page URL → links on the page, for the pages [`Self::learn_redirects`] looked at
+    page_links: HashMap<String, Vec<PageLink>>,
+}
+
+/// A link to a wiki page, as far as resolving it goes.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PageLink {
+    /// Absolute URL, without fragment
+    pub url: String,
+    /// Whether MediaWiki marked the link with `mw-redirect`
+    pub redirect: bool,
+}
+
+/// Identifies a page regardless of fragment, query or percent-encoding differences.
+fn page_key(url: &Url) -> Option<String> {
+    let path = url.path().strip_prefix("/wiki/")?;
+    let path = urlencoding::decode(path).ok()?;
+    Some(format!("{}/{}", url.host_str().unwrap_or_default(), path))
+}
+
+/// The page title without a disambiguation suffix, e.g. “Pikachu” for `Pikachu_(Pokémon)`.
+pub fn base_title(url: &Url) -> Option<String> {
+    let path = url.path().strip_prefix("/wiki/")?;
+    let title = urlencoding::decode(path).ok()?.replace('_', " ");
+    let title = match title.rsplit_once(" (") {
+        Some((base, suffix)) if suffix.ends_with(')') => base.to_string(),
+        _ => title,
+    };
+    Some(title)
+}
+
+impl LinkResolver {
+    pub fn insert(&mut self, url: &str, entry: EntryRef) {
+        let Ok(url) = Url::parse(url) else {
+            return;
+        };
+        let Some(key) = page_key(&url) else {
+            return;
+        };
+        if let Some(title) = base_title(&url) {
+            self.titles
+                .entry(title)
+                .and_modify(|e| *e = None)
+                .or_insert_with(|| Some(entry.clone()));
+        }
+        self.entries.insert(key, entry);
+    }
+
+    /// Resolves a link target to an entry.
+    /// `is_redirect` should be set for links MediaWiki marked as pointing to a redirect page.
+    pub fn resolve(&self, url: &Url, is_redirect: bool) -> Option<EntryRef> {
+        let key = page_key(url)?;
+        if let Some(entry) = self.entries.get(&key) {
+            return Some(entry.clone());
+        }
+        if let Some(target) = self.redirects.get(&key) {
+            return self.entries.get(target).cloned();
+        }
+        if is_redirect {
+            // we haven't seen this redirect yet, but it's probably just the bare name
+            return self.titles.get(&base_title(url)?).cloned().flatten();
+        }
+        None
+    }
+
+    /// Looks up the redirects that `pages` link to (MediaWiki marks those links with
+    /// `mw-redirect`) and aren't in the table yet, using the MediaWiki API. This has to happen
+    /// before the pages are read, so that every page sees the same table.
+    ///
+    /// The links on each page are kept for [`Self::page_links`], and cached in `cache_dir` by page
+    /// content, so unchanged pages don't need to be parsed again.
+    pub fn learn_redirects(
+        &mut self,
+        fetcher: &Fetcher,
+        pages: &[String],
+        cache_dir: &Path,
+    ) -> anyhow::Result<()> {
+        fs::create_dir_all(cache_dir)?;
+        let page_links = pages
+            .par_iter()
+            .map(|url| Ok((url.clone(), read_page_links(fetcher, url, cache_dir)?)))
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
+        let targets: BTreeSet<String> = page_links
+            .values()
+            .flatten()
+            .filter(|link| link.redirect)
+            .filter_map(|link| page_key(&Url::parse(&link.url).ok()?))
+            .collect();
+        self.page_links.extend(page_links);
+
+        let host = Url::parse(API_URL)?
+            .host_str()
+            .unwrap_or_default()
+            .to_string();
+        let titles: Vec<String> = targets
+            .iter()
+            .filter(|key| !self.entries.contains_key(*key) && !self.redirects.contains_key(*key))
+            .filter_map(|key| key.strip_prefix(&format!("{host}/")))
+            .map(|path| path.replace('_', " "))
+            .collect();
+        for batch in titles.chunks(API_BATCH_SIZE) {
+            for (from, to) in query_redirects(fetcher, batch)? {
+                let key = |title: &str| format!("{host}/{}", title.replace(' ', "_"));
+                self.redirects.insert(key(&from), key(&to));
+            }
+        }
+        Ok(())
+    }
+
+    /// The links on a page, if [`Self::learn_redirects`] looked at it.
+    pub fn page_links(&self, page: &str) -> Option<&[PageLink]> {
+        self.page_links.get(page).map(Vec::as_slice)
+    }
+
+    /// The redirect table, sorted.
+    pub fn redirects(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.redirects
+            .iter()
+            .map(|(from, to)| (from.as_str(), to.as_str()))
+    }
+
+    /// Loads the redirect table from a tab-separated file, if it exists.
+    pub fn load_redirects(&mut self, path: &Path) -> io::Result<()> {
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        for line in data.lines() {
+            if let Some((from, to)) = line.split_once('\t') {
+                self.redirects.insert(from.to_string(), to.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save_redirects(&self, path: &Path) -> io::Result<()> {
+        let mut data = String::new();
+        for (from, to) in &self.redirects {
+            writeln!(data, "{from}\t{to}").unwrap();
+        }
+        fs::write(path, data)
+    }
+}
+
+/// The distinct links to wiki pages on a page, read from `cache_dir` if the same page content was
+/// read before.
+fn read_page_links(
+    fetcher: &Fetcher,
+    url: &str,
+    cache_dir: &Path,
+) -> anyhow::Result<Vec<PageLink>> {
+    let page = fetcher.get(url, true)?;
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&PAGE_LINKS_VERSION.to_le_bytes());
+    hasher.update(&page);
+    let path = cache_dir.join(format!("{}.json", hasher.finalize().to_hex()));
+    // a broken cache file is just a cache miss
+    if let Some(links) = fs::read(&path)
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+    {
+        return Ok(links);
+    }
+
+    let base = Url::parse(url)?;
+    let doc = kuchikiki::parse_html().one(String::from_utf8(page)?);
+    let mut links = BTreeSet::new();
+    if let Ok(anchors) = doc.select("a[href]") {
+        for anchor in anchors {
+            let node = anchor.as_node();
+            let href = get_attr(node, "href").unwrap_or_default();
+            let Ok(mut url) = base.join(&href) else {
+                continue;
+            };
+            if href.starts_with('#') || page_key(&url).is_none() {
+                continue;
+            }
+            url.set_fragment(None);
+            let redirect = get_attr(node, "class")
+                .is_some_and(|class| class.split(' ').any(|c| c == "mw-redirect"));
+            links.insert(PageLink {
+                url: url.to_string(),
+                redirect,
+            });
+        }
+    }
+    let links: Vec<_> = links.into_iter().collect();
+    fs::write(&path, serde_json::to_vec(&links)?).context("error writing page links")?;
+    Ok(links)
+}
+
+/// Asks the MediaWiki API where the given page titles redirect to, following double redirects.
+/// Titles that aren't redirects are left out.
+fn query_redirects(fetcher: &Fetcher, titles: &[String]) -> anyhow::Result<Vec<(String, String)>> {
+    let url = Url::parse_with_params(
+        API_URL,
+        [
+            ("action", "query"),
+            ("format", "json"),
+            ("redirects", "1"),
+            ("titles", &titles.join("|")),
+        ],
+    )?;
+    let response: Value = serde_json::from_slice(&fetcher.get(url.as_str(), true)?)
+        .context("error parsing redirect query")?;
+    let pairs = |name: &str| -> HashMap<String, String> {
+        response["query"][name]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|pair| {
+                Some((
+                    pair["from"].as_str()?.to_string(),
+                    pair["to"].as_str()?.to_string(),
+                ))
+            })
+            .collect()
+    };
+    let normalized = pairs("normalized");
+    let redirects = pairs("redirects");
+
+    let mut resolved = Vec::new();
+    for title in titles {
+        let from = normalized.get(title).unwrap_or(title);
+        let mut seen = vec![from];
+        while let Some(next) = redirects.get(*seen.last().unwrap()) {
+            if seen.contains(&next) {
+                // a redirect loop doesn't lead anywhere
+                seen.truncate(1);
+                break;
+            }
+            seen.push(next);
+        }
+        if let [_, .., to] = &seen[..] {
+            resolved.push((title.clone(), to.to_string()));
+        }
+    }
+    Ok(resolved)
+}
+
+/// Drops `#fragment`s from `x-dictionary:r:` links whose target entry doesn't contain the anchor,
+/// e.g. because we only kept some sections of the page, and removes links to entries that aren't
+/// in the dictionary (keeping their contents), e.g. moves dropped by
+/// [`Entries::select`](crate::Entries::select).
+pub fn fix_entry_links(xml: &str) -> String {
+    const LINK_START: &str = "href=\"x-dictionary:r:";
+
+    let mut anchors: HashMap<&str, HashSet<&str>> = HashMap::new();
+    let mut current_entry = None;
+    let mut rest = xml;
+    while let Some(i) = rest.find(" id=\"") {
+        let is_entry = rest[..i + 1].ends_with("<d:entry ");
+        rest = &rest[i + 5..];
+        let Some(end) = rest.find('"') else {
+            break;
+        };
+        let id = &rest[..end];
+        if is_entry {
+            current_entry = Some(id);
+            anchors.entry(id).or_default();
+        } else if let Some(entry) = current_entry {
+            anchors.entry(entry).or_default().insert(id);
+        }
+    }
+
+    let mut out = String::with_capacity(xml.len());
+    let mut rest = xml;
+    while let Some(i) = rest.find(LINK_START) {
+        out.push_str(&rest[..i + LINK_START.len()]);
+        rest = &rest[i + LINK_START.len()..];
+        let Some(end) = rest.find('"') else {
+            break;
+        };
+        let target = &rest[..end];
+        let (entry, fragment) = match target.split_once('#') {
+            Some((entry, fragment)) => (entry, Some(fragment)),
+            None => (target, None),
+        };
+        let Some(entry_anchors) = anchors.get(entry) else {
+            // links don't nest, so the next closing tag is this link's
+            let (Some(tag_start), Some(tag_end)) = (out.rfind("<a "), rest.find('>')) else {
+                out.push_str(target);
+                rest = &rest[end..];
+                continue;
+            };
+            out.truncate(tag_start);
+            rest = &rest[tag_end + 1..];
+            if let Some(close) = rest.find("</a>") {
+                out.push_str(&rest[..close]);
+                rest = &rest[close + "</a>".len()..];
+            }
+            continue;
+        };
+        match fragment {
+            Some(fragment) if !entry_anchors.contains(fragment) => out.push_str(entry),
+            _ => out.push_str(target),
+        }
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn learn_redirects() {
+        let dir = std::env::temp_dir().join(format!("pokedex-links-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let fetcher = Fetcher::new(dir.clone());
+        let cache = |url: &str, data: &str| fs::write(fetcher.cache_path(url), data).unwrap();
+
+        let page = "https://bulbapedia.bulbagarden.net/wiki/Raichu_(Pok%C3%A9mon)";
+        cache(
+            page,
+            r#"<p>Evolves from <a href="/wiki/Pikachu" class="mw-redirect">Pikachu</a>,
+            <a href="/wiki/Thunderbolt_(move)">Thunderbolt</a>,
+            <a href="/wiki/Sparky" class="mw-redirect">Sparky</a>,
+            <a href="/wiki/Loop" class="mw-redirect">Loop</a></p>"#,
+        );
+        let query = Url::parse_with_params(
+            API_URL,
+            [
+                ("action", "query"),
+                ("format", "json"),
+                ("redirects", "1"),
+                ("titles", "Loop|Pikachu|Sparky"),
+            ],
+        )
+        .unwrap();
+        cache(
+            query.as_str(),
+            r#"{"query": {
+                "redirects": [
+                    {"from": "Pikachu", "to": "Pikachu (Pokémon)"},
+                    {"from": "Sparky", "to": "Pikachu"},
+                    {"from": "Loop", "to": "Loop 2"},
+                    {"from": "Loop 2", "to": "Loop"}
+                ]
+            }}"#,
+        );
+
+        let mut links = LinkResolver::default();
+        let pikachu = "https://bulbapedia.bulbagarden.net/wiki/Pikachu_(Pok%C3%A9mon)";
+        links.insert(pikachu, EntryRef::Pokemon(DexId(25)));
+        links.insert(page, EntryRef::Pokemon(DexId(26)));
+        links
+            .learn_redirects(&fetcher, &[page.to_string()], &dir.join("links"))
+            .unwrap();
+        // the page's links are cached by content
+        assert_eq!(fs::read_dir(dir.join("links")).unwrap().count(), 1);
+        let mut again = LinkResolver::default();
+        again
+            .learn_redirects(&fetcher, &[page.to_string()], &dir.join("links"))
+            .unwrap();
+        assert_eq!(again.page_links(page), links.page_links(page));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let page_links: Vec<_> = links
+            .page_links(page)
+            .unwrap()
+            .iter()
+            .map(|link| (link.url.as_str(), link.redirect))
+            .collect();
+        assert_eq!(
+            page_links,
+            [
+                ("https://bulbapedia.bulbagarden.net/wiki/Loop", true),
+                ("https://bulbapedia.bulbagarden.net/wiki/Pikachu", true),
+                ("https://bulbapedia.bulbagarden.net/wiki/Sparky", true),
+                (
+                    "https://bulbapedia.bulbagarden.net/wiki/Thunderbolt_(move)",
+                    false
+                ),
+            ]
+        );
+
+        assert_eq!(
+            links.redirects().collect::<Vec<_>>(),
+            [
+                (
+                    "bulbapedia.bulbagarden.net/Pikachu",
+                    "bulbapedia.bulbagarden.net/Pikachu_(Pokémon)"
+                ),
+                (
+                    "bulbapedia.bulbagarden.net/Sparky",
+                    "bulbapedia.bulbagarden.net/Pikachu_(Pokémon)"
+                ),
+            ]
+        );
+        let url = Url::parse("https://bulbapedia.bulbagarden.net/wiki/Sparky#Biology").unwrap();
+        assert_eq!(
+            links.resolve(&url, false),
+            Some(EntryRef::Pokemon(DexId(25)))
+        );
+    }
+
+    #[test]
+    fn learn_redirects_in_batches() {
+        let dir = std::env::temp_dir().join(format!("pokedex-batches-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let fetcher = Fetcher::new(dir.clone());
+        let cache = |url: &str, data: &str| {
+            let path = fetcher.cache_path(url);
+            assert!(path.file_name().unwrap().len() <= 255, "{}", path.display());
+            fs::write(path, data).unwrap();
+        };
+
+        // more titles than fit in one query, with long, percent-encoded names like real ones
+        let titles: Vec<String> = (0..60)
+            .map(|i| format!("Pokémon Scarlet and Violet Pokédex entry {i:02} (Pokémon)"))
+            .collect();
+        let page = "https://bulbapedia.bulbagarden.net/wiki/Pok%C3%A9dex";
+        let links: String = titles
+            .iter()
+            .map(|title| {
+                let href = urlencoding::encode(&title.replace(' ', "_")).into_owned();
+                format!(r#"<a href="/wiki/{href}" class="mw-redirect">{title}</a>"#)
+            })
+            .collect();
+        cache(page, &links);
+        for batch in titles.chunks(API_BATCH_SIZE) {
+            let query = Url::parse_with_params(
+                API_URL,
+                [
+                    ("action", "query"),
+                    ("format", "json"),
+                    ("redirects", "1"),
+                    ("titles", &batch.join("|")),
+                ],
+            )
+            .unwrap();
+            // too long for a file name as is
+            assert!(query.as_str().len() > 255);
+            let redirects: Vec<Value> = batch
+                .iter()
+                .map(|title| serde_json::json!({"from": title, "to": "Pikachu (Pokémon)"}))
+                .collect();
+            let response = serde_json::json!({"query": {"redirects": redirects}});
+            cache(query.as_str(), &response.to_string());
+        }
+
+        let mut links = LinkResolver::default();
+        let pikachu = "https://bulbapedia.bulbagarden.net/wiki/Pikachu_(Pok%C3%A9mon)";
+        links.insert(pikachu, EntryRef::Pokemon(DexId(25)));
+        links
+            .learn_redirects(&fetcher, &[page.to_string()], &dir.join("links"))
+            .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(links.redirects().count(), titles.len());
+        assert!(links
+            .redirects()
+            .all(|(_, to)| to == "bulbapedia.bulbagarden.net/Pikachu_(Pokémon)"));
+    }
+}
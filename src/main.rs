@@ -1,23 +1,27 @@
-use crate::fetcher::Fetcher;
-use crate::gen::generate_dictionary;
-use crate::image::ImageCache;
-use crate::index::read_index;
-use crate::mon::read_mon;
-use clap::Parser;
-use rayon::prelude::*;
-use std::collections::BTreeMap;
+use clap::{Parser, Subcommand};
+use pokedex_dictgen::backend::backend_by_name;
+use pokedex_dictgen::dict_server;
+use pokedex_dictgen::dictd::DictdDatabase;
+use pokedex_dictgen::index::parse_dex_range;
+use pokedex_dictgen::{
+    read_entries, read_index, Config, Dictionary, EntryCache, Fetcher, ImageCache, Selection,
+};
 use std::fs;
-use std::sync::Arc;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
 
-mod fetcher;
-mod gen;
-mod image;
-mod index;
-mod mon;
-mod xhtml;
+/// Redirects linked from the pages, see [`pokedex_dictgen::links::LinkResolver`].
+const REDIRECTS_PATH: &str = "data/redirects.tsv";
+/// Entries extracted in previous runs, see [`EntryCache`].
+const ENTRY_CACHE_PATH: &str = "data/entries";
+/// Links on each page, by page content, see [`pokedex_dictgen::Index::learn_redirects`].
+const PAGE_LINKS_PATH: &str = "data/page_links";
+const IMAGES_PATH: &str = "data/images";
 
 #[derive(Debug, Parser)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
     /// Will load high-resolution Pokémon images instead of just thumbnails.
     /// Enable this if you plan on zooming in.
     #[arg(long)]
@@ -32,17 +36,65 @@ struct Args {
     /// Enables both HQ Pokémon images and HQ body images.
     #[arg(long)]
     hq: bool,
+    /// Only includes Pokémon up to this generation, e.g. to avoid spoilers.
+    #[arg(long)]
+    max_gen: Option<usize>,
+    /// Only includes Pokémon from these generations (comma-separated).
+    #[arg(long, value_delimiter = ',')]
+    gens: Vec<usize>,
+    /// Only includes Pokémon in these national dex ranges, e.g. “1-151,251” (comma-separated).
+    #[arg(long, value_delimiter = ',', value_parser = parse_dex_range)]
+    dex: Vec<RangeInclusive<u32>>,
+    /// Only includes Pokémon with these names (comma-separated). Combines with --dex.
+    #[arg(long, value_delimiter = ',')]
+    names: Vec<String>,
+    /// Output formats to generate (comma-separated).
+    #[arg(long, value_delimiter = ',', default_value = "apple")]
+    format: Vec<String>,
+    /// Output directory, if generating a single format. Defaults to `ddk` for the Apple format
+    /// and `out/<format>` for others.
+    #[arg(long)]
+    out: Option<PathBuf>,
 }
 
-#[derive(Debug)]
-pub struct Config {
-    pub hq_pokemon_images: bool,
-    pub hq_body_images: bool,
-    pub max_body_sections: usize,
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Serves a dictionary written with `--format dictd` over the DICT protocol (RFC 2229) on
+    /// localhost, e.g. for `dict -h localhost pikachu`.
+    ServeDict {
+        /// Directory with `pokedex.index` and `pokedex.dict.dz`.
+        #[arg(long, default_value = "out/dictd")]
+        dir: PathBuf,
+        #[arg(long, default_value_t = 2628)]
+        port: u16,
+    },
 }
 
 fn main() {
     let args = Args::parse();
+    if let Some(Command::ServeDict { dir, port }) = args.command {
+        let result =
+            DictdDatabase::open(&dir).and_then(|db| dict_server::serve(db, ("127.0.0.1", port)));
+        if let Err(e) = result {
+            eprintln!("{e:#}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    let backends: Vec<_> = args
+        .format
+        .iter()
+        .map(|name| backend_by_name(name))
+        .collect::<Result<_, _>>()
+        .unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(1);
+        });
+    if args.out.is_some() && backends.len() > 1 {
+        eprintln!("--out can only be used with a single format");
+        std::process::exit(1);
+    }
+
     let config = Config {
         hq_pokemon_images: args.hq || args.hq_pokemon_images,
         hq_body_images: args.hq || args.hq_body_images,
@@ -50,41 +102,88 @@ fn main() {
     };
 
     fs::create_dir_all("data/fetch_cache").unwrap();
-    fs::create_dir_all("data/images").unwrap();
+    fs::create_dir_all(IMAGES_PATH).unwrap();
 
-    let fetcher = Arc::new(Fetcher::new("data/fetch_cache".into()));
-    let images = Arc::new(ImageCache::new("data/images".into()));
+    let fetcher = Fetcher::new("data/fetch_cache".into());
+    let images = ImageCache::new(IMAGES_PATH.into());
 
-    let index = read_index(&fetcher).unwrap_or_else(|e| {
+    let mut index = read_index(&fetcher).unwrap_or_else(|e| {
+        eprintln!("{e:#}");
+        std::process::exit(1);
+    });
+    let selection = Selection {
+        max_gen: args.max_gen,
+        gens: args.gens,
+        dex_ranges: args.dex,
+        names: args.names,
+    };
+    index.select(&selection).unwrap_or_else(|e| {
         eprintln!("{e:#}");
         std::process::exit(1);
     });
     eprintln!(
-        "got {} entries and {} generations",
+        "got {} entries, {} generations, {} abilities, {} moves and {} items",
         index.pokemon_pages.len(),
-        index.pokemon_gens.len()
+        index.pokemon_gens.len(),
+        index.ability_pages.len(),
+        index.move_pages.len(),
+        index.item_pages.len()
     );
+    index
+        .links
+        .load_redirects(Path::new(REDIRECTS_PATH))
+        .unwrap_or_else(|e| {
+            eprintln!("error loading redirects: {e}");
+            std::process::exit(1);
+        });
+    if let Err(e) = index.learn_redirects(&fetcher, Path::new(PAGE_LINKS_PATH)) {
+        eprintln!("error looking up redirects: {e:#}");
+    }
+    if let Err(e) = index.links.save_redirects(Path::new(REDIRECTS_PATH)) {
+        eprintln!("error saving redirects: {e}");
+    }
+    let cache = EntryCache::new(ENTRY_CACHE_PATH.into(), &config).unwrap_or_else(|e| {
+        eprintln!("error opening entry cache: {e:#}");
+        std::process::exit(1);
+    });
     eprintln!("loading data");
 
-    let pokemon: BTreeMap<_, _> = index
-        .pokemon_pages
-        .par_iter()
-        .map(|(id, url)| {
-            let mon = read_mon(&fetcher, &index, &images, &config, url).unwrap_or_else(|e| {
-                eprintln!("error reading {id}: {e:#}");
-                std::process::exit(1);
-            });
-            (*id, mon)
-        })
-        .collect();
+    let mut entries = read_entries(&fetcher, &index, &images, &config, Some(&cache))
+        .unwrap_or_else(|e| {
+            eprintln!("{e:#}");
+            std::process::exit(1);
+        });
+    if !selection.is_all() {
+        entries.select(&mut index, &selection);
+        eprintln!(
+            "selected {} abilities, {} moves and {} items",
+            entries.abilities.len(),
+            entries.moves.len(),
+            entries.items.len()
+        );
+    }
 
     eprintln!("generating entries");
 
-    let out = generate_dictionary(&index, &pokemon).unwrap_or_else(|e| {
-        eprintln!("error generating dictionary: {e:#}");
-        std::process::exit(1);
-    });
-    fs::write("ddk/Dictionary.xml", out).unwrap();
+    let dict = Dictionary {
+        index: &index,
+        entries: &entries,
+        image_dir: Path::new(IMAGES_PATH),
+    };
+    for backend in &backends {
+        let out_dir = args
+            .out
+            .clone()
+            .unwrap_or_else(|| backend.default_output_dir());
+        backend.write(&dict, &out_dir).unwrap_or_else(|e| {
+            eprintln!("error generating {} dictionary: {e:#}", backend.name());
+            std::process::exit(1);
+        });
+    }
+
+    if let Err(e) = images.prune() {
+        eprintln!("error removing unused images: {e}");
+    }
 
     eprintln!("done!");
 }
@@ -0,0 +1,322 @@
+//! Writes [MARISA] tries, the word list format Kobo dictionaries use.
+//!
+//! This builds a single patricia trie with the edge labels in a text tail, which libmarisa reads
+//! like any other trie. It doesn't share tail suffixes or use recursive tries, so files are larger
+//! than what libmarisa would build, but that doesn't matter for a few thousand words.
+//!
+//! [MARISA]: https://github.com/s-yata/marisa-trie
+
+use anyhow::ensure;
+use std::collections::VecDeque;
+
+const HEADER: &[u8; 16] = b"We love Marisa.\0";
+
+const NUM_TRIES: u32 = 1;
+const NORMAL_CACHE: u32 = 0x00200;
+const TEXT_TAIL: u32 = 0x01000;
+const LABEL_ORDER: u32 = 0x10000;
+
+const INVALID_EXTRA: u32 = u32::MAX >> 8;
+
+/// Builds a trie containing `words`, which may be unsorted and contain duplicates.
+pub fn build_trie<S: AsRef<str>>(words: &[S]) -> anyhow::Result<Vec<u8>> {
+    let mut keys: Vec<&[u8]> = words.iter().map(|word| word.as_ref().as_bytes()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in &keys {
+        ensure!(
+            !key.is_empty() && !key.contains(&0),
+            "invalid trie key {:?}",
+            String::from_utf8_lossy(key)
+        );
+    }
+
+    let mut cache_size = 256;
+    while cache_size < keys.len() / NORMAL_CACHE as usize {
+        cache_size *= 2;
+    }
+    let mut cache = vec![Cache::default(); cache_size];
+    let cache_mask = cache_size - 1;
+
+    // super-root, then root
+    let mut louds = BitVec::default();
+    louds.push(true);
+    louds.push(false);
+    let mut bases = vec![0u8];
+    let mut link_flags = BitVec::default();
+    link_flags.push(false);
+    let mut terminals = Vec::new();
+    let mut labels: Vec<&[u8]> = Vec::new();
+    let mut num_l1_nodes = 0;
+
+    // (first key, end key, position in the keys) of each node, in breadth-first order
+    let mut queue = VecDeque::from([(0, keys.len(), 0)]);
+    while let Some((mut begin, end, pos)) = queue.pop_front() {
+        let node_id = link_flags.len - queue.len() - 1;
+
+        // keys are sorted, so keys ending here come first
+        if begin < end && keys[begin].len() == pos {
+            terminals.push(node_id);
+            begin += 1;
+        }
+        if begin == end {
+            louds.push(false);
+            continue;
+        }
+
+        let mut children = Vec::new();
+        let mut child_begin = begin;
+        for i in begin + 1..end {
+            if keys[i - 1][pos] != keys[i][pos] {
+                children.push((child_begin, i));
+                child_begin = i;
+            }
+        }
+        children.push((child_begin, end));
+        if node_id == 0 {
+            num_l1_nodes = children.len();
+        }
+
+        for (begin, end) in children {
+            // extend the edge as long as all keys agree
+            let mut next_pos = pos + 1;
+            while next_pos < keys[begin].len()
+                && (begin + 1..end).all(|i| keys[i][next_pos] == keys[i - 1][next_pos])
+            {
+                next_pos += 1;
+            }
+
+            let label = keys[begin][pos];
+            let cache_id = (node_id ^ (node_id << 5) ^ label as usize) & cache_mask;
+            let weight = (end - begin) as f32;
+            if weight > cache[cache_id].weight {
+                cache[cache_id] = Cache {
+                    parent: node_id as u32,
+                    child: bases.len() as u32,
+                    weight,
+                };
+            }
+
+            if next_pos == pos + 1 {
+                bases.push(label);
+                link_flags.push(false);
+            } else {
+                bases.push(0);
+                link_flags.push(true);
+                labels.push(&keys[begin][pos..next_pos]);
+            }
+            queue.push_back((begin, end, next_pos));
+            louds.push(true);
+        }
+        louds.push(false);
+    }
+    louds.push(false);
+
+    let mut terminal_flags = BitVec::default();
+    for node_id in 0..bases.len() {
+        terminal_flags.push(terminals.binary_search(&node_id).is_ok());
+    }
+    terminal_flags.push(false);
+
+    // link nodes point into the tail: the low byte goes in `bases`, the rest in `extras`
+    let mut tail = Vec::new();
+    let mut extras = Vec::new();
+    let mut node_extras = vec![INVALID_EXTRA; bases.len()];
+    let mut links = labels.iter();
+    for (node_id, base) in bases.iter_mut().enumerate() {
+        if link_flags.get(node_id) {
+            let offset = tail.len() as u32;
+            *base = (offset % 256) as u8;
+            extras.push(offset / 256);
+            node_extras[node_id] = offset / 256;
+            tail.extend_from_slice(links.next().unwrap());
+            tail.push(0);
+        }
+    }
+
+    let mut cache_data = Vec::with_capacity(cache.len() * 12);
+    for entry in &cache {
+        let (parent, child, link) = if entry.child == 0 {
+            (u32::MAX, u32::MAX, entry.weight.to_bits())
+        } else {
+            let child = entry.child as usize;
+            (
+                entry.parent,
+                entry.child,
+                bases[child] as u32 | (node_extras[child] << 8),
+            )
+        };
+        cache_data.extend_from_slice(&parent.to_le_bytes());
+        cache_data.extend_from_slice(&child.to_le_bytes());
+        cache_data.extend_from_slice(&link.to_le_bytes());
+    }
+
+    let mut out = HEADER.to_vec();
+    louds.write(&mut out, true, true);
+    terminal_flags.write(&mut out, false, true);
+    link_flags.write(&mut out, false, false);
+    write_vec(&mut out, &bases);
+    write_flat_vec(&mut out, &extras);
+    write_vec(&mut out, &tail);
+    BitVec::default().write(&mut out, false, false); // tail end flags, only for binary tails
+    write_vec(&mut out, &cache_data);
+    out.extend_from_slice(&(num_l1_nodes as u32).to_le_bytes());
+    out.extend_from_slice(&(NUM_TRIES | NORMAL_CACHE | TEXT_TAIL | LABEL_ORDER).to_le_bytes());
+    Ok(out)
+}
+
+#[derive(Clone, Copy)]
+struct Cache {
+    parent: u32,
+    child: u32,
+    weight: f32,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self {
+            parent: 0,
+            child: 0,
+            weight: f32::MIN_POSITIVE,
+        }
+    }
+}
+
+#[derive(Default)]
+struct BitVec {
+    units: Vec<u64>,
+    len: usize,
+}
+
+impl BitVec {
+    fn push(&mut self, bit: bool) {
+        if self.len.is_multiple_of(64) {
+            self.units.push(0);
+        }
+        if bit {
+            *self.units.last_mut().unwrap() |= 1 << (self.len % 64);
+        }
+        self.len += 1;
+    }
+
+    fn get(&self, i: usize) -> bool {
+        self.units[i / 64] >> (i % 64) & 1 == 1
+    }
+
+    /// Writes the bits with a rank index and optionally select indexes, like
+    /// `marisa::grimoire::vector::BitVector`.
+    fn write(&self, out: &mut Vec<u8>, select0: bool, select1: bool) {
+        let num_blocks = self.len.div_ceil(512);
+        // per 512-bit block: absolute rank, then ranks at each 64-bit unit relative to that
+        let mut ranks = vec![[0u32; 8]; num_blocks + 1];
+        let mut select0s = Vec::new();
+        let mut select1s = Vec::new();
+        let (mut num_0s, mut num_1s) = (0, 0);
+        for (unit_id, &unit) in self.units.iter().enumerate() {
+            let bit_id = unit_id * 64;
+            let block = &mut ranks[bit_id / 512];
+            let sub = unit_id % 8;
+            block[sub] = if sub == 0 {
+                num_1s as u32
+            } else {
+                (num_1s - block[0] as usize) as u32
+            };
+
+            let unit_num_1s = unit.count_ones() as usize;
+            if select0 {
+                let unit_num_0s = (self.len - bit_id).min(64) - unit_num_1s;
+                let zero_bit_id = (512 - num_0s % 512) % 512;
+                if zero_bit_id < unit_num_0s {
+                    select0s.push((bit_id + select_bit(!unit, zero_bit_id)) as u32);
+                }
+                num_0s += unit_num_0s;
+            }
+            if select1 {
+                let one_bit_id = (512 - num_1s % 512) % 512;
+                if one_bit_id < unit_num_1s {
+                    select1s.push((bit_id + select_bit(unit, one_bit_id)) as u32);
+                }
+            }
+            num_1s += unit_num_1s;
+        }
+        if !self.len.is_multiple_of(512) {
+            // relative ranks past the last unit count everything
+            let block = &mut ranks[(self.len - 1) / 512];
+            for sub in ((self.len - 1) / 64 % 8 + 1)..8 {
+                block[sub] = (num_1s - block[0] as usize) as u32;
+            }
+        }
+        ranks[num_blocks][0] = num_1s as u32;
+        if select0 {
+            select0s.push(self.len as u32);
+        }
+        if select1 {
+            select1s.push(self.len as u32);
+        }
+
+        let units: Vec<u8> = self.units.iter().flat_map(|u| u.to_le_bytes()).collect();
+        write_vec(out, &units);
+        out.extend_from_slice(&(self.len as u32).to_le_bytes());
+        out.extend_from_slice(&(num_1s as u32).to_le_bytes());
+        let ranks: Vec<u8> = ranks
+            .iter()
+            .flat_map(|[abs, rel @ ..]| {
+                let lo =
+                    rel[0] & 0x7f | (rel[1] & 0xff) << 7 | (rel[2] & 0xff) << 15 | rel[3] << 23;
+                let hi = rel[4] & 0x1ff | (rel[5] & 0x1ff) << 9 | (rel[6] & 0x1ff) << 18;
+                [*abs, lo, hi].into_iter().flat_map(u32::to_le_bytes)
+            })
+            .collect();
+        write_vec(out, &ranks);
+        let select0s: Vec<u8> = select0s.iter().flat_map(|i| i.to_le_bytes()).collect();
+        write_vec(out, &select0s);
+        let select1s: Vec<u8> = select1s.iter().flat_map(|i| i.to_le_bytes()).collect();
+        write_vec(out, &select1s);
+    }
+}
+
+/// Position of the `i`th set bit.
+fn select_bit(mut unit: u64, i: usize) -> usize {
+    for _ in 0..i {
+        unit &= unit - 1;
+    }
+    unit.trailing_zeros() as usize
+}
+
+/// `marisa::grimoire::vector::Vector`: byte size, data, padding to 8 bytes.
+fn write_vec(out: &mut Vec<u8>, data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    out.extend_from_slice(data);
+    out.resize(out.len() + (8 - data.len() % 8) % 8, 0);
+}
+
+/// `marisa::grimoire::vector::FlatVector`: values packed with as few bits as possible.
+fn write_flat_vec(out: &mut Vec<u8>, values: &[u32]) {
+    let max = values.iter().copied().max().unwrap_or(0);
+    let value_size = 32 - max.leading_zeros() as usize;
+    let num_units = if values.is_empty() {
+        0
+    } else {
+        (value_size * values.len()).div_ceil(64).max(1)
+    };
+    let mut units = vec![0u64; num_units];
+    for (i, &value) in values.iter().enumerate() {
+        let pos = i * value_size;
+        let (unit_id, offset) = (pos / 64, pos % 64);
+        units[unit_id] |= (value as u64) << offset;
+        if offset + value_size > 64 {
+            units[unit_id + 1] |= (value as u64) >> (64 - offset);
+        }
+    }
+
+    let units: Vec<u8> = units.iter().flat_map(|u| u.to_le_bytes()).collect();
+    write_vec(out, &units);
+    out.extend_from_slice(&(value_size as u32).to_le_bytes());
+    let mask = if value_size == 0 {
+        0
+    } else {
+        u32::MAX >> (32 - value_size)
+    };
+    out.extend_from_slice(&mask.to_le_bytes());
+    out.extend_from_slice(&(values.len() as u64).to_le_bytes());
+}
@@ -0,0 +1,250 @@
+//! MDict dictionaries (version 2.0 `.mdx` and `.mdd`), for the many dictionary apps that read
+//! them.
+//!
+//! Both files are a header, a key section and a record section. The keys and records are split
+//! into zlib-compressed blocks, with an index of the first and last key of each key block.
+
+use crate::backend::{Backend, Dictionary};
+use crate::rendered::{rewrite_images, rewrite_links};
+use crate::xhtml::XhtmlEscaped;
+use anyhow::Context;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+const STYLESHEET: &str = include_str!("../ddk/Dictionary.css");
+
+/// Base name of the output files.
+const NAME: &str = "pokedex";
+
+/// Uncompressed size after which a block is closed.
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// One record per entry title, with `@@@LINK=` records for the other index keys. Links become
+/// `entry://` links to the target entry's title, and the `.mdd` holds the images and stylesheet.
+pub struct MDict;
+
+impl Backend for MDict {
+    fn name(&self) -> &'static str {
+        "mdict"
+    }
+
+    fn write(&self, dict: &Dictionary, out_dir: &Path) -> anyhow::Result<()> {
+        let entries = dict.rendered_entries()?;
+        let titles: HashMap<&str, &str> = entries
+            .iter()
+            .map(|entry| (entry.id.as_str(), entry.title.as_str()))
+            .collect();
+
+        let mut records = Vec::new();
+        let mut images = BTreeSet::new();
+        for entry in &entries {
+            let html = rewrite_links(&entry.html, |id, fragment| {
+                let title = titles.get(id).copied().unwrap_or(id);
+                match fragment {
+                    Some(fragment) => format!(
+                        "entry://{}#{}",
+                        XhtmlEscaped(title, true),
+                        XhtmlEscaped(fragment, true)
+                    ),
+                    None => format!("entry://{}", XhtmlEscaped(title, true)),
+                }
+            });
+            let html = rewrite_images(&html, |file| {
+                images.insert(file.to_string());
+                format!("images/{}", XhtmlEscaped(file, true))
+            });
+            records.push((
+                entry.title.clone(),
+                format!(r#"<link rel="stylesheet" type="text/css" href="{NAME}.css" />{html}"#),
+            ));
+            for word in entry.words().into_iter().skip(1) {
+                records.push((word.to_string(), format!("@@@LINK={}", entry.title)));
+            }
+        }
+
+        let mut files = vec![(format!("\\{NAME}.css"), STYLESHEET.as_bytes().to_vec())];
+        for file in images {
+            let data = fs::read(dict.image_dir.join(&file))
+                .with_context(|| format!("error reading image {file}"))?;
+            files.push((format!("\\images\\{file}"), data));
+        }
+
+        fs::create_dir_all(out_dir)?;
+        let description = "Pokémon, abilities, moves, items and types from Bulbapedia.";
+        fs::write(
+            out_dir.join(format!("{NAME}.mdx")),
+            write_mdx("Pokédex", description, records)?,
+        )
+        .context("error writing .mdx")?;
+        fs::write(out_dir.join(format!("{NAME}.mdd")), write_mdd(files)?)
+            .context("error writing .mdd")?;
+        Ok(())
+    }
+}
+
+/// Writes an `.mdx` file with UTF-8 HTML records. Keys may repeat.
+pub fn write_mdx(
+    title: &str,
+    description: &str,
+    records: Vec<(String, String)>,
+) -> anyhow::Result<Vec<u8>> {
+    let header = format!(
+        r#"<Dictionary GeneratedByEngineVersion="2.0" RequiredEngineVersion="2.0" Encrypted="No" Encoding="UTF-8" Format="Html" Stripkey="Yes" CreationDate="" Compact="Yes" Compat="Yes" KeyCaseSensitive="No" Description="{}" Title="{}" DataSourceFormat="106" StyleSheet="" Left2Right="Yes" RegisterBy=""/>"#,
+        XhtmlEscaped(description, true),
+        XhtmlEscaped(title, true)
+    );
+    let mut records: Vec<_> = records
+        .into_iter()
+        .map(|(key, mut text)| {
+            text.push('\0');
+            (key, text.into_bytes())
+        })
+        .collect();
+    records.sort_by(|a, b| mdx_key_cmp(&a.0, &b.0));
+    write_mdict(&header, &records, false)
+}
+
+/// Writes an `.mdd` file. Keys are paths like `\images\file.png`.
+pub fn write_mdd(mut files: Vec<(String, Vec<u8>)>) -> anyhow::Result<Vec<u8>> {
+    let header = r#"<Library_Data GeneratedByEngineVersion="2.0" RequiredEngineVersion="2.0" Encrypted="No" Encoding="UTF-16" Format="" CreationDate="" KeyCaseSensitive="No" Stripkey="No" Description="" Title="" RegisterBy=""/>"#;
+    files.sort_by_key(|(key, _)| key.to_lowercase());
+    write_mdict(header, &files, true)
+}
+
+/// The order MDict apps binary-search `.mdx` keys in: case-insensitive, ignoring punctuation
+/// and spaces (`Stripkey`).
+fn mdx_key_cmp(a: &str, b: &str) -> Ordering {
+    let strip = |s: &str| {
+        s.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect::<String>()
+    };
+    strip(a).cmp(&strip(b)).then_with(|| a.cmp(b))
+}
+
+/// Writes a whole file. `records` must be sorted, and keys are UTF-16 in `.mdd` files.
+fn write_mdict(
+    header: &str,
+    records: &[(String, Vec<u8>)],
+    utf16: bool,
+) -> anyhow::Result<Vec<u8>> {
+    let encode_key = |key: &str| -> Vec<u8> {
+        if utf16 {
+            key.encode_utf16().flat_map(u16::to_le_bytes).collect()
+        } else {
+            key.as_bytes().to_vec()
+        }
+    };
+    let terminator: &[u8] = if utf16 { &[0, 0] } else { &[0] };
+
+    let mut out = Vec::new();
+    let header: Vec<u8> = format!("{header}\r\n\0")
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect();
+    out.extend_from_slice(&(header.len() as u32).to_be_bytes());
+    out.extend_from_slice(&header);
+    out.extend_from_slice(&adler32(&header).to_le_bytes());
+
+    // records, and the key blocks pointing into them
+    let mut record_blocks = Vec::new();
+    let mut record_block_sizes = Vec::new();
+    let mut record_block = Vec::new();
+    let mut key_blocks = Vec::new();
+    let mut num_key_blocks = 0u64;
+    let mut key_block_info = Vec::new();
+    let mut key_block = Vec::new();
+    let mut key_block_start = 0;
+    let mut offset = 0u64;
+    for (i, (key, data)) in records.iter().enumerate() {
+        key_block.extend_from_slice(&offset.to_be_bytes());
+        key_block.extend_from_slice(&encode_key(key));
+        key_block.extend_from_slice(terminator);
+        if key_block.len() >= BLOCK_SIZE || i == records.len() - 1 {
+            // number of entries, first and last key (length in code units), sizes
+            let compressed = compress_block(&key_block)?;
+            key_block_info.extend_from_slice(&((i + 1 - key_block_start) as u64).to_be_bytes());
+            for key in [&records[key_block_start].0, key] {
+                let encoded = encode_key(key);
+                let len = u16::try_from(encoded.len() / terminator.len())?;
+                key_block_info.extend_from_slice(&len.to_be_bytes());
+                key_block_info.extend_from_slice(&encoded);
+                key_block_info.extend_from_slice(terminator);
+            }
+            key_block_info.extend_from_slice(&(compressed.len() as u64).to_be_bytes());
+            key_block_info.extend_from_slice(&(key_block.len() as u64).to_be_bytes());
+            key_blocks.extend_from_slice(&compressed);
+            num_key_blocks += 1;
+            key_block.clear();
+            key_block_start = i + 1;
+        }
+
+        record_block.extend_from_slice(data);
+        offset += data.len() as u64;
+        if record_block.len() >= BLOCK_SIZE || i == records.len() - 1 {
+            let compressed = compress_block(&record_block)?;
+            record_block_sizes.push((compressed.len() as u64, record_block.len() as u64));
+            record_blocks.extend_from_slice(&compressed);
+            record_block.clear();
+        }
+    }
+    let compressed_info = compress_block(&key_block_info)?;
+
+    let mut key_header = Vec::new();
+    for n in [
+        num_key_blocks,
+        records.len() as u64,
+        key_block_info.len() as u64,
+        compressed_info.len() as u64,
+        key_blocks.len() as u64,
+    ] {
+        key_header.extend_from_slice(&n.to_be_bytes());
+    }
+    out.extend_from_slice(&key_header);
+    out.extend_from_slice(&adler32(&key_header).to_be_bytes());
+    out.extend_from_slice(&compressed_info);
+    out.extend_from_slice(&key_blocks);
+
+    for n in [
+        record_block_sizes.len() as u64,
+        records.len() as u64,
+        record_block_sizes.len() as u64 * 16,
+        record_blocks.len() as u64,
+    ] {
+        out.extend_from_slice(&n.to_be_bytes());
+    }
+    for (compressed, decompressed) in record_block_sizes {
+        out.extend_from_slice(&compressed.to_be_bytes());
+        out.extend_from_slice(&decompressed.to_be_bytes());
+    }
+    out.extend_from_slice(&record_blocks);
+    Ok(out)
+}
+
+/// A zlib-compressed block: compression type 2, Adler-32 of the data, zlib stream.
+fn compress_block(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut out = vec![2, 0, 0, 0];
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    let mut zlib = ZlibEncoder::new(out, Compression::best());
+    zlib.write_all(data)?;
+    Ok(zlib.finish()?)
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    b << 16 | a
+}
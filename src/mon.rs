@@ -1,29 +1,48 @@
+use crate::evolution::{read_evolutions, Evolution};
 use crate::fetcher::Fetcher;
+use crate::forms::{labels_match, read_forms, MonForm};
 use crate::image::ImageCache;
 use crate::index::DexId;
-use crate::index::Index;
+use crate::index::{Index, RegionalDexId};
+use crate::page::{
+    first_el_child, get_attr, get_highest_quality_src, inner_xhtml, is_element, outer_xhtml,
+    parse_simple_style_attr, Page, Reference,
+};
 use crate::Config;
 use anyhow::{anyhow, bail, ensure, Context};
 use html5ever::tendril::TendrilSink;
 use kuchikiki::NodeRef;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use url::Url;
 
 const FIRST_EXTRA_INFO_BOX: &str = "Gender ratio";
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MonEntry {
     /// Page URL
     pub url: String,
 
     // general information
     pub dex_id: DexId,
+    /// Numbers in regional Pokédexes, e.g. Galar #025
+    pub regional_ids: Vec<RegionalDexId>,
     pub name: String,
+    /// Categories as plain text, e.g. “Seed Pokémon”
+    pub categories_text: Vec<String>,
     pub categories_html: Vec<String>,
     pub name_jp_text: String,
     pub name_jp_html: String,
+    /// Trademarked romanization, e.g. “Fushigidane”
+    pub name_jp_translit_text: String,
     pub name_jp_translit_html: String,
     pub images: Vec<MonImage>,
+    /// Type names of the base form, e.g. “Grass” and “Poison”
+    pub types: Vec<String>,
+    /// Forms with their own types, abilities and stats; at least one
+    pub forms: Vec<MonForm>,
+    /// Every step of the evolution chain the Pokémon is part of, not just its own
+    pub evolutions: Vec<Evolution>,
 
     /// CSS for info boxes
     pub info_box_style: BTreeMap<String, String>,
@@ -36,6 +55,8 @@ pub struct MonEntry {
     pub summary_html: String,
     /// Body HTML contents
     pub body_html: String,
+    /// Footnotes cited in the info boxes, summary or body, in citation order
+    pub references: Vec<Reference>,
 
     /// Previous entry ID and image
     pub prev_entry: Option<(DexId, String)>,
@@ -43,7 +64,26 @@ pub struct MonEntry {
     pub next_entry: Option<(DexId, String)>,
 }
 
-#[derive(Debug)]
+impl MonEntry {
+    /// Whether the form gets an entry of its own. The first form is the base entry.
+    pub fn has_form_entry(&self, i: usize) -> bool {
+        i > 0 && i < self.forms.len()
+    }
+
+    /// The `d:entry` ID of a form's entry.
+    pub fn form_entry_id(&self, i: usize) -> String {
+        format!("pokemon-{}-form-{i}", self.dex_id.0)
+    }
+
+    /// Finds a form by a label as written in some list, e.g. “Alolan Form”.
+    pub fn find_form(&self, label: &str) -> Option<usize> {
+        self.forms
+            .iter()
+            .position(|form| labels_match(&self.name, &form.name, label))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MonImage {
     pub href: String,
     pub alt: String,
@@ -63,7 +103,8 @@ pub fn read_mon(
 ) -> anyhow::Result<MonEntry> {
     let html = String::from_utf8(fetcher.get(url, true)?)?;
     let doc = kuchikiki::parse_html().one(html);
-    let base_url = Url::parse(url).unwrap();
+    let mut page = Page::new(fetcher, index, image_cache, config, url, &doc);
+    let base_url = page.base_url.clone();
 
     let Ok(header_table) = doc.select_first(".mw-parser-output table > tbody > tr:nth-child(2)")
     else {
@@ -124,6 +165,8 @@ pub fn read_mon(
         .filter(|(k, _)| ["background", "border", "padding", "text-align"].contains(&&**k))
         .collect();
 
+    let regional_ids = read_regional_ids(info_box.as_node());
+
     let mut header_box = None;
     let mut is_extra = false;
     let mut top_info_nodes = Vec::new();
@@ -149,7 +192,7 @@ pub fn read_mon(
 
     let header_box = header_box.ok_or(anyhow!("no header box"))?;
 
-    let (name, categories_html, name_jp_text, name_jp_html, name_jp_translit_html, dex_id, images) = {
+    let (name, categories, name_jp_text, name_jp_html, name_jp_translit, dex_id, images) = {
         let td = first_el_child(&header_box, "td").ok_or(anyhow!("no header box > td"))?;
         let table = first_el_child(&td, "table").ok_or(anyhow!("no header box > td > table"))?;
         let tbody =
@@ -164,7 +207,7 @@ pub fn read_mon(
         );
 
         let name_box = &first_tr_items[0];
-        let (name, categories_html, name_jp_text, name_jp_html, name_jp_translit_html) = {
+        let (name, categories, name_jp_text, name_jp_html, name_jp_translit) = {
             let table = first_el_child(name_box, "table").ok_or(anyhow!("no name box > table"))?;
             let tbody =
                 first_el_child(&table, "tbody").ok_or(anyhow!("no name box > table > tbody"))?;
@@ -194,23 +237,25 @@ pub fn read_mon(
             );
             let mut categories = Vec::new();
             for node in category_items[0].children() {
-                if node
-                    .as_element()
-                    .map_or(false, |el| &*el.name.local == "br")
-                {
-                    categories.push(String::new());
+                if node.as_element().is_some_and(|el| &*el.name.local == "br") {
+                    categories.push((String::new(), String::new()));
                     continue;
                 }
+                let text = node.text_contents();
                 let html = outer_xhtml(&node);
-                if let Some(last) = categories.last_mut() {
-                    last.push_str(&html);
+                if let Some((last_text, last_html)) = categories.last_mut() {
+                    last_text.push_str(&text);
+                    last_html.push_str(&html);
                 } else {
-                    categories.push(html);
+                    categories.push((text, html));
                 }
             }
-            if categories.last().map_or(false, |s| s.is_empty()) {
+            if categories.last().is_some_and(|(_, html)| html.is_empty()) {
                 categories.pop();
             }
+            for (text, _) in &mut categories {
+                *text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            }
 
             let jp_box = &tds[1];
             let name_jp = jp_box
@@ -222,7 +267,10 @@ pub fn read_mon(
             let name_jp_translit = jp_box
                 .select_first("i")
                 .map_err(|()| anyhow!("could not find jp translit"))?;
-            let name_jp_translit = outer_xhtml(name_jp_translit.as_node());
+            let name_jp_translit = (
+                name_jp_translit.text_contents().trim().to_string(),
+                outer_xhtml(name_jp_translit.as_node()),
+            );
 
             (
                 name,
@@ -251,7 +299,7 @@ pub fn read_mon(
                     .map(|s| parse_simple_style_attr(&s))
                     .unwrap_or_default();
 
-                if tr_style.get("display").map_or(false, |d| d == "none") {
+                if tr_style.get("display").is_some_and(|d| d == "none") {
                     continue;
                 }
 
@@ -262,7 +310,7 @@ pub fn read_mon(
                         let td_style: BTreeMap<_, _> = get_attr(td, "style")
                             .map(|s| parse_simple_style_attr(&s))
                             .unwrap_or_default();
-                        td_style.get("display").map_or(true, |d| d != "none")
+                        td_style.get("display").is_none_or(|d| d != "none")
                     })
                     .count();
 
@@ -270,7 +318,7 @@ pub fn read_mon(
                     let td_style: BTreeMap<_, _> = get_attr(&td, "style")
                         .map(|s| parse_simple_style_attr(&s))
                         .unwrap_or_default();
-                    if td_style.get("display").map_or(false, |d| d == "none") {
+                    if td_style.get("display").is_some_and(|d| d == "none") {
                         continue;
                     }
 
@@ -320,17 +368,51 @@ pub fn read_mon(
 
         (
             name,
-            categories_html,
+            categories,
             name_jp_text,
             name_jp_html,
-            name_jp_translit_html,
+            name_jp_translit,
             dex_id,
             images,
         )
     };
+    let (name_jp_translit_text, name_jp_translit_html) = name_jp_translit;
+    let (categories_text, categories_html) = categories.into_iter().unzip();
+
+    let find_row = |labels: &[&str]| {
+        top_info_nodes
+            .iter()
+            .find(|tr| {
+                let text = tr.text_contents();
+                labels.iter().any(|l| text.trim().starts_with(l))
+            })
+            .cloned()
+    };
+    let type_row = find_row(&["Type"]);
+    let ability_row = find_row(&["Abilities", "Ability"]);
+    let forms = read_forms(
+        &page,
+        &name,
+        &images,
+        type_row.as_ref(),
+        ability_row.as_ref(),
+        &doc,
+    )
+    .context("error reading forms")?;
+
+    let types = forms
+        .first()
+        .map(|form| form.types.clone())
+        .unwrap_or_default();
+
+    if forms.iter().all(|form| !form.types.is_empty()) {
+        // these are shown per form instead
+        top_info_nodes
+            .retain(|tr| Some(tr) != type_row.as_ref() && Some(tr) != ability_row.as_ref());
+    }
 
     for node in top_info_nodes.iter().chain(extra_info_nodes.iter()) {
-        fix_links(fetcher, index, image_cache, config, &base_url, node)
+        page.fix_links(node)
             .context("error fixing info box links")?;
     }
 
@@ -346,6 +428,9 @@ pub fn read_mon(
     let mw_parser_output = doc
         .select_first(".mw-parser-output")
         .map_err(|()| anyhow!("no mw-parser-output"))?;
+    // before fixing the body's links, which would make them unreadable
+    let evolutions =
+        read_evolutions(&page, mw_parser_output.as_node()).context("error reading evolutions")?;
     let mut summary_nodes = Vec::new();
     let mut body_nodes = Vec::new();
 
@@ -355,7 +440,7 @@ pub fn read_mon(
     for node in mw_parser_output.as_node().children() {
         let tag_name = node.as_element().map(|el| &*el.name.local);
         let id = get_attr(&node, "id");
-        if id.map_or(false, |id| id == "toc") {
+        if id.is_some_and(|id| id == "toc") {
             is_in_body = true;
             continue;
         }
@@ -372,7 +457,7 @@ pub fn read_mon(
         if h2s_seen > config.max_body_sections {
             break;
         }
-        fix_links(fetcher, index, image_cache, config, &base_url, &node)
+        page.fix_links(&node)
             .context("error fixing summary links")?;
         if is_in_body {
             body_nodes.push(node);
@@ -388,178 +473,84 @@ pub fn read_mon(
         .into_iter()
         .fold(String::new(), |s, node| s + &outer_xhtml(&node));
 
+    let references = page.references()?;
+
     Ok(MonEntry {
         url: url.to_string(),
         info_box_style,
         dex_id,
+        regional_ids,
         name,
+        categories_text,
         categories_html,
         name_jp_text,
         name_jp_html,
+        name_jp_translit_text,
         name_jp_translit_html,
         images,
+        types,
+        forms,
+        evolutions,
         top_info_boxes_html,
         extra_info_boxes_html,
         summary_html,
         body_html,
+        references,
         prev_entry,
         next_entry,
     })
 }
 
-fn fix_links(
-    fetcher: &Fetcher,
-    index: &Index,
-    image_cache: &ImageCache,
-    config: &Config,
-    base_url: &Url,
-    node: &NodeRef,
-) -> anyhow::Result<()> {
-    // remove references. we don't have a references section
-    if let Ok(refs) = node.select("sup.reference") {
-        for reference in refs.collect::<Vec<_>>() {
-            reference.as_node().detach();
-        }
-    }
-
-    if let Ok(links) = node.select("a") {
-        for link in links {
-            let mut attrs = link.as_node().as_element().unwrap().attributes.borrow_mut();
-            if let Some(href) = attrs.get("href") {
-                let url = base_url
-                    .join(href)
-                    .with_context(|| format!("error fixing <a href=\"{href}\""))?;
-                let mut url_str = url.to_string();
-
-                if href.starts_with("/wiki/") && href.ends_with("_(Pok%C3%A9mon)") {
-                    if let Some((id, _)) = index
-                        .pokemon_pages
-                        .iter()
-                        .find(|(_, page)| **page == *url_str)
-                    {
-                        url_str = format!("x-dictionary:r:pokemon-{}", id.0);
-                        attrs.remove("title");
-                    }
-                }
-
-                attrs.insert("href", url_str);
-            }
-        }
-    }
-
-    if let Ok(images) = node.select("img") {
-        for image in images {
-            let src = get_highest_quality_src(image.as_node(), base_url, config.hq_body_images)
-                .ok_or(anyhow!("<img> without src"))?;
-            let image_id = image_cache
-                .get(fetcher, &src)
-                .with_context(|| format!("error fixing <img src=\"{src}\">"))?;
-            let mut attrs = image
-                .as_node()
-                .as_element()
-                .unwrap()
-                .attributes
-                .borrow_mut();
-            attrs.remove("srcset");
-            attrs.insert("src", format!("images/{}", urlencoding::encode(&image_id)));
-
-            // keep aspect ratio
-            if attrs.contains("width") {
-                attrs.remove("height");
-            }
+/// Reads regional Pokédex numbers, which the info box lists next to links to the regional
+/// Pokédex lists (“List of Pokémon by Johto Pokédex number”).
+fn read_regional_ids(info_box: &NodeRef) -> Vec<RegionalDexId> {
+    let mut ids = Vec::new();
+    let Ok(links) = info_box.select("a[title$=' Pokédex number']") else {
+        return ids;
+    };
+    for link in links {
+        let title = get_attr(link.as_node(), "title").unwrap_or_default();
+        let Some(dex) = title
+            .strip_prefix("List of Pokémon by ")
+            .and_then(|title| title.strip_suffix(" Pokédex number"))
+        else {
+            continue;
+        };
+        if dex == "National" {
+            continue;
         }
-    }
-
-    Ok(())
-}
 
-fn get_attr(node: &NodeRef, attr: &str) -> Option<String> {
-    let el = node.as_element()?;
-    el.attributes.borrow().get(attr).map(|s| s.to_string())
-}
-
-fn first_el_child(node: &NodeRef, tag: &str) -> Option<NodeRef> {
-    node.children()
-        .find(|node| node.as_element().map_or(false, |el| &*el.name.local == tag))
-}
-
-fn is_element(node: &NodeRef) -> bool {
-    node.as_element().is_some()
-}
+        // the number follows the link in the same row
+        let Some(row) = link
+            .as_node()
+            .ancestors()
+            .find(|node| node.as_element().is_some_and(|el| &*el.name.local == "tr"))
+        else {
+            continue;
+        };
+        let row_text = row.text_contents();
+        let link_text = link.text_contents();
+        let after = row_text
+            .find(&link_text)
+            .map_or(&*row_text, |i| &row_text[i + link_text.len()..]);
+        let Some(start) = after.find(|c: char| c.is_ascii_digit()) else {
+            continue;
+        };
+        let digits: String = after[start..]
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+        let Ok(number) = digits.parse() else {
+            continue;
+        };
 
-fn parse_simple_style_attr(style: &str) -> BTreeMap<String, String> {
-    let mut values = BTreeMap::new();
-    for entry in style.split(';') {
-        let entry = entry.trim();
-        if let Some((k, v)) = entry.split_once(':') {
-            values.insert(k.trim().to_string(), v.trim().to_string());
+        let id = RegionalDexId {
+            dex: dex.to_string(),
+            number,
+        };
+        if !ids.contains(&id) {
+            ids.push(id);
         }
     }
-    values
-}
-
-fn outer_xhtml(node: &NodeRef) -> String {
-    let mut w = Vec::new();
-    crate::xhtml::serialize(&mut w, node).unwrap();
-    String::from_utf8(w).unwrap()
-}
-
-fn inner_xhtml(node: &NodeRef) -> String {
-    let mut w = Vec::new();
-    for child in node.children() {
-        crate::xhtml::serialize(&mut w, &child).unwrap();
-    }
-    String::from_utf8(w).unwrap()
-}
-
-fn get_highest_quality_src(img: &NodeRef, base_url: &Url, find_thumb_origin: bool) -> Option<Url> {
-    let mut src_set: BTreeMap<_, _> = get_attr(img, "srcset")
-        .unwrap_or_default()
-        .split(',')
-        .filter_map(|entry| {
-            let (src, size) = entry.rsplit_once(' ')?;
-            Some((size.trim().to_string(), src.to_string()))
-        })
-        .collect();
-    if !src_set.contains_key("1x") {
-        src_set.insert("1x".to_string(), get_attr(img, "src").unwrap_or_default());
-    }
-
-    let src = src_set
-        .get("2x")
-        .or(src_set.get("1.5x"))
-        .or(src_set.get("1x"))?;
-
-    let src = base_url.join(src).ok()?;
-
-    if find_thumb_origin {
-        get_image_thumbnail_origin(&src).or(Some(src))
-    } else {
-        Some(src)
-    }
-}
-
-fn get_image_thumbnail_origin(src: &Url) -> Option<Url> {
-    if src.domain() != Some("archives.bulbagarden.net") || !src.path().contains("/thumb/") {
-        return None;
-    }
-    let mut segments = src.path_segments().unwrap();
-    if segments.next()? != "media" {
-        return None;
-    }
-    if segments.next()? != "upload" {
-        return None;
-    }
-    if segments.next()? != "thumb" {
-        return None;
-    }
-
-    let a = segments.next()?;
-    let b = segments.next()?;
-    let file_name = segments.next()?;
-
-    let new_path = format!("/media/upload/{a}/{b}/{file_name}");
-    let mut url = src.clone();
-    url.set_path(&new_path);
-    Some(url)
+    ids
 }
@@ -0,0 +1,163 @@
+use crate::fetcher::Fetcher;
+use crate::image::ImageCache;
+use crate::index::{DexId, Index, MoveId};
+use crate::page::{is_tag, outer_xhtml, read_sections, Page, Reference, Section, SectionHtml};
+use crate::Config;
+use anyhow::{anyhow, Context};
+use html5ever::tendril::TendrilSink;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MoveEntry {
+    /// Page URL
+    pub url: String,
+
+    pub id: MoveId,
+    pub name: String,
+    pub name_jp_text: Option<String>,
+    pub name_jp_html: Option<String>,
+    pub type_name: String,
+    pub category: String,
+    pub power: String,
+    pub accuracy: String,
+    pub pp: String,
+
+    /// Summary HTML contents
+    pub summary_html: String,
+    /// Effect descriptions, usually one per generation
+    pub effects: Vec<SectionHtml>,
+    /// Pokémon that learn this move, in page order
+    pub learners: Vec<MoveLearner>,
+    /// Footnotes cited in the summary or effects, in citation order
+    pub references: Vec<Reference>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MoveLearner {
+    pub dex_id: DexId,
+    /// Form name, if only some forms learn this move
+    pub form: Option<String>,
+    /// How the move is learned, e.g. “leveling up” or “TM”
+    pub method: String,
+}
+
+pub fn read_move(
+    fetcher: &Fetcher,
+    index: &Index,
+    image_cache: &ImageCache,
+    config: &Config,
+    id: MoveId,
+) -> anyhow::Result<MoveEntry> {
+    let move_page = index
+        .move_pages
+        .get(&id)
+        .ok_or(anyhow!("move {id} is not in the index"))?;
+    let url = &move_page.url;
+
+    let html = String::from_utf8(fetcher.get(url, true)?)?;
+    let doc = kuchikiki::parse_html().one(html);
+    let mut page = Page::new(fetcher, index, image_cache, config, url, &doc);
+
+    let title = doc
+        .select_first("#firstHeading")
+        .map_err(|()| anyhow!("could not find page title"))?
+        .text_contents();
+    let name = title.trim().trim_end_matches("(move)").trim().to_string();
+
+    let name_jp = doc
+        .select_first(".mw-parser-output table.roundy [lang='ja']")
+        .ok();
+    let name_jp_text = name_jp
+        .as_ref()
+        .map(|node| node.text_contents().trim().to_string());
+    let name_jp_html = name_jp.as_ref().map(|node| outer_xhtml(node.as_node()));
+
+    let mw_parser_output = doc
+        .select_first(".mw-parser-output")
+        .map_err(|()| anyhow!("no mw-parser-output"))?;
+
+    let (intro, sections) = read_sections(mw_parser_output.as_node());
+
+    let mut summary_html = String::new();
+    for node in intro {
+        if is_tag(&node, "p") {
+            page.fix_links(&node)
+                .context("error fixing summary links")?;
+            summary_html += &outer_xhtml(&node);
+        }
+    }
+
+    let mut effects = Vec::new();
+    let mut learners = Vec::new();
+    for section in &sections {
+        if section.title == "Effect" {
+            effects = page.section_html(section)?;
+        } else if section.title == "Learnset" {
+            read_learnset(&page, section, &mut learners).context("error reading learnset")?;
+        }
+    }
+
+    let references = page.references()?;
+
+    Ok(MoveEntry {
+        url: url.to_string(),
+        id,
+        name,
+        name_jp_text,
+        name_jp_html,
+        type_name: move_page.type_name.clone(),
+        category: move_page.category.clone(),
+        power: move_page.power.clone(),
+        accuracy: move_page.accuracy.clone(),
+        pp: move_page.pp.clone(),
+        summary_html,
+        effects,
+        learners,
+        references,
+    })
+}
+
+/// Reads the learnset tables, which come in subsections like “By leveling up” and
+/// have one row per Pokémon (or form).
+fn read_learnset(
+    page: &Page,
+    section: &Section,
+    learners: &mut Vec<MoveLearner>,
+) -> anyhow::Result<()> {
+    for (title, nodes) in &section.subsections {
+        let method = title.strip_prefix("By ").unwrap_or(title);
+
+        for node in nodes {
+            let Ok(rows) = node.select("tr") else {
+                continue;
+            };
+            for tr in rows {
+                let tr = tr.as_node();
+                if tr.select_first("table").is_ok() {
+                    // row of an outer layout table
+                    continue;
+                }
+
+                let Ok(link) = tr.select_first("a[href$='_(Pok%C3%A9mon)']") else {
+                    continue;
+                };
+                let Some((dex_id, form)) = page.pokemon_link(link.as_node())? else {
+                    continue;
+                };
+
+                let is_duplicate = learners.iter().any(|learner| {
+                    learner.dex_id == dex_id && learner.form == form && learner.method == method
+                });
+                if !is_duplicate {
+                    learners.push(MoveLearner {
+                        dex_id,
+                        form,
+                        method: method.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,138 @@
+//! Alternate spellings of names, for index keys that are easier to type.
+
+/// Returns spellings of the name that people might type instead, e.g. “Farfetchd” for
+/// “Farfetch’d”, “Nidoran F” for “Nidoran♀” or “Porygon Z” for “Porygon-Z”.
+/// May contain duplicates and the name itself.
+pub fn name_variants(name: &str) -> Vec<String> {
+    let mut variants = vec![name.to_string(), name.replace(['’', 'ʼ'], "'")];
+    expand(&mut variants, |s| vec![ascii_fold(s)]);
+    expand(&mut variants, spell_out_gender);
+    expand(&mut variants, |s| vec![strip_punctuation(s)]);
+    expand(&mut variants, |s| {
+        if s != strip_punctuation(s) {
+            return Vec::new();
+        }
+        vec![
+            s.replace('-', " "),
+            s.replace(' ', "-"),
+            s.replace(['-', ' '], ""),
+        ]
+    });
+    variants.retain(|variant| !variant.trim().is_empty());
+    variants
+}
+
+/// Adds the results of applying `f` to every variant so far.
+fn expand(variants: &mut Vec<String>, f: impl Fn(&str) -> Vec<String>) {
+    let mut new = Vec::new();
+    for variant in variants.iter() {
+        for result in f(variant) {
+            if !variants.contains(&result) && !new.contains(&result) {
+                new.push(result);
+            }
+        }
+    }
+    variants.extend(new);
+}
+
+/// Replaces accented Latin letters with their base letters, e.g. “Flabebe” for “Flabébé”.
+fn ascii_fold(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+            'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' => 'A',
+            'ç' => 'c',
+            'Ç' => 'C',
+            'è' | 'é' | 'ê' | 'ë' => 'e',
+            'È' | 'É' | 'Ê' | 'Ë' => 'E',
+            'ì' | 'í' | 'î' | 'ï' => 'i',
+            'Ì' | 'Í' | 'Î' | 'Ï' => 'I',
+            'ñ' => 'n',
+            'Ñ' => 'N',
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' => 'o',
+            'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' => 'O',
+            'ù' | 'ú' | 'û' | 'ü' => 'u',
+            'Ù' | 'Ú' | 'Û' | 'Ü' => 'U',
+            'ý' | 'ÿ' => 'y',
+            'Ý' => 'Y',
+            c => c,
+        })
+        .collect()
+}
+
+/// Spells out ♀ and ♂, e.g. “NidoranF”, “Nidoran F” and “Nidoran female” for “Nidoran♀”.
+fn spell_out_gender(s: &str) -> Vec<String> {
+    if !s.contains(['♀', '♂']) {
+        return Vec::new();
+    }
+    let spell = |female: &str, male: &str| s.replace('♀', female).replace('♂', male);
+    vec![
+        spell("F", "M"),
+        spell(" F", " M"),
+        spell(" female", " male"),
+    ]
+}
+
+/// Removes punctuation other than hyphens and spaces, e.g. “Mr Mime” for “Mr. Mime”.
+fn strip_punctuation(s: &str) -> String {
+    let stripped: String = s
+        .chars()
+        .map(|c| if c == ':' { ' ' } else { c })
+        .filter(|c| c.is_alphanumeric() || matches!(c, '-' | ' ' | '♀' | '♂'))
+        .collect();
+    stripped.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn variants(name: &str) -> BTreeSet<String> {
+        name_variants(name).into_iter().collect()
+    }
+
+    fn set(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn gender_symbols() {
+        assert_eq!(
+            variants("Nidoran♀"),
+            set(&[
+                "Nidoran♀",
+                "NidoranF",
+                "Nidoran F",
+                "Nidoran-F",
+                "Nidoran female",
+                "Nidoran-female",
+                "Nidoranfemale",
+            ])
+        );
+        assert!(variants("Nidoran♂").contains("Nidoran male"));
+    }
+
+    #[test]
+    fn punctuation() {
+        assert_eq!(
+            variants("Mr. Mime"),
+            set(&["Mr. Mime", "Mr Mime", "Mr-Mime", "MrMime"])
+        );
+        assert_eq!(
+            variants("Type: Null"),
+            set(&["Type: Null", "Type Null", "Type-Null", "TypeNull"])
+        );
+        assert_eq!(
+            variants("Farfetch’d"),
+            set(&["Farfetch’d", "Farfetch'd", "Farfetchd"])
+        );
+        assert!(variants("Porygon-Z").is_superset(&set(&["Porygon Z", "PorygonZ"])));
+    }
+
+    #[test]
+    fn accents() {
+        assert_eq!(variants("Flabébé"), set(&["Flabébé", "Flabebe"]));
+        assert_eq!(variants("Pikachu"), set(&["Pikachu"]));
+    }
+}
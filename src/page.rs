@@ -0,0 +1,492 @@
+use crate::fetcher::Fetcher;
+use crate::image::ImageCache;
+use crate::index::{DexId, Index};
+use crate::links::EntryRef;
+use crate::Config;
+use anyhow::{anyhow, Context};
+use kuchikiki::NodeRef;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use url::Url;
+
+/// State shared by everything that processes nodes from a single wiki page.
+pub struct Page<'a> {
+    pub fetcher: &'a Fetcher,
+    pub index: &'a Index,
+    pub image_cache: &'a ImageCache,
+    pub config: &'a Config,
+    pub base_url: Url,
+    refs: References,
+}
+
+/// Rendered contents of a (sub)section.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SectionHtml {
+    /// Section title, e.g. “Generation III”; empty for text before the first subsection
+    pub title: String,
+    pub html: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Reference {
+    /// Anchor ID of the footnote
+    pub id: String,
+    /// Anchor ID of the first citation
+    pub backlink: String,
+    pub html: String,
+}
+
+/// Keeps track of footnotes cited in the parts of the page we keep.
+struct References {
+    /// Reference list items on the page, by anchor ID
+    notes: BTreeMap<String, NodeRef>,
+    /// Note IDs and the ID of their first citation, in citation order
+    cited: Vec<(String, String)>,
+}
+
+impl References {
+    fn new(doc: &NodeRef) -> Self {
+        let mut notes = BTreeMap::new();
+        if let Ok(items) = doc.select("ol.references > li") {
+            for item in items {
+                if let Some(id) = get_attr(item.as_node(), "id") {
+                    notes.insert(id, item.as_node().clone());
+                }
+            }
+        }
+        Self {
+            notes,
+            cited: Vec::new(),
+        }
+    }
+
+    /// Returns the footnote number for a citation of the given note, if the note exists.
+    fn cite(&mut self, note_id: &str, ref_id: &str) -> Option<usize> {
+        if !self.notes.contains_key(note_id) {
+            return None;
+        }
+        if let Some(i) = self.cited.iter().position(|(id, _)| id == note_id) {
+            return Some(i + 1);
+        }
+        self.cited.push((note_id.to_string(), ref_id.to_string()));
+        Some(self.cited.len())
+    }
+}
+
+impl<'a> Page<'a> {
+    pub fn new(
+        fetcher: &'a Fetcher,
+        index: &'a Index,
+        image_cache: &'a ImageCache,
+        config: &'a Config,
+        url: &str,
+        doc: &NodeRef,
+    ) -> Self {
+        Self {
+            fetcher,
+            index,
+            image_cache,
+            config,
+            base_url: Url::parse(url).unwrap(),
+            refs: References::new(doc),
+        }
+    }
+
+    /// Rewrites links, images and citations in the node so they work inside the dictionary.
+    pub fn fix_links(&mut self, node: &NodeRef) -> anyhow::Result<()> {
+        // point citations at our own references section, renumbered to match it
+        if let Ok(citations) = node.select("sup.reference") {
+            for citation in citations.collect::<Vec<_>>() {
+                let citation = citation.as_node();
+                let link = citation.select_first("a").ok();
+                let note_id = link
+                    .as_ref()
+                    .and_then(|link| get_attr(link.as_node(), "href"))
+                    .and_then(|href| href.strip_prefix('#').map(|s| s.to_string()));
+                let ref_id = get_attr(citation, "id").filter(|id| !id.is_empty());
+
+                // without an ID, the footnote couldn't link back to it
+                let Some(((link, note_id), ref_id)) = link.zip(note_id).zip(ref_id) else {
+                    citation.detach();
+                    continue;
+                };
+                let Some(number) = self.refs.cite(&note_id, &ref_id) else {
+                    // cites something that isn't on the page
+                    citation.detach();
+                    continue;
+                };
+
+                let link = link.as_node();
+                for child in link.children().collect::<Vec<_>>() {
+                    child.detach();
+                }
+                link.append(NodeRef::new_text(format!("[{number}]")));
+            }
+        }
+
+        let mut excluded_links = Vec::new();
+        if let Ok(links) = node.select("a") {
+            for link in links {
+                let mut attrs = link.as_node().as_element().unwrap().attributes.borrow_mut();
+                if let Some(href) = attrs.get("href") {
+                    if href.starts_with('#') {
+                        // in-page anchor, e.g. a citation
+                        continue;
+                    }
+
+                    let url = self
+                        .base_url
+                        .join(href)
+                        .with_context(|| format!("error fixing <a href=\"{href}\""))?;
+                    let mut url_str = url.to_string();
+
+                    let is_redirect = attrs
+                        .get("class")
+                        .is_some_and(|class| class.split(' ').any(|c| c == "mw-redirect"));
+                    let entry = self.index.links.resolve(&url, is_redirect);
+                    if let Some(EntryRef::Pokemon(id)) = entry {
+                        if !self.index.has_pokemon(id) {
+                            // not in this build, so there's nothing to link to
+                            excluded_links.push(link.as_node().clone());
+                            continue;
+                        }
+                    }
+                    if let Some(entry) = entry {
+                        url_str = format!("x-dictionary:r:{}", entry.entry_id());
+                        // fragments are checked against the generated entry later on
+                        if let Some(fragment) = url.fragment() {
+                            url_str.push('#');
+                            url_str.push_str(fragment);
+                        }
+                        attrs.remove("title");
+                    }
+
+                    attrs.insert("href", url_str);
+                }
+            }
+        }
+        for link in excluded_links {
+            for child in link.children().collect::<Vec<_>>() {
+                link.insert_before(child);
+            }
+            link.detach();
+        }
+
+        if let Ok(images) = node.select("img") {
+            for image in images {
+                let src = get_highest_quality_src(
+                    image.as_node(),
+                    &self.base_url,
+                    self.config.hq_body_images,
+                )
+                .ok_or(anyhow!("<img> without src"))?;
+                let image_id = self
+                    .image_cache
+                    .get(self.fetcher, &src)
+                    .with_context(|| format!("error fixing <img src=\"{src}\">"))?;
+                let mut attrs = image
+                    .as_node()
+                    .as_element()
+                    .unwrap()
+                    .attributes
+                    .borrow_mut();
+                attrs.remove("srcset");
+                attrs.insert("src", format!("images/{}", urlencoding::encode(&image_id)));
+
+                // keep aspect ratio
+                if attrs.contains("width") {
+                    attrs.remove("height");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolves a link to a Pokémon page, along with the form name shown next to it, if any.
+    pub fn pokemon_link(&self, link: &NodeRef) -> anyhow::Result<Option<(DexId, Option<String>)>> {
+        let Some(href) = get_attr(link, "href") else {
+            return Ok(None);
+        };
+        let url = self.base_url.join(&href)?;
+        let Some(EntryRef::Pokemon(dex_id)) = self.index.links.resolve(&url, false) else {
+            return Ok(None);
+        };
+
+        let form = link
+            .parent()
+            .and_then(|cell| cell.select_first("small").ok())
+            .map(|small| small.text_contents().trim().to_string())
+            .filter(|form| !form.is_empty());
+
+        Ok(Some((dex_id, form)))
+    }
+
+    /// Renders the section's nodes and subsections with their links fixed, skipping empty ones.
+    pub fn section_html(&mut self, section: &Section) -> anyhow::Result<Vec<SectionHtml>> {
+        let untitled = (String::new(), section.nodes.clone());
+        let mut out = Vec::new();
+        for (title, nodes) in std::iter::once(&untitled).chain(&section.subsections) {
+            if nodes.is_empty() {
+                continue;
+            }
+            let mut html = String::new();
+            for node in nodes {
+                self.fix_links(node)
+                    .with_context(|| format!("error fixing links in {}", section.title))?;
+                html += &outer_xhtml(node);
+            }
+            out.push(SectionHtml {
+                title: title.clone(),
+                html,
+            });
+        }
+        Ok(out)
+    }
+
+    /// Returns the footnotes cited so far, with their links fixed.
+    pub fn references(&mut self) -> anyhow::Result<Vec<Reference>> {
+        // footnotes may cite other footnotes, so refs.cited can grow while we go
+        let mut references = Vec::new();
+        let mut i = 0;
+        while i < self.refs.cited.len() {
+            let (id, backlink) = self.refs.cited[i].clone();
+            let note = self.refs.notes[&id].clone();
+            let text = note
+                .select_first(".reference-text")
+                .map_err(|()| anyhow!("reference {id} has no text"))?;
+            self.fix_links(text.as_node())
+                .context("error fixing reference links")?;
+            references.push(Reference {
+                id,
+                backlink,
+                html: inner_xhtml(text.as_node()),
+            });
+            i += 1;
+        }
+        Ok(references)
+    }
+}
+
+pub fn get_attr(node: &NodeRef, attr: &str) -> Option<String> {
+    let el = node.as_element()?;
+    el.attributes.borrow().get(attr).map(|s| s.to_string())
+}
+
+/// A top-level (`<h2>`) section of a wiki page.
+pub struct Section {
+    pub title: String,
+    /// Nodes before the first subsection
+    pub nodes: Vec<NodeRef>,
+    /// `<h3>` subsections and their nodes
+    pub subsections: Vec<(String, Vec<NodeRef>)>,
+}
+
+impl Section {
+    /// All nodes in the section, including those in subsections.
+    pub fn all_nodes(&self) -> impl Iterator<Item = &NodeRef> {
+        self.nodes
+            .iter()
+            .chain(self.subsections.iter().flat_map(|(_, nodes)| nodes))
+    }
+}
+
+/// Splits the children of `.mw-parser-output` into the nodes before the first heading
+/// and the sections after it.
+pub fn read_sections(root: &NodeRef) -> (Vec<NodeRef>, Vec<Section>) {
+    let mut intro = Vec::new();
+    let mut sections: Vec<Section> = Vec::new();
+
+    for node in root.children().filter(is_element) {
+        let tag_name = node.as_element().unwrap().name.local.to_string();
+        match (&*tag_name, sections.last_mut()) {
+            ("h2", _) => sections.push(Section {
+                title: heading_text(&node),
+                nodes: Vec::new(),
+                subsections: Vec::new(),
+            }),
+            ("h3", Some(section)) => section.subsections.push((heading_text(&node), Vec::new())),
+            (_, Some(section)) => match section.subsections.last_mut() {
+                Some((_, nodes)) => nodes.push(node),
+                None => section.nodes.push(node),
+            },
+            (_, None) => intro.push(node),
+        }
+    }
+
+    (intro, sections)
+}
+
+/// Returns the text of a section heading, without “\[edit\]” links and such.
+pub fn heading_text(node: &NodeRef) -> String {
+    node.select_first(".mw-headline")
+        .map(|headline| headline.text_contents())
+        .unwrap_or_else(|()| node.text_contents())
+        .trim()
+        .to_string()
+}
+
+pub fn first_el_child(node: &NodeRef, tag: &str) -> Option<NodeRef> {
+    node.children()
+        .find(|node| node.as_element().is_some_and(|el| &*el.name.local == tag))
+}
+
+pub fn is_tag(node: &NodeRef, tag: &str) -> bool {
+    matches!(node.as_element(), Some(el) if &*el.name.local == tag)
+}
+
+/// Whether the node or one of its ancestors up to (and including) `root` is hidden with
+/// `display: none`.
+pub fn is_hidden_within(node: &NodeRef, root: &NodeRef) -> bool {
+    for ancestor in node.inclusive_ancestors() {
+        let style = get_attr(&ancestor, "style")
+            .map(|s| parse_simple_style_attr(&s))
+            .unwrap_or_default();
+        if style.get("display").map(|d| &**d) == Some("none") {
+            return true;
+        }
+        if ancestor == *root {
+            break;
+        }
+    }
+    false
+}
+
+pub fn is_element(node: &NodeRef) -> bool {
+    node.as_element().is_some()
+}
+
+pub fn parse_simple_style_attr(style: &str) -> BTreeMap<String, String> {
+    let mut values = BTreeMap::new();
+    for entry in style.split(';') {
+        let entry = entry.trim();
+        if let Some((k, v)) = entry.split_once(':') {
+            values.insert(k.trim().to_string(), v.trim().to_string());
+        }
+    }
+    values
+}
+
+pub fn outer_xhtml(node: &NodeRef) -> String {
+    let mut w = Vec::new();
+    crate::xhtml::serialize(&mut w, node).unwrap();
+    String::from_utf8(w).unwrap()
+}
+
+pub fn inner_xhtml(node: &NodeRef) -> String {
+    let mut w = Vec::new();
+    for child in node.children() {
+        crate::xhtml::serialize(&mut w, &child).unwrap();
+    }
+    String::from_utf8(w).unwrap()
+}
+
+pub fn get_highest_quality_src(
+    img: &NodeRef,
+    base_url: &Url,
+    find_thumb_origin: bool,
+) -> Option<Url> {
+    let mut src_set: BTreeMap<_, _> = get_attr(img, "srcset")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|entry| {
+            let (src, size) = entry.rsplit_once(' ')?;
+            Some((size.trim().to_string(), src.to_string()))
+        })
+        .collect();
+    if !src_set.contains_key("1x") {
+        src_set.insert("1x".to_string(), get_attr(img, "src").unwrap_or_default());
+    }
+
+    let src = src_set
+        .get("2x")
+        .or(src_set.get("1.5x"))
+        .or(src_set.get("1x"))?;
+
+    let src = base_url.join(src).ok()?;
+
+    if find_thumb_origin {
+        get_image_thumbnail_origin(&src).or(Some(src))
+    } else {
+        Some(src)
+    }
+}
+
+fn get_image_thumbnail_origin(src: &Url) -> Option<Url> {
+    if src.domain() != Some("archives.bulbagarden.net") || !src.path().contains("/thumb/") {
+        return None;
+    }
+    let mut segments = src.path_segments().unwrap();
+    if segments.next()? != "media" {
+        return None;
+    }
+    if segments.next()? != "upload" {
+        return None;
+    }
+    if segments.next()? != "thumb" {
+        return None;
+    }
+
+    let a = segments.next()?;
+    let b = segments.next()?;
+    let file_name = segments.next()?;
+
+    let new_path = format!("/media/upload/{a}/{b}/{file_name}");
+    let mut url = src.clone();
+    url.set_path(&new_path);
+    Some(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kuchikiki::traits::TendrilSink;
+
+    #[test]
+    fn citations() {
+        let html = r##"<div id="content"><p>Pikachu
+            <sup id="cite_ref-a" class="reference"><a href="#cite_note-a">[7]</a></sup>
+            <sup class="reference"><a href="#cite_note-b">[8]</a></sup>
+            <sup id="" class="reference"><a href="#cite_note-b">[8]</a></sup>
+            <sup id="cite_ref-c" class="reference"><a href="#cite_note-c">[9]</a></sup>
+            <sup id="cite_ref-b" class="reference"><a href="#cite_note-b">[8]</a></sup>
+            <sup id="cite_ref-a2" class="reference"><a href="#cite_note-a">[7]</a></sup></p></div>
+            <ol class="references">
+            <li id="cite_note-a"><span class="reference-text">A</span></li>
+            <li id="cite_note-b"><span class="reference-text">B</span></li>
+            </ol>"##;
+        let doc = kuchikiki::parse_html().one(html);
+        let dir = std::env::temp_dir();
+        let fetcher = Fetcher::new(dir.clone());
+        let images = ImageCache::new(dir);
+        let index = Index::default();
+        let config = Config::default();
+        let url = "https://bulbapedia.bulbagarden.net/wiki/Pikachu_(Pok%C3%A9mon)";
+        let mut page = Page::new(&fetcher, &index, &images, &config, url, &doc);
+
+        let content = doc.select_first("#content").unwrap();
+        page.fix_links(content.as_node()).unwrap();
+        let citations: Vec<_> = content
+            .as_node()
+            .select("sup")
+            .unwrap()
+            .map(|sup| sup.text_contents())
+            .collect();
+        // the ones without an ID or note are gone, the rest renumbered
+        assert_eq!(citations, ["[1]", "[2]", "[1]"]);
+
+        let references: Vec<_> = page
+            .references()
+            .unwrap()
+            .into_iter()
+            .map(|reference| (reference.id, reference.backlink, reference.html))
+            .collect();
+        assert_eq!(
+            references,
+            [
+                ("cite_note-a".into(), "cite_ref-a".into(), "A".into()),
+                ("cite_note-b".into(), "cite_ref-b".into(), "B".into()),
+            ]
+        );
+    }
+}
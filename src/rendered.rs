@@ -0,0 +1,256 @@
+//! Splits the generated Apple XML back into entries, so that other formats use exactly the same
+//! HTML and index keys as the Apple dictionary.
+
+use anyhow::Context;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// One `d:entry`.
+#[derive(Debug, Clone)]
+pub struct RenderedEntry {
+    /// The `d:entry` ID, e.g. “pokemon-25”
+    pub id: String,
+    pub title: String,
+    pub keys: Vec<IndexKey>,
+    /// The entry's XHTML, without the `d:index` elements
+    pub html: String,
+}
+
+/// One `d:index`.
+#[derive(Debug, Clone)]
+pub struct IndexKey {
+    pub value: String,
+    /// Title shown in search results, if not the entry title
+    pub title: Option<String>,
+    /// Reading, for Japanese keys
+    pub yomi: Option<String>,
+    /// ID of the element the key points to, if not the whole entry
+    pub anchor: Option<String>,
+}
+
+impl RenderedEntry {
+    /// All distinct search words for the entry, starting with the title.
+    pub fn words(&self) -> Vec<&str> {
+        let mut words = vec![self.title.as_str()];
+        for key in &self.keys {
+            if !words.contains(&key.value.as_str()) {
+                words.push(&key.value);
+            }
+        }
+        words
+    }
+}
+
+/// Splits `Dictionary.xml` as written by [`generate_dictionary`](crate::generate_dictionary).
+pub fn split_entries(xml: &str) -> anyhow::Result<Vec<RenderedEntry>> {
+    const ENTRY_START: &str = "<d:entry ";
+    const ENTRY_END: &str = "</d:entry>";
+    const INDEX_START: &str = "<d:index ";
+
+    let mut entries = Vec::new();
+    let mut rest = xml;
+    while let Some(i) = rest.find(ENTRY_START) {
+        rest = &rest[i..];
+        let tag_end = rest.find('>').context("unterminated d:entry tag")?;
+        let tag = &rest[..tag_end];
+        let id = attr_value(tag, "id").context("d:entry without id")?;
+        let title =
+            attr_value(tag, "d:title").with_context(|| format!("entry {id} has no title"))?;
+        rest = &rest[tag_end + 1..];
+
+        let end = rest
+            .find(ENTRY_END)
+            .with_context(|| format!("entry {id} is not terminated"))?;
+        let mut body = &rest[..end];
+        rest = &rest[end + ENTRY_END.len()..];
+
+        let mut keys = Vec::new();
+        let mut html = String::with_capacity(body.len());
+        while let Some(i) = body.find(INDEX_START) {
+            html.push_str(&body[..i]);
+            body = &body[i..];
+            let tag_end = body
+                .find("/>")
+                .with_context(|| format!("unterminated d:index in entry {id}"))?;
+            let tag = &body[..tag_end];
+            keys.push(IndexKey {
+                value: attr_value(tag, "d:value")
+                    .with_context(|| format!("d:index without value in entry {id}"))?,
+                title: attr_value(tag, "d:title"),
+                yomi: attr_value(tag, "d:yomi"),
+                anchor: attr_value(tag, "d:anchor").and_then(|anchor| {
+                    Some(
+                        anchor
+                            .strip_prefix("xpointer(//*[@id='")?
+                            .strip_suffix("'])")?
+                            .to_string(),
+                    )
+                }),
+            });
+            body = &body[tag_end + 2..];
+            body = body.strip_prefix('\n').unwrap_or(body);
+        }
+        html.push_str(body);
+
+        entries.push(RenderedEntry {
+            id,
+            title,
+            keys,
+            html: html.trim().to_string(),
+        });
+    }
+    Ok(entries)
+}
+
+/// Gives entries that share a title (ignoring case) distinct ones, for formats that link to
+/// entries by title: e.g. the Metronome move and item become “Metronome (move)” and
+/// “Metronome (item)”. Pokémon keep their names, and the old title stays a key of the entry.
+pub fn disambiguate_titles(entries: &mut [RenderedEntry]) {
+    let count_titles = |entries: &[RenderedEntry]| {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for entry in entries {
+            *counts.entry(entry.title.to_lowercase()).or_default() += 1;
+        }
+        counts
+    };
+
+    let counts = count_titles(entries);
+    for entry in entries.iter_mut() {
+        let kind = entry.id.split('-').next().unwrap_or_default();
+        if counts[&entry.title.to_lowercase()] < 2 || kind == "pokemon" {
+            continue;
+        }
+        let title = format!("{} ({kind})", entry.title);
+        rename(entry, title);
+    }
+
+    // anything still ambiguous gets numbered, in entry order
+    let counts = count_titles(entries);
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for entry in entries.iter_mut() {
+        let key = entry.title.to_lowercase();
+        if counts[&key] < 2 {
+            continue;
+        }
+        let n = seen.entry(key).or_default();
+        *n += 1;
+        if *n > 1 {
+            let title = format!("{} ({n})", entry.title);
+            rename(entry, title);
+        }
+    }
+}
+
+fn rename(entry: &mut RenderedEntry, title: String) {
+    let old = std::mem::replace(&mut entry.title, title);
+    if !entry.keys.iter().any(|key| key.value == old) {
+        entry.keys.insert(
+            0,
+            IndexKey {
+                value: old,
+                title: None,
+                yomi: None,
+                anchor: None,
+            },
+        );
+    }
+}
+
+fn attr_value(tag: &str, name: &str) -> Option<String> {
+    let start = tag.find(&format!(" {name}=\""))? + name.len() + 3;
+    let len = tag[start..].find('"')?;
+    Some(unescape(&tag[start..start + len]))
+}
+
+/// Reverses [`XhtmlEscaped`](crate::xhtml::XhtmlEscaped).
+pub fn unescape(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Replaces every `x-dictionary:r:<entry>#<fragment>` link target with `f(entry, fragment)`,
+/// which get the unescaped values. The result is inserted as is, so it must already be escaped for
+/// an attribute.
+pub fn rewrite_links(html: &str, mut f: impl FnMut(&str, Option<&str>) -> String) -> String {
+    rewrite_attr(html, "href=\"x-dictionary:r:", |target| {
+        match target.split_once('#') {
+            Some((entry, fragment)) => f(entry, Some(fragment)),
+            None => f(target, None),
+        }
+    })
+}
+
+/// Replaces every `images/<file>` image source with `f(file)`, where `file` is the decoded file
+/// name in the image directory. The result is inserted as is, so it must already be escaped for
+/// an attribute.
+pub fn rewrite_images(html: &str, mut f: impl FnMut(&str) -> String) -> String {
+    rewrite_attr(html, "src=\"images/", |src| {
+        let file = urlencoding::decode(src).unwrap_or(Cow::Borrowed(src));
+        f(&file)
+    })
+}
+
+/// Replaces the rest of every attribute value starting with `start`, e.g. `src="images/`, with
+/// `f` of its unescaped value. A value without a closing quote is left alone.
+fn rewrite_attr(html: &str, start: &str, mut f: impl FnMut(&str) -> String) -> String {
+    let value_start = start.find('"').map_or(0, |i| i + 1);
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(i) = rest.find(start) {
+        let Some(end) = rest[i + start.len()..].find('"') else {
+            break;
+        };
+        out.push_str(&rest[..i + value_start]);
+        rest = &rest[i + start.len()..];
+        out.push_str(&f(&unescape(&rest[..end])));
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Removes `x-dictionary:r:` links, keeping their contents, for formats that can't link between
+/// entries.
+pub fn strip_links(html: &str) -> String {
+    strip_links_where(html, |_| true)
+}
+
+/// Removes the `x-dictionary:r:` links whose (unescaped) entry ID `f` returns true for, keeping
+/// their contents.
+pub fn strip_links_where(html: &str, mut f: impl FnMut(&str) -> bool) -> String {
+    const LINK: &str = "href=\"x-dictionary:r:";
+
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(i) = rest.find(LINK) {
+        let target = rest[i + LINK.len()..]
+            .find('"')
+            .map(|end| unescape(&rest[i + LINK.len()..i + LINK.len() + end]));
+        let strip = target
+            .is_none_or(|target| f(target.split_once('#').map_or(&*target, |(entry, _)| entry)));
+        let tag_start = rest[..i].rfind("<a ").filter(|_| strip);
+        let Some(tag_start) = tag_start else {
+            out.push_str(&rest[..i + LINK.len()]);
+            rest = &rest[i + LINK.len()..];
+            continue;
+        };
+        out.push_str(&rest[..tag_start]);
+        rest = &rest[i..];
+        let Some(tag_end) = rest.find('>') else {
+            break;
+        };
+        rest = &rest[tag_end + 1..];
+        // links don't nest, so the next closing tag is this link's
+        match rest.find("</a>") {
+            Some(close) => {
+                out.push_str(&rest[..close]);
+                rest = &rest[close + "</a>".len()..];
+            }
+            None => break,
+        }
+    }
+    out.push_str(rest);
+    out
+}
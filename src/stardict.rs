@@ -0,0 +1,139 @@
+//! [StarDict] dictionaries, e.g. for GoldenDict or KOReader.
+//!
+//! [StarDict]: https://github.com/huzheng001/stardict-3/blob/master/dict/doc/StarDictFileFormat
+
+use crate::backend::{Backend, Dictionary};
+use crate::dictzip::dictzip;
+use crate::rendered::{rewrite_images, rewrite_links};
+use crate::xhtml::XhtmlEscaped;
+use anyhow::{ensure, Context};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// Base name of the output files.
+const NAME: &str = "pokedex";
+
+/// HTML entries (`sametypesequence=h`) with `.syn` synonyms for the other index keys. Links
+/// become `bword://` links to the target entry's title, and images go in `res/`.
+pub struct StarDict;
+
+impl Backend for StarDict {
+    fn name(&self) -> &'static str {
+        "stardict"
+    }
+
+    fn write(&self, dict: &Dictionary, out_dir: &Path) -> anyhow::Result<()> {
+        let entries = dict.rendered_entries()?;
+        let titles: HashMap<&str, &str> = entries
+            .iter()
+            .map(|entry| (entry.id.as_str(), entry.title.as_str()))
+            .collect();
+
+        let mut data = Vec::new();
+        let mut words = Vec::new();
+        let mut synonyms = Vec::new();
+        let mut images = BTreeSet::new();
+        for (i, entry) in entries.iter().enumerate() {
+            let html = rewrite_links(&entry.html, |id, _| {
+                let title = titles.get(id).copied().unwrap_or(id);
+                format!("bword://{}", XhtmlEscaped(title, true))
+            });
+            // resources are looked up relative to res/
+            let html = rewrite_images(&html, |file| {
+                images.insert(file.to_string());
+                urlencoding::encode(file).into_owned()
+            });
+
+            let offset = u32::try_from(data.len()).context("dictionary is too large")?;
+            data.extend_from_slice(html.as_bytes());
+            words.push(Word {
+                word: &entry.title,
+                offset,
+                size: html.len() as u32,
+                entry: i,
+            });
+            for word in entry.words().into_iter().skip(1) {
+                synonyms.push((word, i));
+            }
+        }
+
+        words.sort_by(|a, b| stardict_cmp(a.word, b.word));
+        // synonyms point at the position in the sorted index
+        let mut positions = vec![0; entries.len()];
+        let mut idx = Vec::new();
+        for (position, word) in words.iter().enumerate() {
+            positions[word.entry] = position as u32;
+            write_word(&mut idx, word.word)?;
+            idx.extend_from_slice(&word.offset.to_be_bytes());
+            idx.extend_from_slice(&word.size.to_be_bytes());
+        }
+
+        synonyms.sort_by(|a, b| stardict_cmp(a.0, b.0));
+        let mut syn = Vec::new();
+        for (word, entry) in &synonyms {
+            write_word(&mut syn, word)?;
+            syn.extend_from_slice(&positions[*entry].to_be_bytes());
+        }
+
+        let mut ifo = String::from("StarDict's dict ifo file\nversion=3.0.0\n");
+        writeln!(ifo, "bookname=Pokédex")?;
+        writeln!(ifo, "wordcount={}", words.len())?;
+        writeln!(ifo, "synwordcount={}", synonyms.len())?;
+        writeln!(ifo, "idxfilesize={}", idx.len())?;
+        writeln!(
+            ifo,
+            "description=Pokémon, abilities, moves, items and types from Bulbapedia."
+        )?;
+        writeln!(ifo, "sametypesequence=h")?;
+
+        fs::create_dir_all(out_dir)?;
+        fs::write(out_dir.join(format!("{NAME}.ifo")), ifo).context("error writing .ifo")?;
+        fs::write(out_dir.join(format!("{NAME}.idx")), idx).context("error writing .idx")?;
+        fs::write(out_dir.join(format!("{NAME}.syn")), syn).context("error writing .syn")?;
+        fs::write(out_dir.join(format!("{NAME}.dict.dz")), dictzip(&data)?)
+            .context("error writing .dict.dz")?;
+
+        let res_dir = out_dir.join("res");
+        if res_dir.exists() {
+            fs::remove_dir_all(&res_dir)?;
+        }
+        fs::create_dir_all(&res_dir)?;
+        for file in images {
+            fs::copy(dict.image_dir.join(&file), res_dir.join(&file))
+                .with_context(|| format!("error copying image {file}"))?;
+        }
+        Ok(())
+    }
+}
+
+struct Word<'a> {
+    word: &'a str,
+    offset: u32,
+    size: u32,
+    /// Index into the entry list
+    entry: usize,
+}
+
+fn write_word(out: &mut Vec<u8>, word: &str) -> anyhow::Result<()> {
+    ensure!(
+        word.len() < 256 && !word.contains('\0'),
+        "invalid StarDict word “{word}”"
+    );
+    out.extend_from_slice(word.as_bytes());
+    out.push(0);
+    Ok(())
+}
+
+/// The order StarDict expects in `.idx` and `.syn` files: ASCII case-insensitive, then
+/// byte-wise.
+fn stardict_cmp(a: &str, b: &str) -> Ordering {
+    let lower = |s: &str| {
+        s.bytes()
+            .map(|b| b.to_ascii_lowercase())
+            .collect::<Vec<_>>()
+    };
+    lower(a).cmp(&lower(b)).then_with(|| a.cmp(b))
+}
@@ -0,0 +1,160 @@
+use crate::fetcher::Fetcher;
+use crate::image::ImageCache;
+use crate::index::{Index, TypeId};
+use crate::page::{is_tag, outer_xhtml, read_sections, Page, Reference, SectionHtml};
+use crate::Config;
+use anyhow::{anyhow, Context};
+use html5ever::tendril::TendrilSink;
+use serde::{Deserialize, Serialize};
+
+/// All types, in the order the games list them.
+pub const TYPE_NAMES: &[&str] = &[
+    "Normal", "Fire", "Water", "Electric", "Grass", "Ice", "Fighting", "Poison", "Ground",
+    "Flying", "Psychic", "Bug", "Rock", "Ghost", "Dragon", "Dark", "Steel", "Fairy",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effectiveness {
+    NoEffect,
+    NotVeryEffective,
+    SuperEffective,
+}
+
+/// Type matchups since Generation VI, as (attacking type, defending type, effectiveness).
+/// Anything not listed is regularly effective.
+#[rustfmt::skip]
+const MATCHUPS: &[(&str, &str, Effectiveness)] = {
+    use Effectiveness::{NoEffect as X, NotVeryEffective as H, SuperEffective as D};
+    &[
+        ("Normal", "Rock", H), ("Normal", "Ghost", X), ("Normal", "Steel", H),
+        ("Fire", "Fire", H), ("Fire", "Water", H), ("Fire", "Grass", D), ("Fire", "Ice", D),
+        ("Fire", "Bug", D), ("Fire", "Rock", H), ("Fire", "Dragon", H), ("Fire", "Steel", D),
+        ("Water", "Fire", D), ("Water", "Water", H), ("Water", "Grass", H), ("Water", "Ground", D),
+        ("Water", "Rock", D), ("Water", "Dragon", H),
+        ("Electric", "Water", D), ("Electric", "Electric", H), ("Electric", "Grass", H),
+        ("Electric", "Ground", X), ("Electric", "Flying", D), ("Electric", "Dragon", H),
+        ("Grass", "Fire", H), ("Grass", "Water", D), ("Grass", "Grass", H), ("Grass", "Poison", H),
+        ("Grass", "Ground", D), ("Grass", "Flying", H), ("Grass", "Bug", H), ("Grass", "Rock", D),
+        ("Grass", "Dragon", H), ("Grass", "Steel", H),
+        ("Ice", "Fire", H), ("Ice", "Water", H), ("Ice", "Grass", D), ("Ice", "Ice", H),
+        ("Ice", "Ground", D), ("Ice", "Flying", D), ("Ice", "Dragon", D), ("Ice", "Steel", H),
+        ("Fighting", "Normal", D), ("Fighting", "Ice", D), ("Fighting", "Poison", H),
+        ("Fighting", "Flying", H), ("Fighting", "Psychic", H), ("Fighting", "Bug", H),
+        ("Fighting", "Rock", D), ("Fighting", "Ghost", X), ("Fighting", "Dark", D),
+        ("Fighting", "Steel", D), ("Fighting", "Fairy", H),
+        ("Poison", "Grass", D), ("Poison", "Poison", H), ("Poison", "Ground", H),
+        ("Poison", "Rock", H), ("Poison", "Ghost", H), ("Poison", "Steel", X), ("Poison", "Fairy", D),
+        ("Ground", "Fire", D), ("Ground", "Electric", D), ("Ground", "Grass", H),
+        ("Ground", "Poison", D), ("Ground", "Flying", X), ("Ground", "Bug", H), ("Ground", "Rock", D),
+        ("Ground", "Steel", D),
+        ("Flying", "Electric", H), ("Flying", "Grass", D), ("Flying", "Fighting", D),
+        ("Flying", "Bug", D), ("Flying", "Rock", H), ("Flying", "Steel", H),
+        ("Psychic", "Fighting", D), ("Psychic", "Poison", D), ("Psychic", "Psychic", H),
+        ("Psychic", "Dark", X), ("Psychic", "Steel", H),
+        ("Bug", "Fire", H), ("Bug", "Grass", D), ("Bug", "Fighting", H), ("Bug", "Poison", H),
+        ("Bug", "Flying", H), ("Bug", "Psychic", D), ("Bug", "Ghost", H), ("Bug", "Dark", D),
+        ("Bug", "Steel", H), ("Bug", "Fairy", H),
+        ("Rock", "Fire", D), ("Rock", "Ice", D), ("Rock", "Fighting", H), ("Rock", "Ground", H),
+        ("Rock", "Flying", D), ("Rock", "Bug", D), ("Rock", "Steel", H),
+        ("Ghost", "Normal", X), ("Ghost", "Psychic", D), ("Ghost", "Ghost", D), ("Ghost", "Dark", H),
+        ("Dragon", "Dragon", D), ("Dragon", "Steel", H), ("Dragon", "Fairy", X),
+        ("Dark", "Fighting", H), ("Dark", "Psychic", D), ("Dark", "Ghost", D), ("Dark", "Dark", H),
+        ("Dark", "Fairy", H),
+        ("Steel", "Fire", H), ("Steel", "Water", H), ("Steel", "Electric", H), ("Steel", "Ice", D),
+        ("Steel", "Rock", D), ("Steel", "Steel", H), ("Steel", "Fairy", D),
+        ("Fairy", "Fire", H), ("Fairy", "Fighting", D), ("Fairy", "Poison", H),
+        ("Fairy", "Dragon", D), ("Fairy", "Dark", D), ("Fairy", "Steel", H),
+    ]
+};
+
+/// Types this type is (super/not very/not) effective against when attacking.
+pub fn offensive_matchups(name: &str, effectiveness: Effectiveness) -> Vec<&'static str> {
+    MATCHUPS
+        .iter()
+        .filter(|(attacker, _, e)| *attacker == name && *e == effectiveness)
+        .map(|(_, defender, _)| *defender)
+        .collect()
+}
+
+/// Types that are (super/not very/not) effective against this type when it defends.
+pub fn defensive_matchups(name: &str, effectiveness: Effectiveness) -> Vec<&'static str> {
+    MATCHUPS
+        .iter()
+        .filter(|(_, defender, e)| *defender == name && *e == effectiveness)
+        .map(|(attacker, _, _)| *attacker)
+        .collect()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TypeEntry {
+    /// Page URL
+    pub url: String,
+
+    pub id: TypeId,
+    pub name: String,
+    pub name_jp_text: Option<String>,
+    pub name_jp_html: Option<String>,
+
+    /// Summary HTML contents
+    pub summary_html: String,
+    /// Battle properties over the generations
+    pub history: Vec<SectionHtml>,
+    /// Footnotes cited in the summary or history, in citation order
+    pub references: Vec<Reference>,
+}
+
+pub fn read_type(
+    fetcher: &Fetcher,
+    index: &Index,
+    image_cache: &ImageCache,
+    config: &Config,
+    id: TypeId,
+    url: &str,
+) -> anyhow::Result<TypeEntry> {
+    let html = String::from_utf8(fetcher.get(url, true)?)?;
+    let doc = kuchikiki::parse_html().one(html);
+    let mut page = Page::new(fetcher, index, image_cache, config, url, &doc);
+
+    let name_jp = doc
+        .select_first(".mw-parser-output table.roundy [lang='ja']")
+        .ok();
+    let name_jp_text = name_jp
+        .as_ref()
+        .map(|node| node.text_contents().trim().to_string());
+    let name_jp_html = name_jp.as_ref().map(|node| outer_xhtml(node.as_node()));
+
+    let mw_parser_output = doc
+        .select_first(".mw-parser-output")
+        .map_err(|()| anyhow!("no mw-parser-output"))?;
+
+    let (intro, sections) = read_sections(mw_parser_output.as_node());
+
+    let mut summary_html = String::new();
+    for node in intro {
+        if is_tag(&node, "p") {
+            page.fix_links(&node)
+                .context("error fixing summary links")?;
+            summary_html += &outer_xhtml(&node);
+        }
+    }
+
+    let mut history = Vec::new();
+    for section in &sections {
+        if section.title == "Battle properties" {
+            history = page.section_html(section)?;
+        }
+    }
+
+    let references = page.references()?;
+
+    Ok(TypeEntry {
+        url: url.to_string(),
+        id,
+        name: id.name().to_string(),
+        name_jp_text,
+        name_jp_html,
+        summary_html,
+        history,
+        references,
+    })
+}
@@ -0,0 +1,289 @@
+//! [XDXF] (XML Dictionary eXchange Format) dictionaries, which many open-source dictionary tools
+//! can import and convert.
+//!
+//! [XDXF]: https://github.com/soshial/xdxf_makedict/blob/master/format_standard/xdxf_description.md
+
+use crate::backend::{Backend, Dictionary};
+use crate::date;
+use crate::page::get_attr;
+use crate::xhtml::XhtmlEscaped;
+use anyhow::Context;
+use kuchikiki::traits::TendrilSink;
+use kuchikiki::NodeRef;
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// One visual-format article per entry, with the title and other index keys as `<k>` keys and a
+/// definition converted with [`xhtml_to_xdxf`]. Written as `dict.xdxf`, with the images in `res/`.
+pub struct Xdxf;
+
+impl Backend for Xdxf {
+    fn name(&self) -> &'static str {
+        "xdxf"
+    }
+
+    fn write(&self, dict: &Dictionary, out_dir: &Path) -> anyhow::Result<()> {
+        let entries = dict.rendered_entries()?;
+        let titles: HashMap<&str, &str> = entries
+            .iter()
+            .map(|entry| (entry.id.as_str(), entry.title.as_str()))
+            .collect();
+
+        let (year, month, day) = date::today();
+
+        let mut out = String::new();
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            out,
+            r#"<!DOCTYPE xdxf SYSTEM "https://raw.github.com/soshial/xdxf_makedict/master/format_standard/xdxf_strict.dtd">"#
+        )?;
+        writeln!(
+            out,
+            r#"<xdxf lang_from="ENG" lang_to="ENG" format="visual" revision="034">"#
+        )?;
+        writeln!(out, "<meta_info>")?;
+        writeln!(
+            out,
+            r#"<languages><from xml:lang="ENG"/><to xml:lang="ENG"/></languages>"#
+        )?;
+        writeln!(out, "<title>Pokédex</title>")?;
+        writeln!(out, "<full_title>Pokédex</full_title>")?;
+        writeln!(
+            out,
+            "<description>Pokémon, abilities, moves, items and types from Bulbapedia.</description>"
+        )?;
+        writeln!(out, "<file_ver>{}</file_ver>", env!("CARGO_PKG_VERSION"))?;
+        writeln!(
+            out,
+            "<creation_date>{day:02}-{month:02}-{year:04}</creation_date>"
+        )?;
+        writeln!(
+            out,
+            "<dict_src_url>https://bulbapedia.bulbagarden.net</dict_src_url>"
+        )?;
+        writeln!(out, "</meta_info>")?;
+        writeln!(out, "<lexicon>")?;
+
+        let mut images = BTreeSet::new();
+        for entry in &entries {
+            out += "<ar>";
+            for word in entry.words() {
+                write!(out, "<k>{}</k>", XhtmlEscaped(word, false))?;
+            }
+            let def = xhtml_to_xdxf(&entry.html, |id| titles.get(id).copied(), &mut images);
+            writeln!(out, "\n<def><deftext>{def}</deftext></def></ar>")?;
+        }
+        writeln!(out, "</lexicon>")?;
+        writeln!(out, "</xdxf>")?;
+
+        fs::create_dir_all(out_dir)?;
+        fs::write(out_dir.join("dict.xdxf"), out).context("error writing dict.xdxf")?;
+
+        let res_dir = out_dir.join("res");
+        if res_dir.exists() {
+            fs::remove_dir_all(&res_dir)?;
+        }
+        fs::create_dir_all(&res_dir)?;
+        for file in images {
+            fs::copy(dict.image_dir.join(&file), res_dir.join(&file))
+                .with_context(|| format!("error copying image {file}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Converts entry XHTML to the contents of a visual-format `<deftext>`. `entry_title` resolves
+/// `x-dictionary:r:` link targets to keys, and image file names are added to `images`.
+///
+/// Block elements become lines, since line breaks are kept in visual format, and list items get
+/// bullets. Bold, italics, underline, super- and subscripts keep their tags, entry links become
+/// `<kref>`, other links `<iref>`, images `<rref>`, and table rows lines with cells separated by
+/// “ | ”.
+pub fn xhtml_to_xdxf<'a>(
+    html: &str,
+    entry_title: impl Fn(&str) -> Option<&'a str>,
+    images: &mut BTreeSet<String>,
+) -> String {
+    let doc = kuchikiki::parse_html().one(html);
+    let mut converter = Converter {
+        entry_title: &entry_title,
+        images,
+        out: String::new(),
+        line_start: true,
+        space: false,
+        depth: 0,
+    };
+    converter.children(&doc);
+    converter.out.trim_end().to_string()
+}
+
+struct Converter<'a, 'b> {
+    entry_title: &'b dyn Fn(&str) -> Option<&'a str>,
+    images: &'b mut BTreeSet<String>,
+    out: String,
+    /// Whether nothing but tags has been written since the last line break
+    line_start: bool,
+    /// Whether whitespace was skipped since the last text
+    space: bool,
+    /// List nesting depth
+    depth: usize,
+}
+
+impl Converter<'_, '_> {
+    fn children(&mut self, node: &NodeRef) {
+        for child in node.children() {
+            self.node(&child);
+        }
+    }
+
+    fn node(&mut self, node: &NodeRef) {
+        if let Some(text) = node.as_text() {
+            self.text(&text.borrow());
+            return;
+        }
+        let Some(el) = node.as_element() else {
+            self.children(node);
+            return;
+        };
+
+        match &*el.name.local {
+            "head" | "script" | "style" => (),
+            "br" => self.break_line(),
+            "b" | "strong" => self.inline(node, "b"),
+            "i" | "em" | "cite" => self.inline(node, "i"),
+            "u" => self.inline(node, "u"),
+            "sup" => self.inline(node, "sup"),
+            "sub" => self.inline(node, "sub"),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "dt" | "caption" => {
+                self.break_line();
+                self.inline(node, "b");
+                self.break_line();
+            }
+            "img" => {
+                let src = get_attr(node, "src").unwrap_or_default();
+                if let Some(src) = src.strip_prefix("images/") {
+                    let file = urlencoding::decode(src)
+                        .unwrap_or(Cow::Borrowed(src))
+                        .into_owned();
+                    self.push(&format!("<rref>{}</rref>", XhtmlEscaped(&file, false)));
+                    self.images.insert(file);
+                }
+            }
+            "a" => self.link(node),
+            "li" | "dd" => {
+                self.break_line();
+                self.depth += 1;
+                let indent = "  ".repeat(self.depth - 1);
+                if &*el.name.local == "li" {
+                    self.push(&format!("{indent}• "));
+                } else {
+                    self.push(&format!("{indent}  "));
+                }
+                self.children(node);
+                self.break_line();
+                self.depth -= 1;
+            }
+            "td" | "th" => {
+                if !self.line_start {
+                    self.push(" | ");
+                }
+                if &*el.name.local == "th" {
+                    self.inline(node, "b");
+                } else {
+                    self.children(node);
+                }
+            }
+            "p" | "div" | "ul" | "ol" | "dl" | "table" | "tbody" | "thead" | "tr" | "figure"
+            | "figcaption" | "blockquote" | "section" | "header" | "footer" => {
+                self.break_line();
+                self.children(node);
+                self.break_line();
+            }
+            _ => self.children(node),
+        }
+    }
+
+    fn link(&mut self, node: &NodeRef) {
+        let href = get_attr(node, "href").unwrap_or_default();
+        if href.starts_with("http://") || href.starts_with("https://") {
+            self.tag(
+                node,
+                &format!(r#"<iref href="{}">"#, XhtmlEscaped(&href, true)),
+                "</iref>",
+            );
+            return;
+        }
+        let target = href
+            .strip_prefix("x-dictionary:r:")
+            .map(|target| target.split('#').next().unwrap_or(target))
+            .and_then(|id| (self.entry_title)(id));
+        let Some(title) = target else {
+            self.children(node);
+            return;
+        };
+
+        let text = node.text_contents();
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let kref = format!("<kref>{}</kref>", XhtmlEscaped(title, false));
+        // <kref> shows the key it links to, so keep differing link text next to it
+        if text.is_empty() || text == title {
+            self.push(&kref);
+        } else {
+            self.children(node);
+            self.push(&format!(" ({kref})"));
+        }
+    }
+
+    fn inline(&mut self, node: &NodeRef, tag: &str) {
+        self.tag(node, &format!("<{tag}>"), &format!("</{tag}>"));
+    }
+
+    /// Writes the children between an opening and closing tag, which don't count as content.
+    fn tag(&mut self, node: &NodeRef, open: &str, close: &str) {
+        self.flush_space();
+        self.out.push_str(open);
+        self.children(node);
+        self.out.push_str(close);
+    }
+
+    fn text(&mut self, text: &str) {
+        let mut words = text.split_whitespace().peekable();
+        if text.starts_with(char::is_whitespace) {
+            self.space = true;
+        }
+        while let Some(word) = words.next() {
+            self.push(&XhtmlEscaped(word, false).to_string());
+            if words.peek().is_some() {
+                self.space = true;
+            }
+        }
+        if text.ends_with(char::is_whitespace) {
+            self.space = true;
+        }
+    }
+
+    /// Writes content, preceded by a space if whitespace was skipped before it.
+    fn push(&mut self, s: &str) {
+        self.flush_space();
+        self.out.push_str(s);
+        self.line_start = false;
+    }
+
+    fn flush_space(&mut self) {
+        if std::mem::take(&mut self.space) && !self.line_start && !self.out.ends_with(' ') {
+            self.out.push(' ');
+        }
+    }
+
+    /// Ends the current line, if it has any content.
+    fn break_line(&mut self) {
+        self.space = false;
+        if !self.line_start {
+            self.out.push('\n');
+            self.line_start = true;
+        }
+    }
+}
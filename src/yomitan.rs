@@ -0,0 +1,183 @@
+//! [Yomitan] dictionaries, for pop-up lookups of Pokémon names in Japanese games.
+//!
+//! [Yomitan]: https://github.com/yomidevs/yomitan/blob/master/docs/making-yomitan-dictionaries.md
+
+use crate::backend::{Backend, Dictionary};
+use crate::kana::to_hiragana;
+use crate::mon::MonEntry;
+use anyhow::Context;
+use kuchikiki::traits::TendrilSink;
+use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Terms per term bank file.
+const BANK_SIZE: usize = 10000;
+
+/// Width of the Pokémon image in the glossary, in em.
+const IMAGE_WIDTH: f32 = 6.0;
+
+/// Image file extensions Yomitan can show.
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "svg", "bmp", "avif"];
+
+/// One term per Japanese and English Pokémon name, with a glossary showing the image, Pokédex
+/// number, types, categories and the first paragraph of the summary. Types are also term tags.
+pub struct Yomitan;
+
+impl Backend for Yomitan {
+    fn name(&self) -> &'static str {
+        "yomitan"
+    }
+
+    fn write(&self, dict: &Dictionary, out_dir: &Path) -> anyhow::Result<()> {
+        let mut terms = Vec::new();
+        let mut types = BTreeSet::new();
+        let mut images = BTreeSet::new();
+        for mon in dict.entries.pokemon.values() {
+            let image = mon
+                .images
+                .first()
+                .and_then(|image| image.src.strip_prefix("images/"))
+                .map(|src| {
+                    urlencoding::decode(src)
+                        .unwrap_or(Cow::Borrowed(src))
+                        .into_owned()
+                })
+                .filter(|file| {
+                    let ext = file.rsplit_once('.').map_or("", |(_, ext)| ext);
+                    IMAGE_EXTENSIONS.contains(&&*ext.to_ascii_lowercase())
+                });
+            let glossary = [glossary(mon, image.as_deref())];
+            let tags = mon.types.join(" ");
+            let sequence = mon.dex_id.0;
+
+            if !mon.name_jp_text.is_empty() {
+                terms.push(json!([
+                    mon.name_jp_text,
+                    to_hiragana(&mon.name_jp_text),
+                    tags,
+                    "",
+                    0,
+                    glossary,
+                    sequence,
+                    "",
+                ]));
+            }
+            terms.push(json!([mon.name, "", tags, "", 0, glossary, sequence, ""]));
+
+            types.extend(mon.types.iter().cloned());
+            images.extend(image);
+        }
+
+        let index = json!({
+            "title": "Pokédex",
+            "revision": env!("CARGO_PKG_VERSION"),
+            "format": 3,
+            "sequenced": true,
+            "description": "Pokémon names in Japanese and English, from Bulbapedia.",
+            "attribution": "Bulbapedia (CC BY-NC-SA 2.5)",
+            "url": "https://bulbapedia.bulbagarden.net",
+        });
+        let tag_bank: Vec<_> = types
+            .iter()
+            .map(|name| json!([name, "partOfSpeech", 0, format!("{name} type"), 0]))
+            .collect();
+
+        fs::create_dir_all(out_dir)?;
+        let path = out_dir.join("pokedex.zip");
+        let mut zip = ZipWriter::new(File::create(&path).context("error creating pokedex.zip")?);
+        let options = FileOptions::default();
+        zip.start_file("index.json", options)?;
+        zip.write_all(&serde_json::to_vec(&index)?)?;
+        zip.start_file("tag_bank_1.json", options)?;
+        zip.write_all(&serde_json::to_vec(&tag_bank)?)?;
+        for (i, bank) in terms.chunks(BANK_SIZE).enumerate() {
+            zip.start_file(format!("term_bank_{}.json", i + 1), options)?;
+            zip.write_all(&serde_json::to_vec(bank)?)?;
+        }
+
+        // PNG, JPEG etc. are compressed already
+        let options = options.compression_method(CompressionMethod::Stored);
+        for file in images {
+            let data = fs::read(dict.image_dir.join(&file))
+                .with_context(|| format!("error reading image {file}"))?;
+            zip.start_file(format!("images/{file}"), options)?;
+            zip.write_all(&data)?;
+        }
+        zip.finish().context("error writing pokedex.zip")?;
+        Ok(())
+    }
+}
+
+/// The structured-content glossary of a Pokémon.
+fn glossary(mon: &MonEntry, image: Option<&str>) -> Value {
+    let mut content = Vec::new();
+    if let Some(image) = image {
+        content.push(json!({
+            "tag": "img",
+            "path": format!("images/{image}"),
+            "width": IMAGE_WIDTH,
+            "sizeUnits": "em",
+            "title": mon.name,
+            "collapsible": false,
+            "background": false,
+        }));
+    }
+
+    let mut heading = format!("#{:04} {}", mon.dex_id.0, mon.name);
+    if !mon.name_jp_text.is_empty() {
+        heading += &format!(" ({})", mon.name_jp_text);
+    }
+    content.push(json!({
+        "tag": "div",
+        "style": { "fontWeight": "bold" },
+        "content": heading,
+    }));
+
+    let mut details = mon.types.join("/");
+    for category in &mon.categories_text {
+        details += &format!(" · {category}");
+    }
+    content.push(json!({ "tag": "div", "content": details }));
+
+    let summary = summary_text(&mon.summary_html);
+    if !summary.is_empty() {
+        content.push(json!({
+            "tag": "div",
+            "style": { "marginTop": "0.5em" },
+            "content": summary,
+        }));
+    }
+    content.push(json!({
+        "tag": "div",
+        "style": { "fontSize": "smaller" },
+        "content": { "tag": "a", "href": mon.url, "content": "Bulbapedia" },
+    }));
+
+    json!({ "type": "structured-content", "content": content })
+}
+
+/// The first paragraph of the summary as plain text, without citations.
+fn summary_text(html: &str) -> String {
+    let doc = kuchikiki::parse_html().one(html);
+    if let Ok(citations) = doc.select("sup.reference") {
+        for citation in citations.collect::<Vec<_>>() {
+            citation.as_node().detach();
+        }
+    }
+    let paragraph = doc
+        .select("p")
+        .ok()
+        .and_then(|paragraphs| {
+            paragraphs
+                .map(|p| p.text_contents())
+                .find(|text| !text.trim().is_empty())
+        })
+        .unwrap_or_else(|| doc.text_contents());
+    paragraph.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,199 @@
+//! DICT protocol sessions against a small hand-built dictd database.
+
+use pokedex_dictgen::dict_server::handle_client;
+use pokedex_dictgen::dictd::{encode_number, DictdDatabase, NAME};
+use pokedex_dictgen::dictzip::dictzip;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+fn write_database(name: &str, index: &str, data: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pokedex-{name}-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join(format!("{NAME}.index")), index).unwrap();
+    fs::write(
+        dir.join(format!("{NAME}.dict.dz")),
+        dictzip(data.as_bytes()).unwrap(),
+    )
+    .unwrap();
+    dir
+}
+
+fn database() -> &'static DictdDatabase {
+    static DB: OnceLock<DictdDatabase> = OnceLock::new();
+    DB.get_or_init(|| {
+        let entries: [(&[&str], &str); 4] = [
+            (&["00-database-short"], "00-database-short\n  Pokédex\n"),
+            (
+                &["Pikachu", "ピカチュウ"],
+                "Pikachu\n  Mouse Pokémon.\n.hidden line\n",
+            ),
+            (
+                &["Pichu"],
+                "Pichu\n  Tiny Mouse Pokémon. Evolves into {Pikachu}.\n",
+            ),
+            (&["Raichu"], "Raichu\n  Evolves from {Pikachu}.\n"),
+        ];
+        let mut data = String::new();
+        let mut index = String::new();
+        for (words, text) in entries {
+            for word in words {
+                index += &format!(
+                    "{word}\t{}\t{}\n",
+                    encode_number(data.len() as u64),
+                    encode_number(text.len() as u64)
+                );
+            }
+            data += text;
+        }
+
+        let dir = write_database("dict-server", &index, &data);
+        let db = DictdDatabase::open(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        db
+    })
+}
+
+/// Runs a session with the given command lines, returning the response lines after the banner.
+fn session(commands: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    handle_client(database(), 0, commands.as_bytes(), &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.ends_with("\r\n"));
+    let mut lines: Vec<_> = out.split("\r\n").map(str::to_string).collect();
+    lines.pop();
+    assert!(lines[0].starts_with("220 "), "{}", lines[0]);
+    lines.remove(0);
+    lines
+}
+
+#[test]
+fn define() {
+    assert_eq!(
+        session("DEFINE pokedex pikachu\nQUIT\n"),
+        [
+            "150 1 definitions retrieved",
+            "151 \"Pikachu\" pokedex \"Pokédex\"",
+            "Pikachu",
+            "  Mouse Pokémon.",
+            "..hidden line",
+            ".",
+            "250 ok",
+            "221 bye",
+        ]
+    );
+    assert_eq!(
+        session("DEFINE * ピカチュウ\n"),
+        [
+            "150 1 definitions retrieved",
+            "151 \"ピカチュウ\" pokedex \"Pokédex\"",
+            "Pikachu",
+            "  Mouse Pokémon.",
+            "..hidden line",
+            ".",
+            "250 ok",
+        ]
+    );
+}
+
+#[test]
+fn match_words() {
+    assert_eq!(
+        session("MATCH pokedex prefix pi\n"),
+        [
+            "152 2 matches found",
+            "pokedex \"Pichu\"",
+            "pokedex \"Pikachu\"",
+            ".",
+            "250 ok",
+        ]
+    );
+    assert_eq!(
+        session("MATCH pokedex exact RAICHU\n"),
+        ["152 1 matches found", "pokedex \"Raichu\"", ".", "250 ok"]
+    );
+    // Levenshtein distance one, which `.` defaults to
+    for strategy in ["lev", "."] {
+        assert_eq!(
+            session(&format!("MATCH ! {strategy} pichi\n")),
+            ["152 1 matches found", "pokedex \"Pichu\"", ".", "250 ok"]
+        );
+    }
+    // metadata headwords don't match
+    assert_eq!(session("MATCH pokedex prefix 00\n"), ["552 no match"]);
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        session(concat!(
+            "DEFINE other pikachu\n",
+            "MATCH pokedex soundex pikachu\n",
+            "DEFINE pokedex missingno\n",
+            "FROBNICATE\n",
+            "DEFINE pokedex\n",
+            "DEFINE pokedex \"pikachu\n",
+            "AUTH user secret\n",
+        )),
+        [
+            "550 invalid database, use \"SHOW DB\" for a list",
+            "551 invalid strategy, use \"SHOW STRAT\" for a list",
+            "552 no match",
+            "500 unknown command",
+            "501 syntax error, illegal parameters",
+            "501 syntax error, illegal parameters",
+            "502 command not implemented",
+        ]
+    );
+}
+
+#[test]
+fn mime() {
+    let header = "Content-Type: text/plain; charset=utf-8";
+    assert_eq!(
+        session(
+            "OPTION MIME\nSHOW DB\nSHOW STRAT\nMATCH pokedex exact raichu\nDEFINE pokedex raichu\n"
+        ),
+        [
+            "250 ok",
+            "110 1 databases present",
+            header,
+            "",
+            "pokedex \"Pokédex\"",
+            ".",
+            "250 ok",
+            "111 3 strategies available",
+            header,
+            "",
+            "exact \"Match headwords exactly\"",
+            "prefix \"Match prefixes\"",
+            "lev \"Match headwords within Levenshtein distance one\"",
+            ".",
+            "250 ok",
+            "152 1 matches found",
+            header,
+            "",
+            "pokedex \"Raichu\"",
+            ".",
+            "250 ok",
+            "150 1 definitions retrieved",
+            "151 \"Raichu\" pokedex \"Pokédex\"",
+            header,
+            "",
+            "Raichu",
+            "  Evolves from {Pikachu}.",
+            ".",
+            "250 ok",
+        ]
+    );
+}
+
+#[test]
+fn index_past_end() {
+    // the largest offset, which overflows when the length is added
+    let dir = write_database("dict-server-overflow", "x\tP//////////\tB\n", "x\n");
+    let result = DictdDatabase::open(&dir);
+    fs::remove_dir_all(&dir).unwrap();
+    let error = result.err().unwrap().to_string();
+    assert!(error.contains("past the end"), "{error}");
+}
@@ -0,0 +1,60 @@
+//! Generating `Dictionary.xml`.
+
+use pokedex_dictgen::rendered::split_entries;
+use pokedex_dictgen::{generate_dictionary, DexId, Entries, Index, TypeEntry, TypeId};
+use std::collections::BTreeMap;
+
+#[test]
+fn no_pokemon() {
+    // e.g. a selection that matched nothing, which main refuses, but the output should still be
+    // well-formed
+    let index = Index {
+        pokemon_gens: vec![vec![DexId(1)]],
+        ..Default::default()
+    };
+    let xml = generate_dictionary(&index, &Entries::default()).unwrap();
+    assert_eq!(
+        xml.matches("<d:entry ").count(),
+        xml.matches("</d:entry>").count()
+    );
+    assert_eq!(xml.matches("<ul").count(), xml.matches("</ul>").count());
+    assert!(xml.trim_end().ends_with("</d:dictionary>"));
+    let entries = split_entries(&xml).unwrap();
+    assert_eq!(entries.len(), 1, "only the front matter");
+    assert!(!entries[0].html.contains("list-of-pokemon-gen-"));
+}
+
+#[test]
+fn type_keys() {
+    let index = Index {
+        pokemon_gens: vec![vec![DexId(1)]],
+        ..Default::default()
+    };
+    let id = TypeId::from_name("Electric").unwrap();
+    let entries = Entries {
+        types: BTreeMap::from([(
+            id,
+            TypeEntry {
+                url: "https://bulbapedia.bulbagarden.net/wiki/Electric_(type)".to_string(),
+                id,
+                name: "Electric".to_string(),
+                name_jp_text: None,
+                name_jp_html: None,
+                summary_html: String::new(),
+                history: Vec::new(),
+                references: Vec::new(),
+            },
+        )]),
+        ..Default::default()
+    };
+    let xml = generate_dictionary(&index, &entries).unwrap();
+    let entries = split_entries(&xml).unwrap();
+    let entry = entries
+        .iter()
+        .find(|entry| entry.id == "type-electric")
+        .unwrap();
+    assert_eq!(entry.title, "Electric (type)");
+    let keys: Vec<_> = entry.keys.iter().map(|key| &*key.value).collect();
+    // the bare name too, as people search for it
+    assert_eq!(keys, ["Electric", "Electric type", "Electric-type"]);
+}
@@ -0,0 +1,182 @@
+//! JSON records against the schema they promise to follow.
+
+use pokedex_dictgen::evolution::Evolution;
+use pokedex_dictgen::forms::{FormAbility, MonForm};
+use pokedex_dictgen::index::RegionalDexId;
+use pokedex_dictgen::json::{Json, Record, SCHEMA, SCHEMA_FILE, SCHEMA_VERSION};
+use pokedex_dictgen::mon::MonImage;
+use pokedex_dictgen::page::Reference;
+use pokedex_dictgen::{AbilityId, Backend, DexId, Dictionary, Entries, Index, MonEntry};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+
+/// Validates against `#/$defs/record`, like a line of `pokedex.jsonl`.
+fn record_errors(record: &Value) -> Vec<String> {
+    let mut schema: Value = serde_json::from_str(SCHEMA).unwrap();
+    let root = schema.as_object_mut().unwrap();
+    for keyword in ["type", "required", "additionalProperties", "properties"] {
+        root.remove(keyword);
+    }
+    root.insert("$ref".to_string(), json!("#/$defs/record"));
+    let validator = jsonschema::validator_for(&schema).unwrap();
+    validator
+        .iter_errors(record)
+        .map(|error| format!("{} at {}", error, error.instance_path))
+        .collect()
+}
+
+fn pikachu() -> MonEntry {
+    MonEntry {
+        url: "https://bulbapedia.bulbagarden.net/wiki/Pikachu_(Pok%C3%A9mon)".to_string(),
+        dex_id: DexId(25),
+        regional_ids: vec![RegionalDexId {
+            dex: "Paldea".to_string(),
+            number: 74,
+        }],
+        name: "Pikachu".to_string(),
+        categories_text: vec!["Mouse Pokémon".to_string()],
+        categories_html: vec!["<span>Mouse</span> Pokémon".to_string()],
+        name_jp_text: "ピカチュウ".to_string(),
+        name_jp_html: "ピカチュウ".to_string(),
+        name_jp_translit_text: "Pikachu".to_string(),
+        name_jp_translit_html: "<i>Pikachu</i>".to_string(),
+        images: vec![MonImage {
+            href: "https://bulbapedia.bulbagarden.net/wiki/File:0025Pikachu.png".to_string(),
+            alt: "Pikachu".to_string(),
+            width: 250,
+            src: "images/0025Pikachu.png".to_string(),
+            caption_text: None,
+            caption_html: Some("<b>Pikachu</b>".to_string()),
+            flex: false,
+        }],
+        types: vec!["Electric".to_string()],
+        forms: vec![MonForm {
+            name: "Pikachu".to_string(),
+            image: Some(0),
+            types: vec!["Electric".to_string()],
+            abilities: vec![
+                FormAbility {
+                    name: "Static".to_string(),
+                    id: Some(AbilityId(9)),
+                    hidden: false,
+                },
+                FormAbility {
+                    name: "Lightning Rod".to_string(),
+                    id: None,
+                    hidden: true,
+                },
+            ],
+            stats: vec![("HP".to_string(), 35), ("Attack".to_string(), 55)],
+        }],
+        evolutions: vec![
+            Evolution {
+                from: DexId(172),
+                to: DexId(25),
+                method: "High Friendship".to_string(),
+            },
+            Evolution {
+                from: DexId(25),
+                to: DexId(26),
+                method: "Thunder Stone".to_string(),
+            },
+        ],
+        info_box_style: BTreeMap::from([("background".to_string(), "#F7D02C".to_string())]),
+        top_info_boxes_html: vec!["<div>Type</div>".to_string()],
+        extra_info_boxes_html: Vec::new(),
+        summary_html: "<p><b>Pikachu</b> is an Electric-type Pokémon.<sup><a href=\"#cite-1\">[1]</a></sup></p>".to_string(),
+        body_html: "<h2>Evolution</h2><p>Evolves from <a href=\"x-dictionary:r:pokemon-172\">Pichu</a>.</p>".to_string(),
+        references: vec![Reference {
+            id: "cite-1".to_string(),
+            backlink: "cite-ref-1".to_string(),
+            html: "Pokédex".to_string(),
+        }],
+        prev_entry: Some((DexId(24), "0024Arbok.png".to_string())),
+        next_entry: None,
+    }
+}
+
+#[test]
+fn pokemon_record() {
+    let mon = pikachu();
+    let record = serde_json::to_value(Record::Pokemon(&mon)).unwrap();
+    assert_eq!(record["kind"], "pokemon");
+    assert_eq!(record["data"]["dex_id"], "#0025");
+    assert_eq!(record_errors(&record), Vec::<String>::new());
+
+    // reading the data back gives the same record
+    let read: MonEntry = serde_json::from_value(record["data"].clone()).unwrap();
+    assert_eq!(
+        serde_json::to_value(Record::Pokemon(&read)).unwrap(),
+        record
+    );
+
+    // the schema does reject records that break it
+    let mut missing = record.clone();
+    missing["data"].as_object_mut().unwrap().remove("forms");
+    assert!(!record_errors(&missing).is_empty());
+    let mut bad_id = record.clone();
+    bad_id["data"]["dex_id"] = json!("25");
+    assert!(!record_errors(&bad_id).is_empty());
+    let mut bad_evolution = record.clone();
+    bad_evolution["data"]["evolutions"][0]["to"] = json!(25);
+    assert!(!record_errors(&bad_evolution).is_empty());
+    let mut wrong_kind = record;
+    wrong_kind["kind"] = json!("ability");
+    assert!(!record_errors(&wrong_kind).is_empty());
+}
+
+#[test]
+fn header_record() {
+    let record = serde_json::to_value(Record::Header {
+        schema: SCHEMA_FILE,
+        version: SCHEMA_VERSION,
+    })
+    .unwrap();
+    assert_eq!(
+        record,
+        json!({ "kind": "header", "data": { "$schema": SCHEMA_FILE, "version": SCHEMA_VERSION } })
+    );
+    assert_eq!(record_errors(&record), Vec::<String>::new());
+}
+
+#[test]
+fn links() {
+    let dir = std::env::temp_dir().join(format!("pokedex-json-{}", std::process::id()));
+    let image_dir = dir.join("images");
+    fs::create_dir_all(&image_dir).unwrap();
+    for file in ["0025Pikachu.png", "0024Arbok.png"] {
+        fs::write(image_dir.join(file), file).unwrap();
+    }
+
+    let mut mon = pikachu();
+    mon.summary_html = concat!(
+        r#"<p><a href="x-dictionary:r:pokemon-25#Biology">Pikachu</a> evolves from "#,
+        r#"<a href="x-dictionary:r:pokemon-172">Pichu</a>.</p>"#,
+    )
+    .to_string();
+    let entries = Entries {
+        pokemon: BTreeMap::from([(mon.dex_id, mon)]),
+        ..Default::default()
+    };
+    let dict = Dictionary {
+        index: &Index::default(),
+        entries: &entries,
+        image_dir: &image_dir,
+    };
+    let out_dir = dir.join("out");
+    Json.write(&dict, &out_dir).unwrap();
+
+    // Pichu wasn't selected, so its link goes, and the other one no longer needs Apple's scheme
+    let expected = r##"<p><a href="#pokemon-25">Pikachu</a> evolves from Pichu.</p>"##;
+    let document: Value =
+        serde_json::from_str(&fs::read_to_string(out_dir.join("pokedex.json")).unwrap()).unwrap();
+    assert_eq!(document["pokemon"][0]["summary_html"], expected);
+    let jsonl = fs::read_to_string(out_dir.join("pokedex.jsonl")).unwrap();
+    let record: Value = serde_json::from_str(jsonl.lines().nth(2).unwrap()).unwrap();
+    assert_eq!(record["data"]["summary_html"], expected);
+    assert!(!jsonl.contains("x-dictionary:"));
+    assert!(out_dir.join("images/0025Pikachu.png").exists());
+
+    fs::remove_dir_all(dir).unwrap();
+}
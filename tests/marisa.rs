@@ -0,0 +1,422 @@
+//! Reads tries back the way libmarisa does, using the rank and select indexes that were written.
+
+use pokedex_dictgen::marisa::build_trie;
+use std::collections::BTreeSet;
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> &'a [u8] {
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        bytes
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.take(4).try_into().unwrap())
+    }
+
+    fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.take(8).try_into().unwrap())
+    }
+
+    /// `marisa::grimoire::vector::Vector` of bytes.
+    fn vec(&mut self) -> &'a [u8] {
+        let len = self.u64() as usize;
+        let data = self.take(len);
+        assert!(self.take((8 - len % 8) % 8).iter().all(|b| *b == 0));
+        data
+    }
+
+    fn u32_vec(&mut self) -> Vec<u32> {
+        let data = self.vec();
+        assert_eq!(data.len() % 4, 0);
+        data.chunks(4)
+            .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+            .collect()
+    }
+
+    fn bit_vec(&mut self) -> BitVec {
+        let data = self.vec();
+        assert_eq!(data.len() % 8, 0);
+        let units = data
+            .chunks(8)
+            .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+            .collect();
+        let len = self.u32() as usize;
+        let num_1s = self.u32() as usize;
+        let ranks = self
+            .u32_vec()
+            .chunks(3)
+            .map(|c| {
+                let (lo, hi) = (c[1], c[2]);
+                [
+                    c[0],
+                    lo & 0x7f,
+                    (lo >> 7) & 0xff,
+                    (lo >> 15) & 0xff,
+                    lo >> 23,
+                    hi & 0x1ff,
+                    (hi >> 9) & 0x1ff,
+                    (hi >> 18) & 0x1ff,
+                ]
+            })
+            .collect();
+        let select0s = self.u32_vec();
+        let select1s = self.u32_vec();
+        BitVec {
+            units,
+            len,
+            num_1s,
+            ranks,
+            select0s,
+            select1s,
+        }
+    }
+
+    /// `marisa::grimoire::vector::FlatVector`.
+    fn flat_vec(&mut self) -> Vec<u32> {
+        let data = self.vec();
+        let units: Vec<u64> = data
+            .chunks(8)
+            .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+            .collect();
+        let value_size = self.u32() as usize;
+        let mask = self.u32();
+        let len = self.u64() as usize;
+        (0..len)
+            .map(|i| {
+                let pos = i * value_size;
+                let (unit_id, offset) = (pos / 64, pos % 64);
+                let mut value = units[unit_id] >> offset;
+                if offset + value_size > 64 {
+                    value |= units[unit_id + 1] << (64 - offset);
+                }
+                value as u32 & mask
+            })
+            .collect()
+    }
+}
+
+struct BitVec {
+    units: Vec<u64>,
+    len: usize,
+    num_1s: usize,
+    /// Absolute rank, then ranks of the other 7 units relative to it, per 512-bit block
+    ranks: Vec<[u32; 8]>,
+    select0s: Vec<u32>,
+    select1s: Vec<u32>,
+}
+
+impl BitVec {
+    fn get(&self, i: usize) -> bool {
+        assert!(i < self.len);
+        self.units[i / 64] >> (i % 64) & 1 == 1
+    }
+
+    /// Number of set bits before `i`, from the rank index.
+    fn rank1(&self, i: usize) -> usize {
+        let block = &self.ranks[i / 512];
+        let sub = i / 64 % 8;
+        let mut rank = block[0] as usize;
+        if sub != 0 {
+            rank += block[sub] as usize;
+        }
+        if !i.is_multiple_of(64) {
+            rank += (self.units[i / 64] << (64 - i % 64)).count_ones() as usize;
+        }
+        rank
+    }
+
+    /// Position of the `i`th unset bit, from the select index like libmarisa.
+    fn select0(&self, i: usize) -> usize {
+        self.select(
+            i,
+            &self.select0s,
+            |block| block * 512 - self.ranks[block][0] as usize,
+            |unit| !unit,
+            |bits, ones| bits - ones,
+        )
+    }
+
+    /// Position of the `i`th set bit, from the select index like libmarisa.
+    fn select1(&self, i: usize) -> usize {
+        self.select(
+            i,
+            &self.select1s,
+            |block| self.ranks[block][0] as usize,
+            |unit| unit,
+            |_, ones| ones,
+        )
+    }
+
+    fn select(
+        &self,
+        i: usize,
+        index: &[u32],
+        block_rank: impl Fn(usize) -> usize,
+        unit_bits: impl Fn(u64) -> u64,
+        sub_rank: impl Fn(usize, usize) -> usize,
+    ) -> usize {
+        let select_id = i / 512;
+        if i.is_multiple_of(512) {
+            return index[select_id] as usize;
+        }
+        // the block is between the ones of the surrounding indexed bits
+        let mut block = index[select_id] as usize / 512;
+        let end = (index[select_id + 1] as usize).div_ceil(512);
+        while block + 1 < end && block_rank(block + 1) <= i {
+            block += 1;
+        }
+        let mut rest = i - block_rank(block);
+        let mut unit_id = block * 8;
+        for sub in (1..8).rev() {
+            let count = sub_rank(sub * 64, self.ranks[block][sub] as usize);
+            if count <= rest {
+                unit_id = block * 8 + sub;
+                rest -= count;
+                break;
+            }
+        }
+        let mut unit = unit_bits(self.units[unit_id]);
+        for _ in 0..rest {
+            unit &= unit - 1;
+        }
+        unit_id * 64 + unit.trailing_zeros() as usize
+    }
+
+    /// Checks the indexes against counting bits one by one.
+    fn check(&self) {
+        let mut num_1s = 0;
+        for i in 0..self.len {
+            assert_eq!(self.rank1(i), num_1s, "rank1({i})");
+            if self.get(i) {
+                if !self.select1s.is_empty() {
+                    assert_eq!(self.select1(num_1s), i, "select1({num_1s})");
+                }
+                num_1s += 1;
+            } else if !self.select0s.is_empty() {
+                assert_eq!(self.select0(i - num_1s), i, "select0({})", i - num_1s);
+            }
+        }
+        assert_eq!(self.num_1s, num_1s);
+        assert_eq!(self.rank1(self.len), num_1s);
+        assert_eq!(self.ranks.len(), self.len.div_ceil(512) + 1);
+        if !self.select1s.is_empty() {
+            assert_eq!(self.select1s.len(), num_1s.div_ceil(512) + 1);
+        }
+        if !self.select0s.is_empty() {
+            assert_eq!(self.select0s.len(), (self.len - num_1s).div_ceil(512) + 1);
+        }
+    }
+}
+
+struct Trie<'a> {
+    louds: BitVec,
+    terminal_flags: BitVec,
+    link_flags: BitVec,
+    bases: &'a [u8],
+    extras: Vec<u32>,
+    tail: &'a [u8],
+    cache: Vec<(u32, u32, u32)>,
+    num_l1_nodes: usize,
+}
+
+impl<'a> Trie<'a> {
+    fn read(data: &'a [u8]) -> Self {
+        let mut r = Reader { data, pos: 0 };
+        assert_eq!(r.take(16), b"We love Marisa.\0");
+        let louds = r.bit_vec();
+        let terminal_flags = r.bit_vec();
+        let link_flags = r.bit_vec();
+        let bases = r.vec();
+        let extras = r.flat_vec();
+        let tail = r.vec();
+        let end_flags = r.bit_vec();
+        assert_eq!(end_flags.len, 0);
+        let cache = r.u32_vec().chunks(3).map(|c| (c[0], c[1], c[2])).collect();
+        let num_l1_nodes = r.u32() as usize;
+        let config = r.u32();
+        assert_eq!(r.pos, data.len());
+        assert_eq!(
+            config, 0x11201,
+            "one trie, normal cache, text tail, label order"
+        );
+
+        Self {
+            louds,
+            terminal_flags,
+            link_flags,
+            bases,
+            extras,
+            tail,
+            cache,
+            num_l1_nodes,
+        }
+    }
+
+    /// Index into the tail of a link node.
+    fn link(&self, node: usize) -> usize {
+        self.bases[node] as usize | (self.extras[self.link_flags.rank1(node)] as usize) << 8
+    }
+
+    /// The label of the edge into `node`.
+    fn label(&self, node: usize) -> Vec<u8> {
+        if self.link_flags.get(node) {
+            let tail = &self.tail[self.link(node)..];
+            tail[..tail.iter().position(|b| *b == 0).unwrap()].to_vec()
+        } else {
+            vec![self.bases[node]]
+        }
+    }
+
+    fn children(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        let first = self.louds.select0(node) + 1;
+        (first..)
+            .take_while(|&pos| self.louds.get(pos))
+            .map(move |pos| pos - node - 1)
+    }
+
+    fn parent(&self, node: usize) -> usize {
+        self.louds.select1(node) - node - 1
+    }
+
+    /// The key ID of `key`, like `marisa::Trie::lookup`.
+    fn lookup(&self, key: &[u8]) -> Option<usize> {
+        let mut node = 0;
+        let mut pos = 0;
+        while pos < key.len() {
+            node = self
+                .children(node)
+                .find(|&child| key[pos..].starts_with(&self.label(child)))?;
+            pos += self.label(node).len();
+        }
+        self.terminal_flags
+            .get(node)
+            .then(|| self.terminal_flags.rank1(node))
+    }
+
+    /// The key with ID `id`, like `marisa::Trie::reverse_lookup`.
+    fn reverse_lookup(&self, id: usize) -> Vec<u8> {
+        let mut node = self.terminal_flags.select1(id);
+        let mut key = Vec::new();
+        while node != 0 {
+            key.splice(0..0, self.label(node));
+            node = self.parent(node);
+        }
+        key
+    }
+
+    /// All keys, in order of their IDs.
+    fn keys(&self) -> Vec<Vec<u8>> {
+        (0..self.terminal_flags.num_1s)
+            .map(|id| self.reverse_lookup(id))
+            .collect()
+    }
+}
+
+/// Many keys with shared prefixes, long unique tails and non-ASCII characters.
+fn words() -> Vec<String> {
+    let mut words = Vec::new();
+    for i in 0..1500 {
+        words.push(format!("pokémon {i}"));
+        words.push(format!("word{}", i * 7919 % 2000));
+        words.push(format!("{i:04} evolves"));
+    }
+    for name in [
+        "pikachu",
+        "pichu",
+        "pidgey",
+        "pidgeotto",
+        "pidgeot",
+        "mr. mime",
+        "ニャース",
+    ] {
+        words.push(name.to_string());
+        words.push(format!("{name} with a long unique suffix"));
+    }
+    words.push("p".to_string());
+    words
+}
+
+#[test]
+fn read_back() {
+    let words = words();
+    let data = build_trie(&words).unwrap();
+    let trie = Trie::read(&data);
+
+    for bits in [&trie.louds, &trie.terminal_flags, &trie.link_flags] {
+        bits.check();
+    }
+    assert!(trie.louds.len > 4 * 512, "should span several index blocks");
+    assert!(trie.link_flags.num_1s > 512, "should have many link nodes");
+    assert_eq!(trie.extras.len(), trie.link_flags.num_1s);
+    assert_eq!(trie.bases.len() + 1, trie.terminal_flags.len);
+    assert_eq!(trie.children(0).count(), trie.num_l1_nodes);
+
+    let expected: BTreeSet<&[u8]> = words.iter().map(|word| word.as_bytes()).collect();
+    let keys = trie.keys();
+    assert_eq!(keys.len(), expected.len());
+    assert_eq!(
+        keys.iter().map(Vec::as_slice).collect::<BTreeSet<_>>(),
+        expected
+    );
+
+    for word in &expected {
+        let id = trie.lookup(word).unwrap();
+        assert_eq!(keys[id], *word);
+    }
+    for missing in [
+        "",
+        "pi",
+        "pokémon",
+        "pokémon 1500",
+        "word",
+        "pikachu!",
+        "ニャ",
+    ] {
+        assert_eq!(trie.lookup(missing.as_bytes()), None, "{missing}");
+    }
+}
+
+#[test]
+fn cache_points_to_children() {
+    let data = build_trie(&words()).unwrap();
+    let trie = Trie::read(&data);
+
+    assert!(trie.cache.len().is_power_of_two());
+    let mut used = 0;
+    for &(parent, child, link) in &trie.cache {
+        if parent == u32::MAX {
+            continue;
+        }
+        used += 1;
+        let (parent, child) = (parent as usize, child as usize);
+        assert_eq!(trie.parent(child), parent);
+        let extra = if trie.link_flags.get(child) {
+            (trie.link(child) >> 8) as u32
+        } else {
+            u32::MAX >> 8
+        };
+        assert_eq!(link, trie.bases[child] as u32 | extra << 8);
+    }
+    assert!(used > 0);
+}
+
+#[test]
+fn small_trie() {
+    let data = build_trie(&["b", "a", "a", "ab"]).unwrap();
+    let trie = Trie::read(&data);
+    for bits in [&trie.louds, &trie.terminal_flags, &trie.link_flags] {
+        bits.check();
+    }
+    let keys: BTreeSet<_> = trie.keys().into_iter().collect();
+    assert_eq!(
+        keys,
+        BTreeSet::from([b"a".to_vec(), b"ab".to_vec(), b"b".to_vec()])
+    );
+    assert!(build_trie(&[""]).is_err());
+}
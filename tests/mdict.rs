@@ -0,0 +1,242 @@
+//! Reads `.mdx` and `.mdd` files back to check that they contain what was written.
+
+use flate2::read::ZlibDecoder;
+use pokedex_dictgen::mdict::{write_mdd, write_mdx};
+use std::io::Read;
+
+/// A minimal MDict 2.0 reader: returns the header and all (key, record) pairs in file order.
+fn read_mdict(data: &[u8], utf16: bool) -> (String, Vec<(String, Vec<u8>)>) {
+    let mut r = Reader { data, pos: 0 };
+
+    let header_len = r.u32_be() as usize;
+    let header_bytes = r.take(header_len);
+    assert_eq!(
+        u32::from_le_bytes(r.take(4).try_into().unwrap()),
+        adler32(header_bytes)
+    );
+    let header = decode_utf16(header_bytes);
+
+    let key_header = &data[r.pos..r.pos + 40];
+    let num_key_blocks = r.u64_be();
+    let num_entries = r.u64_be();
+    let info_len = r.u64_be() as usize;
+    let info_compressed_len = r.u64_be() as usize;
+    let key_blocks_len = r.u64_be() as usize;
+    assert_eq!(r.u32_be(), adler32(key_header));
+
+    let info = decompress(r.take(info_compressed_len));
+    assert_eq!(info.len(), info_len);
+    let unit = if utf16 { 2 } else { 1 };
+    let mut info_r = Reader {
+        data: &info,
+        pos: 0,
+    };
+    let mut blocks = Vec::new();
+    for _ in 0..num_key_blocks {
+        let count = info_r.u64_be();
+        let mut bounds = Vec::new();
+        for _ in 0..2 {
+            let len = info_r.u16_be() as usize;
+            bounds.push(decode_key(info_r.take(len * unit), utf16));
+            assert!(info_r.take(unit).iter().all(|b| *b == 0));
+        }
+        let compressed_len = info_r.u64_be() as usize;
+        let len = info_r.u64_be() as usize;
+        blocks.push((count, bounds, compressed_len, len));
+    }
+    assert_eq!(info_r.pos, info.len());
+
+    let key_blocks_start = r.pos;
+    let mut keys = Vec::new();
+    for (count, bounds, compressed_len, len) in blocks {
+        let block = decompress(r.take(compressed_len));
+        assert_eq!(block.len(), len);
+        let mut block_r = Reader {
+            data: &block,
+            pos: 0,
+        };
+        let mut block_keys = Vec::new();
+        while block_r.pos < block.len() {
+            let offset = block_r.u64_be();
+            let mut key = Vec::new();
+            loop {
+                let c = block_r.take(unit);
+                if c.iter().all(|b| *b == 0) {
+                    break;
+                }
+                key.extend_from_slice(c);
+            }
+            block_keys.push((offset, decode_key(&key, utf16)));
+        }
+        assert_eq!(block_keys.len() as u64, count);
+        assert_eq!(block_keys.first().unwrap().1, bounds[0]);
+        assert_eq!(block_keys.last().unwrap().1, bounds[1]);
+        keys.extend(block_keys);
+    }
+    assert_eq!(r.pos - key_blocks_start, key_blocks_len);
+    assert_eq!(keys.len() as u64, num_entries);
+
+    let num_record_blocks = r.u64_be();
+    assert_eq!(r.u64_be(), num_entries);
+    assert_eq!(r.u64_be(), num_record_blocks * 16);
+    let record_blocks_len = r.u64_be() as usize;
+    let sizes: Vec<_> = (0..num_record_blocks)
+        .map(|_| (r.u64_be() as usize, r.u64_be() as usize))
+        .collect();
+    let mut records = Vec::new();
+    let records_start = r.pos;
+    for (compressed_len, len) in sizes {
+        let block = decompress(r.take(compressed_len));
+        assert_eq!(block.len(), len);
+        records.extend(block);
+    }
+    assert_eq!(r.pos - records_start, record_blocks_len);
+    assert_eq!(r.pos, data.len());
+
+    let mut entries = Vec::new();
+    for (i, (offset, key)) in keys.iter().enumerate() {
+        let end = keys.get(i + 1).map_or(records.len() as u64, |next| next.0);
+        entries.push((
+            key.clone(),
+            records[*offset as usize..end as usize].to_vec(),
+        ));
+    }
+    (header, entries)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> &'a [u8] {
+        let slice = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        slice
+    }
+
+    fn u16_be(&mut self) -> u16 {
+        u16::from_be_bytes(self.take(2).try_into().unwrap())
+    }
+
+    fn u32_be(&mut self) -> u32 {
+        u32::from_be_bytes(self.take(4).try_into().unwrap())
+    }
+
+    fn u64_be(&mut self) -> u64 {
+        u64::from_be_bytes(self.take(8).try_into().unwrap())
+    }
+}
+
+fn decompress(block: &[u8]) -> Vec<u8> {
+    assert_eq!(&block[..4], &[2, 0, 0, 0], "expected a zlib block");
+    let mut data = Vec::new();
+    ZlibDecoder::new(&block[8..])
+        .read_to_end(&mut data)
+        .unwrap();
+    assert_eq!(
+        u32::from_be_bytes(block[4..8].try_into().unwrap()),
+        adler32(&data)
+    );
+    data
+}
+
+fn decode_utf16(data: &[u8]) -> String {
+    let units: Vec<u16> = data
+        .chunks(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+    String::from_utf16(&units).unwrap()
+}
+
+fn decode_key(data: &[u8], utf16: bool) -> String {
+    if utf16 {
+        decode_utf16(data)
+    } else {
+        String::from_utf8(data.to_vec()).unwrap()
+    }
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    b << 16 | a
+}
+
+#[test]
+fn mdx_round_trip() {
+    let mut records = vec![
+        (
+            "Pikachu".to_string(),
+            r#"<a href="entry://Raichu">Raichu</a>"#.to_string(),
+        ),
+        ("ピカチュウ".to_string(), "@@@LINK=Pikachu".to_string()),
+        ("#25".to_string(), "@@@LINK=Pikachu".to_string()),
+        ("Mr. Mime".to_string(), "<p>Mime</p>".to_string()),
+    ];
+    // enough to need several blocks
+    for i in 0..5000 {
+        records.push((
+            format!("Entry {i}"),
+            format!("<p>{}</p>", "text ".repeat(i % 50)),
+        ));
+    }
+    records.push(("Pikachu".to_string(), "<p>duplicate key</p>".to_string()));
+
+    let mdx = write_mdx("Pokédex", "Test & more", records.clone()).unwrap();
+    let (header, entries) = read_mdict(&mdx, false);
+    assert!(header.starts_with("<Dictionary "));
+    assert!(header.contains(r#"Title="Pokédex""#));
+    assert!(header.contains(r#"Description="Test &amp; more""#));
+    assert!(header.contains(r#"Encoding="UTF-8""#));
+
+    let mut read: Vec<_> = entries
+        .into_iter()
+        .map(|(key, record)| {
+            let text = String::from_utf8(record).unwrap();
+            (key, text.strip_suffix('\0').unwrap().to_string())
+        })
+        .collect();
+    read.sort();
+    records.sort();
+    assert_eq!(read, records);
+}
+
+#[test]
+fn mdx_keys_are_sorted_ignoring_case_and_punctuation() {
+    let records = ["b", "Mr. Mime", "A", "mr mime", "#25", "Mime Jr."]
+        .iter()
+        .map(|key| (key.to_string(), String::new()))
+        .collect();
+    let mdx = write_mdx("Test", "", records).unwrap();
+    let (_, entries) = read_mdict(&mdx, false);
+    let keys: Vec<_> = entries.iter().map(|(key, _)| key.as_str()).collect();
+    assert_eq!(keys, ["#25", "A", "b", "Mime Jr.", "Mr. Mime", "mr mime"]);
+}
+
+#[test]
+fn mdd_round_trip() {
+    let mut files = vec![
+        ("\\pokedex.css".to_string(), b"h1 { color: red; }".to_vec()),
+        (
+            "\\images\\Pokémon.png".to_string(),
+            vec![0x89, b'P', b'N', b'G', 0, 0, 1],
+        ),
+    ];
+    for i in 0..200 {
+        files.push((format!("\\images\\{i}.png"), vec![i as u8; 1000 + i]));
+    }
+
+    let mdd = write_mdd(files.clone()).unwrap();
+    let (header, entries) = read_mdict(&mdd, true);
+    assert!(header.starts_with("<Library_Data "));
+
+    let mut read = entries;
+    read.sort();
+    files.sort();
+    assert_eq!(read, files);
+}
@@ -0,0 +1,193 @@
+//! Splitting `Dictionary.xml` into entries and rewriting their links and images.
+
+use pokedex_dictgen::rendered::{
+    disambiguate_titles, rewrite_images, rewrite_links, split_entries, strip_links,
+    strip_links_where,
+};
+
+const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<d:dictionary xmlns="http://www.w3.org/1999/xhtml" xmlns:d="http://www.apple.com/DTDs/DictionaryService-1.0.rng">
+<d:entry id="pokemon-29" d:title="Nidoran♀">
+<d:index d:value="Nidoran♀" />
+<d:index d:value="ニドラン♀" d:yomi="にどらん" />
+<d:index d:value="Poison Pin Pokémon" d:title="Nidoran♀ (Poison Pin Pokémon)" d:anchor="xpointer(//*[@id='pokemon-categories'])" />
+<h1>Nidoran♀</h1>
+<p>See <a href="x-dictionary:r:pokemon-32">Nidoran♂</a>.</p>
+</d:entry>
+<d:entry id="item-tom-&amp;-jerry" d:title="&quot;Tom&quot; &amp; &lt;Jerry&gt;">
+<d:index d:value="Tom &amp; Jerry" />
+<p>x</p>
+</d:entry>
+</d:dictionary>
+"#;
+
+#[test]
+fn split() {
+    let entries = split_entries(XML).unwrap();
+    assert_eq!(entries.len(), 2);
+
+    let nidoran = &entries[0];
+    assert_eq!(nidoran.id, "pokemon-29");
+    assert_eq!(nidoran.title, "Nidoran♀");
+    assert_eq!(
+        nidoran.html,
+        "<h1>Nidoran♀</h1>\n<p>See <a href=\"x-dictionary:r:pokemon-32\">Nidoran♂</a>.</p>"
+    );
+    assert_eq!(nidoran.keys.len(), 3);
+    assert_eq!(nidoran.keys[0].value, "Nidoran♀");
+    assert_eq!(nidoran.keys[0].title, None);
+    assert_eq!(nidoran.keys[1].yomi.as_deref(), Some("にどらん"));
+    assert_eq!(nidoran.keys[1].anchor, None);
+    let category = &nidoran.keys[2];
+    assert_eq!(category.value, "Poison Pin Pokémon");
+    assert_eq!(
+        category.title.as_deref(),
+        Some("Nidoran♀ (Poison Pin Pokémon)")
+    );
+    assert_eq!(category.anchor.as_deref(), Some("pokemon-categories"));
+    assert_eq!(
+        nidoran.words(),
+        ["Nidoran♀", "ニドラン♀", "Poison Pin Pokémon"]
+    );
+
+    let item = &entries[1];
+    assert_eq!(item.id, "item-tom-&-jerry");
+    assert_eq!(item.title, "\"Tom\" & <Jerry>");
+    assert_eq!(item.keys[0].value, "Tom & Jerry");
+    assert_eq!(item.html, "<p>x</p>");
+}
+
+#[test]
+fn split_errors() {
+    assert!(split_entries(r#"<d:entry d:title="x"><p>x</p></d:entry>"#).is_err());
+    assert!(split_entries(r#"<d:entry id="x"><p>x</p></d:entry>"#).is_err());
+    assert!(split_entries(r#"<d:entry id="x" d:title="x"><p>x</p>"#).is_err());
+    assert!(
+        split_entries(r#"<d:entry id="x" d:title="x"><d:index d:value="x"></d:entry>"#).is_err()
+    );
+    assert!(split_entries("<p>no entries</p>").unwrap().is_empty());
+}
+
+#[test]
+fn links() {
+    let html = concat!(
+        r#"<a href="x-dictionary:r:pokemon-25">Pikachu</a> "#,
+        r#"<a href="x-dictionary:r:move-1#Ruby_&amp;_Sapphire">Pound</a> "#,
+        r#"<a href="https://example.com/">elsewhere</a> "#,
+        r#"<a href="x-dictionary:r:item-&quot;odd&quot;">odd</a>"#,
+    );
+    let mut targets = Vec::new();
+    let out = rewrite_links(html, |entry, fragment| {
+        targets.push((entry.to_string(), fragment.map(str::to_string)));
+        format!("entry://{}", entry.len())
+    });
+    assert_eq!(
+        targets,
+        [
+            ("pokemon-25".to_string(), None),
+            ("move-1".to_string(), Some("Ruby_&_Sapphire".to_string())),
+            ("item-\"odd\"".to_string(), None),
+        ]
+    );
+    assert_eq!(
+        out,
+        concat!(
+            r#"<a href="entry://10">Pikachu</a> "#,
+            r#"<a href="entry://6">Pound</a> "#,
+            r#"<a href="https://example.com/">elsewhere</a> "#,
+            r#"<a href="entry://10">odd</a>"#,
+        )
+    );
+
+    // an unterminated value is kept as is
+    let broken = r#"<a href="x-dictionary:r:pokemon-25>"#;
+    assert_eq!(rewrite_links(broken, |_, _| unreachable!()), broken);
+}
+
+#[test]
+fn images() {
+    let html = concat!(
+        r#"<img src="images/Pikachu.png-0-0d" alt="Pikachu"> "#,
+        r#"<img src="images/Flab%C3%A9b%C3%A9.png" alt=""> "#,
+        r#"<img src="images/Tom%20&amp;%20Jerry.png"> "#,
+        r#"<img src="https://example.com/x.png">"#,
+    );
+    let mut files = Vec::new();
+    let out = rewrite_images(html, |file| {
+        files.push(file.to_string());
+        format!("res/{}", files.len())
+    });
+    assert_eq!(
+        files,
+        ["Pikachu.png-0-0d", "Flabébé.png", "Tom & Jerry.png"]
+    );
+    assert_eq!(
+        out,
+        concat!(
+            r#"<img src="res/1" alt="Pikachu"> "#,
+            r#"<img src="res/2" alt=""> "#,
+            r#"<img src="res/3"> "#,
+            r#"<img src="https://example.com/x.png">"#,
+        )
+    );
+}
+
+#[test]
+fn strip() {
+    let html = concat!(
+        r#"<p><a href="x-dictionary:r:pokemon-25" class="entry-name"><b>Pikachu</b></a>, "#,
+        r#"<a href="https://example.com/">kept</a> and "#,
+        r#"<a href="x-dictionary:r:move-1#x">Pound</a>.</p>"#,
+    );
+    assert_eq!(
+        strip_links(html),
+        r#"<p><b>Pikachu</b>, <a href="https://example.com/">kept</a> and Pound.</p>"#
+    );
+    assert_eq!(strip_links("<p>no links</p>"), "<p>no links</p>");
+    assert_eq!(
+        strip_links_where(html, |entry| entry == "move-1"),
+        concat!(
+            r#"<p><a href="x-dictionary:r:pokemon-25" class="entry-name"><b>Pikachu</b></a>, "#,
+            r#"<a href="https://example.com/">kept</a> and Pound.</p>"#,
+        )
+    );
+}
+
+#[test]
+fn unique_titles() {
+    let xml = concat!(
+        r#"<d:entry id="move-118" d:title="Metronome"><d:index d:value="Metronome" /></d:entry>"#,
+        r#"<d:entry id="item-metronome" d:title="Metronome"><d:index d:value="Metronome" />"#,
+        r#"<d:index d:value="メトロノーム" /></d:entry>"#,
+        r#"<d:entry id="pokemon-143" d:title="Snorlax"></d:entry>"#,
+        r#"<d:entry id="ability-1" d:title="Psychic"></d:entry>"#,
+        r#"<d:entry id="move-94" d:title="Psychic"></d:entry>"#,
+        r#"<d:entry id="type-psychic" d:title="psychic"></d:entry>"#,
+        r#"<d:entry id="pokemon-1" d:title="Twin"></d:entry>"#,
+        r#"<d:entry id="pokemon-2" d:title="Twin"></d:entry>"#,
+    );
+    let mut entries = split_entries(xml).unwrap();
+    disambiguate_titles(&mut entries);
+
+    let titles: Vec<_> = entries.iter().map(|entry| entry.title.as_str()).collect();
+    assert_eq!(
+        titles,
+        [
+            "Metronome (move)",
+            "Metronome (item)",
+            "Snorlax",
+            "Psychic (ability)",
+            "Psychic (move)",
+            "psychic (type)",
+            "Twin",
+            "Twin (2)",
+        ]
+    );
+    // the old title is still a key
+    assert_eq!(
+        entries[1].words(),
+        ["Metronome (item)", "Metronome", "メトロノーム"]
+    );
+    assert_eq!(entries[3].words(), ["Psychic (ability)", "Psychic"]);
+    assert_eq!(entries[2].words(), ["Snorlax"]);
+}